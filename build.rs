@@ -3,8 +3,15 @@
 //! This script:
 //! 1. Probes for libibverbs using pkg-config
 //! 2. Generates FFI bindings using bindgen
-//! 3. Applies custom type replacements (FwVer, Guid, WRID)
+//! 3. Applies custom type replacements (FwVer, Guid, WRID, capability flags,
+//!    port state and MTU)
 //! 4. Derives serialization traits for select types
+//!
+//! With the `extra-allowlist` feature, the comma-separated symbols in
+//! `RUAPC_RDMA_EXTRA_ALLOWLIST` are appended to the allowlist.
+//!
+//! With the `rdmacm` feature, librdmacm is probed as well and the
+//! connection manager functions from `rdma/rdma_cma.h` are bound.
 
 use std::collections::HashSet;
 use std::env;
@@ -23,7 +30,7 @@ impl ParseCallbacks for CustomDerive {
     fn add_derives(&self, info: &DeriveInfo<'_>) -> Vec<String> {
         match info.name {
             "ibv_device_attr" | "ibv_atomic_cap" | "ibv_port_state" | "ibv_mtu"
-            | "ibv_port_cap_flags" | "ibv_port_attr" => {
+            | "ibv_port_attr" => {
                 vec![
                     "Serialize".to_string(),
                     "Deserialize".to_string(),
@@ -40,8 +47,11 @@ impl ParseCallbacks for CustomDerive {
 /// This function post-processes the bindgen output to:
 /// - Replace `fw_ver` field type with `FwVer` wrapper
 /// - Replace `node_guid` and `sys_image_guid` field types with `Guid` wrapper
-/// - Replace `wr_id` field type with `WRID` wrapper
+/// - Replace `device_cap_flags` field type with the `ibv_device_cap_flags` bitfield
+/// - Replace `wr_id` field type with `WRID` wrapper (work requests and completions)
 /// - Replace `link_layer` field type with `LinkLayer` wrapper
+/// - Replace port `state` with `PortState` and `max_mtu`/`active_mtu` with `Mtu`
+/// - Replace `port_cap_flags` field type with the `ibv_port_cap_flags` bitfield
 ///
 /// These wrappers provide safer, more idiomatic Rust interfaces
 fn replace_custom_types(input: &str) -> String {
@@ -63,6 +73,10 @@ fn replace_custom_types(input: &str) -> String {
                                         field.ty = syn::parse_str("Guid")
                                             .expect("Failed to parse Guid type");
                                     }
+                                    "device_cap_flags" => {
+                                        field.ty = syn::parse_str("ibv_device_cap_flags")
+                                            .expect("Failed to parse ibv_device_cap_flags type");
+                                    }
                                     _ => {}
                                 }
                             }
@@ -72,16 +86,31 @@ fn replace_custom_types(input: &str) -> String {
                 "ibv_port_attr" => {
                     if let syn::Fields::Named(ref mut fields) = struct_item.fields {
                         for field in fields.named.iter_mut() {
-                            if let Some(ident) = &field.ident
-                                && ident == "link_layer"
-                            {
-                                field.ty = syn::parse_str("LinkLayer")
-                                    .expect("Failed to parse LinkLayer type");
+                            if let Some(ident) = &field.ident {
+                                match ident.to_string().as_str() {
+                                    "link_layer" => {
+                                        field.ty = syn::parse_str("LinkLayer")
+                                            .expect("Failed to parse LinkLayer type");
+                                    }
+                                    "state" => {
+                                        field.ty = syn::parse_str("PortState")
+                                            .expect("Failed to parse PortState type");
+                                    }
+                                    "max_mtu" | "active_mtu" => {
+                                        field.ty = syn::parse_str("Mtu")
+                                            .expect("Failed to parse Mtu type");
+                                    }
+                                    "port_cap_flags" => {
+                                        field.ty = syn::parse_str("ibv_port_cap_flags")
+                                            .expect("Failed to parse ibv_port_cap_flags type");
+                                    }
+                                    _ => {}
+                                }
                             }
                         }
                     }
                 }
-                "ibv_wc" | "ibv_send_wr" | "ibv_recv_wr" => {
+                "ibv_wc" | "ibv_send_wr" | "ibv_recv_wr" | "ibv_cq_ex" => {
                     if let syn::Fields::Named(ref mut fields) = struct_item.fields {
                         for field in fields.named.iter_mut() {
                             if let Some(ident) = &field.ident
@@ -101,7 +130,28 @@ fn replace_custom_types(input: &str) -> String {
     prettyplease::unparse(&ast)
 }
 
-fn main() {
+/// Returns the extra symbols to allowlist, if the `extra-allowlist` feature is on
+///
+/// Reads `RUAPC_RDMA_EXTRA_ALLOWLIST` as a comma-separated list of function or
+/// type names (e.g. `ibv_resize_cq,ibv_srq_init_attr`). Extra symbols are bound
+/// as bindgen emits them, without the custom derives or type replacements.
+fn extra_allowlist() -> Vec<String> {
+    const VAR: &str = "RUAPC_RDMA_EXTRA_ALLOWLIST";
+    println!("cargo:rerun-if-env-changed={VAR}");
+    if env::var_os("CARGO_FEATURE_EXTRA_ALLOWLIST").is_none() {
+        return vec![];
+    }
+    env::var(VAR)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Probes the system libibverbs and returns its include paths
+fn system_include_paths() -> HashSet<PathBuf> {
     // Probe for libibverbs installation
     let lib = pkg_config::Config::new()
         .statik(false)
@@ -111,11 +161,39 @@ fn main() {
     // Collect include paths from pkg-config and add /usr/include as fallback
     let mut include_paths = lib.include_paths.into_iter().collect::<HashSet<_>>();
     include_paths.insert(PathBuf::from("/usr/include"));
+    include_paths
+}
+
+/// Probes the system librdmacm and returns its include paths
+fn rdmacm_include_paths() -> HashSet<PathBuf> {
+    let lib = pkg_config::Config::new()
+        .statik(false)
+        .probe("librdmacm")
+        .unwrap_or_else(|_| panic!("rdmacm: please install librdmacm-dev and pkg-config"));
+    lib.include_paths.into_iter().collect()
+}
+
+fn main() {
+    let rdmacm = env::var_os("CARGO_FEATURE_RDMACM").is_some();
+    let include_paths = system_include_paths();
+
+    // librdmacm paths come last so the libibverbs headers take precedence
+    let rdmacm_paths = if rdmacm {
+        rdmacm_include_paths()
+    } else {
+        HashSet::new()
+    };
+    let header = if rdmacm {
+        "#include <infiniband/verbs.h>\n#include <rdma/rdma_cma.h>"
+    } else {
+        "#include <infiniband/verbs.h>"
+    };
 
     // Configure bindgen to generate RDMA verb bindings
-    let builder = bindgen::Builder::default()
+    let mut builder = bindgen::Builder::default()
         .clang_args(include_paths.iter().map(|p| format!("-I{p:?}")))
-        .header_contents("header.h", "#include <infiniband/verbs.h>")
+        .clang_args(rdmacm_paths.iter().map(|p| format!("-I{p:?}")))
+        .header_contents("header.h", header)
         // Enable common derives for generated types
         .derive_copy(true)
         .derive_debug(true)
@@ -142,16 +220,27 @@ fn main() {
         .allowlist_type("ibv_mr")
         .allowlist_type("ibv_pd")
         .allowlist_type("ibv_port_attr")
+        .allowlist_type("ibv_port_cap_flags")
         .allowlist_type("ibv_qp")
         .allowlist_type("ibv_qp_attr_mask")
         .allowlist_type("ibv_qp_init_attr")
         .allowlist_type("ibv_send_flags")
+        .allowlist_type("ibv_sge")
         .allowlist_type("ibv_wc")
         .allowlist_type("ibv_wc_flags")
         .allowlist_type("ibv_wc_status")
         .allowlist_type("ibv_atomic_cap")
         .allowlist_type("ibv_device_attr")
         .allowlist_type("ibv_device_cap_flags")
+        // Extended verbs; the functions are static inline and live in src/ffi
+        .allowlist_type("verbs_context")
+        .allowlist_type("ibv_cq_ex")
+        .allowlist_type("ibv_cq_init_attr_ex")
+        .allowlist_type("ibv_poll_cq_attr")
+        .allowlist_type("ibv_create_cq_wc_flags")
+        .allowlist_type("ibv_device_attr_ex")
+        .allowlist_type("ibv_query_device_ex_input")
+        .allowlist_type("ibv_odp_general_caps")
         .allowlist_function("ibv_ack_cq_events")
         .allowlist_function("ibv_alloc_pd")
         .allowlist_function("ibv_close_device")
@@ -168,28 +257,70 @@ fn main() {
         .allowlist_function("ibv_get_device_guid")
         .allowlist_function("ibv_get_device_list")
         .allowlist_function("ibv_modify_qp")
+        .allowlist_function("ibv_modify_qp_rate_limit")
         .allowlist_function("ibv_req_notify_cq")
         .allowlist_function("ibv_poll_cq")
         .allowlist_function("ibv_post_recv")
         .allowlist_function("ibv_post_send")
         .allowlist_function("ibv_query_device")
         .allowlist_function("ibv_query_gid")
+        .allowlist_function("ibv_query_pkey")
         .allowlist_function("ibv_query_port")
+        .allowlist_function("ibv_query_qp")
         .allowlist_function("ibv_open_device")
         .allowlist_function("ibv_reg_mr")
+        .allowlist_function("ibv_reg_mr_iova2")
         .bitfield_enum("ibv_access_flags")
         .bitfield_enum("ibv_send_flags")
         .bitfield_enum("ibv_wc_flags")
         .bitfield_enum("ibv_qp_attr_mask")
         .bitfield_enum("ibv_device_cap_flags")
+        .bitfield_enum("ibv_port_cap_flags")
+        .bitfield_enum("ibv_create_cq_wc_flags")
         .parse_callbacks(Box::new(CustomDerive))
         // Types with function pointers shouldn't implement Copy
         .no_copy("ibv_context")
         .no_copy("ibv_cq")
+        .no_copy("ibv_cq_ex")
+        .no_copy("verbs_context")
         .no_copy("ibv_qp")
         .no_copy("ibv_srq")
         .no_debug("ibv_device");
 
+    // Connection manager; only the calls wrapped by `CmId` and friends
+    if rdmacm {
+        builder = builder
+            .allowlist_type("rdma_cm_event")
+            .allowlist_type("rdma_cm_event_type")
+            .allowlist_type("rdma_cm_id")
+            .allowlist_type("rdma_conn_param")
+            .allowlist_type("rdma_event_channel")
+            .allowlist_type("rdma_port_space")
+            .allowlist_function("rdma_accept")
+            .allowlist_function("rdma_ack_cm_event")
+            .allowlist_function("rdma_bind_addr")
+            .allowlist_function("rdma_connect")
+            .allowlist_function("rdma_create_event_channel")
+            .allowlist_function("rdma_create_id")
+            .allowlist_function("rdma_create_qp")
+            .allowlist_function("rdma_destroy_event_channel")
+            .allowlist_function("rdma_destroy_id")
+            .allowlist_function("rdma_destroy_qp")
+            .allowlist_function("rdma_disconnect")
+            .allowlist_function("rdma_event_str")
+            .allowlist_function("rdma_get_cm_event")
+            .allowlist_function("rdma_listen")
+            .allowlist_function("rdma_reject")
+            .allowlist_function("rdma_resolve_addr")
+            .allowlist_function("rdma_resolve_route")
+            .no_copy("rdma_cm_id");
+    }
+
+    // Downstream additions; allowlist_item matches functions, types and vars
+    for name in extra_allowlist() {
+        builder = builder.allowlist_item(name);
+    }
+
     // Generate the FFI bindings
     let bindings = builder.generate().expect("Unable to generate bindings");
 
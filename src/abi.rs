@@ -0,0 +1,134 @@
+//! # ABI layout verification
+//!
+//! The build disables bindgen's layout tests, so a binding generated from
+//! headers that don't match the linked libibverbs goes unnoticed until it
+//! corrupts memory. [`verify_abi`] compares the size, alignment and field
+//! offsets of the structs passed across the data path with the libibverbs
+//! ABI on 64-bit Linux, so an application can fail fast at startup.
+
+use std::mem::{align_of, offset_of, size_of};
+
+use crate::{
+    Error, ErrorKind, Result, ibv_qp_init_attr, ibv_recv_wr, ibv_send_wr, ibv_sge, ibv_wc,
+};
+
+/// Compares one struct's layout with the expected values.
+macro_rules! check_layout {
+    ($mismatches:ident, $ty:ident, size = $size:expr, align = $align:expr, {
+        $($field:ident: $offset:expr),* $(,)?
+    }) => {
+        check(&mut $mismatches, concat!("size_of::<", stringify!($ty), ">"), size_of::<$ty>(), $size);
+        check(&mut $mismatches, concat!("align_of::<", stringify!($ty), ">"), align_of::<$ty>(), $align);
+        $(
+            check(
+                &mut $mismatches,
+                concat!("offset_of!(", stringify!($ty), ", ", stringify!($field), ")"),
+                offset_of!($ty, $field),
+                $offset,
+            );
+        )*
+    };
+}
+
+fn check(mismatches: &mut Vec<String>, what: &str, actual: usize, expected: usize) {
+    if actual != expected {
+        mismatches.push(format!("{what} is {actual}, expected {expected}"));
+    }
+}
+
+/// Verifies the layout of `ibv_wc`, `ibv_send_wr`, `ibv_recv_wr`, `ibv_sge`
+/// and `ibv_qp_init_attr` against the libibverbs ABI.
+///
+/// # Errors
+///
+/// Returns [`ErrorKind::AbiMismatch`] listing every size, alignment or
+/// offset that differs, or if the target is not 64-bit, for which no
+/// reference layout is known.
+///
+/// # Examples
+///
+/// ```rust
+/// ruapc_rdma_sys::verify_abi().expect("libibverbs ABI mismatch");
+/// ```
+pub fn verify_abi() -> Result<()> {
+    if cfg!(not(target_pointer_width = "64")) {
+        return Err(Error::new(
+            ErrorKind::AbiMismatch,
+            "no reference layout for this target".to_string(),
+        ));
+    }
+
+    let mut mismatches = Vec::new();
+    check_layout!(mismatches, ibv_wc, size = 48, align = 8, {
+        wr_id: 0,
+        status: 8,
+        opcode: 12,
+        vendor_err: 16,
+        byte_len: 20,
+        __bindgen_anon_1: 24,
+        qp_num: 28,
+        src_qp: 32,
+        wc_flags: 36,
+        pkey_index: 40,
+        slid: 42,
+        sl: 44,
+        dlid_path_bits: 45,
+    });
+    check_layout!(mismatches, ibv_send_wr, size = 128, align = 8, {
+        wr_id: 0,
+        next: 8,
+        sg_list: 16,
+        num_sge: 24,
+        opcode: 28,
+        send_flags: 32,
+        __bindgen_anon_1: 36,
+        wr: 40,
+        qp_type: 72,
+        __bindgen_anon_2: 80,
+    });
+    check_layout!(mismatches, ibv_recv_wr, size = 32, align = 8, {
+        wr_id: 0,
+        next: 8,
+        sg_list: 16,
+        num_sge: 24,
+    });
+    check_layout!(mismatches, ibv_sge, size = 16, align = 8, {
+        addr: 0,
+        length: 8,
+        lkey: 12,
+    });
+    check_layout!(mismatches, ibv_qp_init_attr, size = 64, align = 8, {
+        qp_context: 0,
+        send_cq: 8,
+        recv_cq: 16,
+        srq: 24,
+        cap: 32,
+        qp_type: 52,
+        sq_sig_all: 56,
+    });
+
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::new(ErrorKind::AbiMismatch, mismatches.join("; ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_abi() {
+        verify_abi().unwrap();
+    }
+
+    #[test]
+    fn test_check_reports_mismatch() {
+        let mut mismatches = Vec::new();
+        check(&mut mismatches, "size_of::<ibv_wc>", 48, 48);
+        assert!(mismatches.is_empty());
+        check(&mut mismatches, "size_of::<ibv_wc>", 40, 48);
+        assert_eq!(mismatches, ["size_of::<ibv_wc> is 40, expected 48"]);
+    }
+}
@@ -0,0 +1,74 @@
+//! # Cleanup error reporting
+//!
+//! Drop implementations cannot return errors, so failures of the verbs
+//! destroy calls are passed to a global hook installed with
+//! [`set_cleanup_error_hook`]. By default they are ignored.
+
+use std::sync::RwLock;
+
+/// Hook called with the failed operation name and errno.
+pub type CleanupErrorHook = Box<dyn Fn(&str, i32) + Send + Sync>;
+
+static CLEANUP_ERROR_HOOK: RwLock<Option<CleanupErrorHook>> = RwLock::new(None);
+
+/// Installs the hook invoked when a resource fails to clean up on drop.
+///
+/// The hook receives the verbs call that failed, e.g. `"ibv_dealloc_pd"`,
+/// and its errno. It replaces any previously installed hook. It runs inside
+/// `Drop`, so it must not panic.
+///
+/// # Examples
+///
+/// ```rust
+/// ruapc_rdma_sys::set_cleanup_error_hook(Box::new(|op, errno| {
+///     eprintln!("{op} failed: {}", std::io::Error::from_raw_os_error(errno));
+/// }));
+/// ```
+pub fn set_cleanup_error_hook(hook: CleanupErrorHook) {
+    *CLEANUP_ERROR_HOOK
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(hook);
+}
+
+/// Reports the result of a destroy call made during drop.
+///
+/// Verbs return the errno directly, or -1 with `errno` set.
+pub(crate) fn check_cleanup(op: &str, ret: libc::c_int) {
+    if ret == 0 {
+        return;
+    }
+    let errno = if ret > 0 {
+        ret
+    } else {
+        std::io::Error::last_os_error()
+            .raw_os_error()
+            .unwrap_or(-ret)
+    };
+    let hook = CLEANUP_ERROR_HOOK
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(hook) = hook.as_ref() {
+        hook(op, errno);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn cleanup_error_hook() {
+        static REPORTED: Mutex<Vec<(String, i32)>> = Mutex::new(Vec::new());
+        set_cleanup_error_hook(Box::new(|op, errno| {
+            REPORTED.lock().unwrap().push((op.to_string(), errno));
+        }));
+
+        check_cleanup("ibv_close_device", 0);
+        check_cleanup("ibv_dealloc_pd", libc::EBUSY);
+        assert_eq!(
+            *REPORTED.lock().unwrap(),
+            [("ibv_dealloc_pd".to_string(), libc::EBUSY)]
+        );
+    }
+}
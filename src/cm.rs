@@ -0,0 +1,475 @@
+//! # RDMA connection manager
+//!
+//! Minimal wrappers over librdmacm (`rdmacm` feature). [`CmEventChannel`]
+//! delivers connection manager events, and [`CmId`] resolves addresses and
+//! routes and connects, much like a socket.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use ruapc_rdma_sys::{CmEventChannel, CmId, rdma_cm_event_type, rdma_port_space};
+//! use std::{sync::Arc, time::Duration};
+//!
+//! let channel = Arc::new(CmEventChannel::new()?);
+//! let id = CmId::new(&channel, rdma_port_space::RDMA_PS_TCP)?;
+//! id.resolve_addr(None, "192.168.1.10:7471".parse()?, Duration::from_secs(2))?;
+//! channel.expect_event(rdma_cm_event_type::RDMA_CM_EVENT_ADDR_RESOLVED)?;
+//! id.resolve_route(Duration::from_secs(2))?;
+//! channel.expect_event(rdma_cm_event_type::RDMA_CM_EVENT_ROUTE_RESOLVED)?;
+//! // The id is now bound to a device: id.context_ptr(), id.port_num()
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{
+    ffi::CStr,
+    net::SocketAddr,
+    os::{fd::RawFd, raw::c_int},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{Error, ErrorKind, Result, rdma_cm_event_type, rdma_conn_param, rdma_port_space};
+
+/// Returns the current errno as an [`ErrorKind::RdmaCmFailed`] error.
+///
+/// librdmacm calls return -1 and set `errno` on failure.
+fn cm_error(op: &str) -> Error {
+    let err = ErrorKind::RdmaCmFailed.with_errno();
+    Error {
+        msg: format!("{op}: {}", err.msg),
+        ..err
+    }
+}
+
+/// Converts a timeout to the milliseconds librdmacm expects.
+fn timeout_ms(timeout: Duration) -> c_int {
+    timeout.as_millis().try_into().unwrap_or(c_int::MAX)
+}
+
+/// Encodes `addr` as a `sockaddr_in` or `sockaddr_in6`.
+fn to_sockaddr(addr: &SocketAddr) -> libc::sockaddr_storage {
+    let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+    match addr {
+        SocketAddr::V4(v4) => {
+            let sin = &mut storage as *mut _ as *mut libc::sockaddr_in;
+            unsafe {
+                (*sin).sin_family = libc::AF_INET as libc::sa_family_t;
+                (*sin).sin_port = v4.port().to_be();
+                (*sin).sin_addr.s_addr = u32::from(*v4.ip()).to_be();
+            }
+        }
+        SocketAddr::V6(v6) => {
+            let sin6 = &mut storage as *mut _ as *mut libc::sockaddr_in6;
+            unsafe {
+                (*sin6).sin6_family = libc::AF_INET6 as libc::sa_family_t;
+                (*sin6).sin6_port = v6.port().to_be();
+                (*sin6).sin6_flowinfo = v6.flowinfo().to_be();
+                (*sin6).sin6_addr.s6_addr = v6.ip().octets();
+                (*sin6).sin6_scope_id = v6.scope_id();
+            }
+        }
+    }
+    storage
+}
+
+/// Connection manager event channel with automatic cleanup.
+///
+/// Every [`CmId`] created on the channel holds a reference to it, so the
+/// channel outlives its ids.
+pub struct CmEventChannel {
+    channel: *mut crate::rdma_event_channel,
+}
+
+unsafe impl Send for CmEventChannel {}
+unsafe impl Sync for CmEventChannel {}
+
+impl CmEventChannel {
+    /// Creates an event channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::RdmaCmFailed`] if `rdma_create_event_channel`
+    /// fails, e.g. when the `rdma_cm` kernel module is not loaded.
+    pub fn new() -> Result<Self> {
+        let channel = unsafe { crate::rdma_create_event_channel() };
+        if channel.is_null() {
+            return Err(cm_error("rdma_create_event_channel"));
+        }
+        Ok(Self { channel })
+    }
+
+    /// Returns the file descriptor of the channel.
+    pub fn fd(&self) -> RawFd {
+        unsafe { (*self.channel).fd }
+    }
+
+    /// Blocks until the next event arrives and returns it.
+    ///
+    /// The event is acknowledged when the returned [`CmEvent`] is dropped;
+    /// a [`CmId`] cannot be destroyed while it has unacknowledged events.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::RdmaCmFailed`] if `rdma_get_cm_event` fails.
+    pub fn get_event(&self) -> Result<CmEvent<'_>> {
+        let mut event = std::ptr::null_mut();
+        let ret = unsafe { crate::rdma_get_cm_event(self.channel, &mut event) };
+        if ret != 0 {
+            return Err(cm_error("rdma_get_cm_event"));
+        }
+        Ok(CmEvent {
+            event,
+            _channel: std::marker::PhantomData,
+        })
+    }
+
+    /// Waits for the next event and checks it is of type `expected`.
+    ///
+    /// Suits the sequential client flow, where each asynchronous call on a
+    /// [`CmId`] completes with exactly one event.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::RdmaCmFailed`] if no event can be read, if the
+    /// event has another type, or if it carries a non-zero status.
+    pub fn expect_event(&self, expected: rdma_cm_event_type) -> Result<()> {
+        let event = self.get_event()?;
+        if event.event_type() != expected || event.status() != 0 {
+            return Err(Error::new(
+                ErrorKind::RdmaCmFailed,
+                format!(
+                    "expected {}, got {} (status {})",
+                    event_str(expected),
+                    event_str(event.event_type()),
+                    event.status()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the raw event channel pointer.
+    ///
+    /// # Safety
+    ///
+    /// The returned pointer is only valid as long as this channel exists.
+    pub unsafe fn channel_ptr(&self) -> *mut crate::rdma_event_channel {
+        self.channel
+    }
+}
+
+impl Drop for CmEventChannel {
+    fn drop(&mut self) {
+        unsafe { crate::rdma_destroy_event_channel(self.channel) };
+    }
+}
+
+impl std::fmt::Debug for CmEventChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CmEventChannel")
+            .field("fd", &self.fd())
+            .finish()
+    }
+}
+
+/// Returns the librdmacm name of an event type, e.g. `RDMA_CM_EVENT_ESTABLISHED`.
+fn event_str(event: rdma_cm_event_type) -> String {
+    let name = unsafe { crate::rdma_event_str(event) };
+    if name.is_null() {
+        format!("{event:?}")
+    } else {
+        unsafe { CStr::from_ptr(name) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Connection manager event, acknowledged on drop.
+pub struct CmEvent<'a> {
+    event: *mut crate::rdma_cm_event,
+    _channel: std::marker::PhantomData<&'a CmEventChannel>,
+}
+
+impl CmEvent<'_> {
+    /// Returns the event type.
+    pub fn event_type(&self) -> rdma_cm_event_type {
+        unsafe { (*self.event).event }
+    }
+
+    /// Returns the event status; non-zero for failed operations.
+    pub fn status(&self) -> i32 {
+        unsafe { (*self.event).status }
+    }
+
+    /// Returns the raw id the event refers to.
+    ///
+    /// For `RDMA_CM_EVENT_CONNECT_REQUEST` this is a new id for the incoming
+    /// connection, owned by the caller, and the listening id is available
+    /// from the raw event.
+    ///
+    /// # Safety
+    ///
+    /// The returned pointer is only valid as long as that id exists.
+    pub unsafe fn id_ptr(&self) -> *mut crate::rdma_cm_id {
+        unsafe { (*self.event).id }
+    }
+
+    /// Returns the raw event pointer.
+    ///
+    /// # Safety
+    ///
+    /// The returned pointer is only valid until this event is dropped.
+    pub unsafe fn event_ptr(&self) -> *mut crate::rdma_cm_event {
+        self.event
+    }
+}
+
+impl Drop for CmEvent<'_> {
+    fn drop(&mut self) {
+        let ret = unsafe { crate::rdma_ack_cm_event(self.event) };
+        crate::check_cleanup("rdma_ack_cm_event", ret);
+    }
+}
+
+impl std::fmt::Debug for CmEvent<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CmEvent")
+            .field("event", &event_str(self.event_type()))
+            .field("status", &self.status())
+            .finish()
+    }
+}
+
+/// Connection manager identifier with automatic cleanup.
+///
+/// Asynchronous operations complete with an event on the [`CmEventChannel`]
+/// the id was created on.
+pub struct CmId {
+    id: *mut crate::rdma_cm_id,
+    channel: Arc<CmEventChannel>,
+}
+
+unsafe impl Send for CmId {}
+unsafe impl Sync for CmId {}
+
+impl CmId {
+    /// Creates an id on `channel` in the given port space.
+    ///
+    /// `RDMA_PS_TCP` gives reliable-connected semantics and `RDMA_PS_UDP`
+    /// unreliable datagrams.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::RdmaCmFailed`] if `rdma_create_id` fails.
+    pub fn new(channel: &Arc<CmEventChannel>, port_space: rdma_port_space) -> Result<Self> {
+        let mut id = std::ptr::null_mut();
+        let ret = unsafe {
+            crate::rdma_create_id(channel.channel, &mut id, std::ptr::null_mut(), port_space)
+        };
+        if ret != 0 {
+            return Err(cm_error("rdma_create_id"));
+        }
+        Ok(Self {
+            id,
+            channel: channel.clone(),
+        })
+    }
+
+    /// Returns the event channel of this id.
+    pub fn channel(&self) -> &Arc<CmEventChannel> {
+        &self.channel
+    }
+
+    /// Binds the id to a local address, and to its RDMA device if the
+    /// address is not a wildcard.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::RdmaCmFailed`] if `rdma_bind_addr` fails.
+    pub fn bind_addr(&self, addr: SocketAddr) -> Result<()> {
+        let mut addr = to_sockaddr(&addr);
+        let ret = unsafe {
+            crate::rdma_bind_addr(self.id, (&mut addr as *mut libc::sockaddr_storage).cast())
+        };
+        if ret != 0 {
+            return Err(cm_error("rdma_bind_addr"));
+        }
+        Ok(())
+    }
+
+    /// Starts listening for connection requests on the bound address.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::RdmaCmFailed`] if `rdma_listen` fails.
+    pub fn listen(&self, backlog: i32) -> Result<()> {
+        let ret = unsafe { crate::rdma_listen(self.id, backlog) };
+        if ret != 0 {
+            return Err(cm_error("rdma_listen"));
+        }
+        Ok(())
+    }
+
+    /// Resolves `dst` to an RDMA address, binding the id to a local device.
+    ///
+    /// Completes with `RDMA_CM_EVENT_ADDR_RESOLVED` or
+    /// `RDMA_CM_EVENT_ADDR_ERROR`. With `src` unset, the local address is
+    /// picked from the routing table.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::RdmaCmFailed`] if `rdma_resolve_addr` fails.
+    pub fn resolve_addr(
+        &self,
+        src: Option<SocketAddr>,
+        dst: SocketAddr,
+        timeout: Duration,
+    ) -> Result<()> {
+        let mut src = src.map(|addr| to_sockaddr(&addr));
+        let src_ptr = src.as_mut().map_or(std::ptr::null_mut(), |addr| {
+            (addr as *mut libc::sockaddr_storage).cast()
+        });
+        let mut dst = to_sockaddr(&dst);
+        let ret = unsafe {
+            crate::rdma_resolve_addr(
+                self.id,
+                src_ptr,
+                (&mut dst as *mut libc::sockaddr_storage).cast(),
+                timeout_ms(timeout),
+            )
+        };
+        if ret != 0 {
+            return Err(cm_error("rdma_resolve_addr"));
+        }
+        Ok(())
+    }
+
+    /// Resolves the route to the destination of a resolved address.
+    ///
+    /// Completes with `RDMA_CM_EVENT_ROUTE_RESOLVED` or
+    /// `RDMA_CM_EVENT_ROUTE_ERROR`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::RdmaCmFailed`] if `rdma_resolve_route` fails.
+    pub fn resolve_route(&self, timeout: Duration) -> Result<()> {
+        let ret = unsafe { crate::rdma_resolve_route(self.id, timeout_ms(timeout)) };
+        if ret != 0 {
+            return Err(cm_error("rdma_resolve_route"));
+        }
+        Ok(())
+    }
+
+    /// Initiates a connection to the resolved destination.
+    ///
+    /// Completes with `RDMA_CM_EVENT_ESTABLISHED`, or an error or rejection
+    /// event.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::RdmaCmFailed`] if `rdma_connect` fails.
+    pub fn connect(&self, param: &mut rdma_conn_param) -> Result<()> {
+        let ret = unsafe { crate::rdma_connect(self.id, param) };
+        if ret != 0 {
+            return Err(cm_error("rdma_connect"));
+        }
+        Ok(())
+    }
+
+    /// Accepts the connection request this id was created for.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::RdmaCmFailed`] if `rdma_accept` fails.
+    pub fn accept(&self, param: &mut rdma_conn_param) -> Result<()> {
+        let ret = unsafe { crate::rdma_accept(self.id, param) };
+        if ret != 0 {
+            return Err(cm_error("rdma_accept"));
+        }
+        Ok(())
+    }
+
+    /// Disconnects the connection; both sides get `RDMA_CM_EVENT_DISCONNECTED`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::RdmaCmFailed`] if `rdma_disconnect` fails.
+    pub fn disconnect(&self) -> Result<()> {
+        let ret = unsafe { crate::rdma_disconnect(self.id) };
+        if ret != 0 {
+            return Err(cm_error("rdma_disconnect"));
+        }
+        Ok(())
+    }
+
+    /// Returns the verbs context of the bound device, or null while unbound.
+    pub fn context_ptr(&self) -> *mut crate::ibv_context {
+        unsafe { (*self.id).verbs }
+    }
+
+    /// Returns the bound port number, or 0 while unbound.
+    pub fn port_num(&self) -> u8 {
+        unsafe { (*self.id).port_num }
+    }
+
+    /// Returns the raw id pointer.
+    ///
+    /// # Safety
+    ///
+    /// The returned pointer is only valid as long as this id exists.
+    pub unsafe fn id_ptr(&self) -> *mut crate::rdma_cm_id {
+        self.id
+    }
+}
+
+impl Drop for CmId {
+    fn drop(&mut self) {
+        let ret = unsafe { crate::rdma_destroy_id(self.id) };
+        crate::check_cleanup("rdma_destroy_id", ret);
+    }
+}
+
+impl std::fmt::Debug for CmId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CmId")
+            .field("port_num", &self.port_num())
+            .field("bound", &!self.context_ptr().is_null())
+            .field("channel", &self.channel)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_sockaddr() {
+        let storage = to_sockaddr(&"192.168.1.10:7471".parse().unwrap());
+        let sin = unsafe { &*(&storage as *const _ as *const libc::sockaddr_in) };
+        assert_eq!(sin.sin_family, libc::AF_INET as libc::sa_family_t);
+        assert_eq!(u16::from_be(sin.sin_port), 7471);
+        assert_eq!(sin.sin_addr.s_addr.to_ne_bytes(), [192, 168, 1, 10]);
+
+        let storage = to_sockaddr(&"[fe80::1%2]:18515".parse().unwrap());
+        let sin6 = unsafe { &*(&storage as *const _ as *const libc::sockaddr_in6) };
+        assert_eq!(sin6.sin6_family, libc::AF_INET6 as libc::sa_family_t);
+        assert_eq!(u16::from_be(sin6.sin6_port), 18515);
+        assert_eq!(sin6.sin6_addr.s6_addr[0], 0xfe);
+        assert_eq!(sin6.sin6_addr.s6_addr[15], 1);
+        assert_eq!(sin6.sin6_scope_id, 2);
+
+        assert_eq!(timeout_ms(Duration::from_secs(2)), 2000);
+        assert_eq!(timeout_ms(Duration::MAX), c_int::MAX);
+    }
+
+    #[test]
+    fn cm_id_listen() {
+        let channel = Arc::new(CmEventChannel::new().unwrap());
+        assert!(channel.fd() >= 0);
+        let id = CmId::new(&channel, rdma_port_space::RDMA_PS_TCP).unwrap();
+        id.bind_addr("0.0.0.0:0".parse().unwrap()).unwrap();
+        assert!(id.context_ptr().is_null());
+        id.listen(1).unwrap();
+    }
+}
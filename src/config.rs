@@ -1,8 +1,13 @@
-use std::collections::HashSet;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+};
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::{LinkLayer, Mtu};
+
 /// Global Identifier (GID) type for InfiniBand/RoCE networks.
 ///
 /// Different GID types represent different network layer protocols:
@@ -28,20 +33,93 @@ pub enum GidType {
     Other(String),
 }
 
+impl GidType {
+    /// Returns the name of the GID type, matching the CLI value names.
+    pub fn as_str(&self) -> Cow<'static, str> {
+        match self {
+            Self::IB => Cow::Borrowed("IB"),
+            Self::RoCEv1 => Cow::Borrowed("RoCEv1"),
+            Self::RoCEv2 => Cow::Borrowed("RoCEv2"),
+            Self::Other(name) => Cow::Owned(name.clone()),
+        }
+    }
+}
+
+impl std::fmt::Display for GidType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.as_str())
+    }
+}
+
+/// Completion queue depth used when none is requested.
+pub const DEFAULT_CQ_SIZE: u32 = 1024;
+
 /// Device-level configuration for RDMA device filtering.
 ///
 /// Controls which devices, ports, and GID types are selected
 /// for RDMA operations.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DeviceConfig {
     /// Set of device names to include. Empty means all devices.
+    ///
+    /// Entries may also be qualified as `name@guid` (see
+    /// [`DeviceInfo::qualified_name`](crate::DeviceInfo::qualified_name)) to
+    /// select one of several devices sharing a name.
     pub device_filter: HashSet<String>,
     /// Set of GID types to include. Empty means all types.
     pub gid_type_filter: HashSet<GidType>,
+    /// GID types in order of preference when picking a GID on a port, e.g.
+    /// `[RoCEv2, RoCEv1, IB]`. Pass it to
+    /// [`Port::preferred_gid`](crate::Port::preferred_gid). Unlike
+    /// [`gid_type_filter`](Self::gid_type_filter), it removes no GIDs.
+    pub gid_type_preference: Vec<GidType>,
     /// Whether to skip inactive ports during device enumeration.
     pub skip_inactive_port: bool,
     /// For RoCE v2, whether to skip link-local addresses.
     pub roce_v2_skip_link_local_addr: bool,
+    /// Only include ports carrying this partition key. `None` means all ports.
+    pub pkey_filter: Option<u16>,
+    /// Set of link layers ports must use. Empty means all link layers.
+    pub link_layer_filter: HashSet<LinkLayer>,
+    /// Only include ports whose active MTU is at least this. `None` means
+    /// all ports.
+    pub min_active_mtu: Option<Mtu>,
+    /// Whether to keep only the first GID of each address on a port.
+    pub dedup_gids: bool,
+    /// Whether to keep null GIDs, which pad the GID tables of some
+    /// providers. By default they are skipped without reading their type.
+    pub include_null_gids: bool,
+    /// GID types for non-standard sysfs type strings, keyed by the string
+    /// without its trailing newline, e.g. `"RoCE v2 (vendor)"`.
+    ///
+    /// Consulted only for strings other than the standard `"IB/RoCE v1"`
+    /// and `"RoCE v2"`; strings matching no key become
+    /// [`GidType::Other`]. The GID type filter applies to the mapped type.
+    pub gid_type_overrides: HashMap<String, GidType>,
+    /// Completion queue depth used by
+    /// [`Device::create_cq`](crate::Device::create_cq) when the caller
+    /// passes `None`. Clamped to the device's `max_cqe` like explicit sizes.
+    /// Defaults to [`DEFAULT_CQ_SIZE`].
+    pub default_cq_size: u32,
+}
+
+impl Default for DeviceConfig {
+    fn default() -> Self {
+        Self {
+            device_filter: HashSet::new(),
+            gid_type_filter: HashSet::new(),
+            gid_type_preference: Vec::new(),
+            skip_inactive_port: false,
+            roce_v2_skip_link_local_addr: false,
+            pkey_filter: None,
+            link_layer_filter: HashSet::new(),
+            min_active_mtu: None,
+            dedup_gids: false,
+            include_null_gids: false,
+            gid_type_overrides: HashMap::new(),
+            default_cq_size: DEFAULT_CQ_SIZE,
+        }
+    }
 }
 
 impl DeviceConfig {
@@ -50,6 +128,38 @@ impl DeviceConfig {
         DeviceConfigBuilder::default()
     }
 
+    /// Creates a config selecting the active ports with GIDs of `gid_types`.
+    ///
+    /// Shorthand for a [`builder`](Self::builder) with `gid_types` and
+    /// `skip_inactive` set; see
+    /// [`Devices::open_roce`](crate::Devices::open_roce) for RoCE discovery.
+    pub fn for_gid_types(gid_types: &[GidType]) -> Self {
+        Self {
+            gid_type_filter: gid_types.iter().cloned().collect(),
+            skip_inactive_port: true,
+            ..Default::default()
+        }
+    }
+
+    /// Returns true if a port with `port_attr` passes the port filters.
+    ///
+    /// Checks [`skip_inactive_port`](Self::skip_inactive_port),
+    /// [`link_layer_filter`](Self::link_layer_filter) and
+    /// [`min_active_mtu`](Self::min_active_mtu); the P_Key filter needs the
+    /// port's P_Key table and is applied separately.
+    pub(crate) fn accepts_port(&self, port_attr: &crate::ibv_port_attr) -> bool {
+        if self.skip_inactive_port && port_attr.state != crate::ibv_port_state::IBV_PORT_ACTIVE {
+            return false;
+        }
+        if !self.link_layer_filter.is_empty()
+            && !self.link_layer_filter.contains(&port_attr.link_layer)
+        {
+            return false;
+        }
+        self.min_active_mtu
+            .is_none_or(|mtu| port_attr.active_mtu.bytes() >= mtu.bytes())
+    }
+
     /// Adds a device name to the filter.
     pub fn with_device(mut self, device: impl Into<String>) -> Self {
         self.device_filter.insert(device.into());
@@ -62,6 +172,12 @@ impl DeviceConfig {
         self
     }
 
+    /// Sets the GID type preference order.
+    pub fn with_gid_type_preference(mut self, preference: impl Into<Vec<GidType>>) -> Self {
+        self.gid_type_preference = preference.into();
+        self
+    }
+
     /// Sets whether to skip inactive ports.
     pub fn with_skip_inactive(mut self, skip: bool) -> Self {
         self.skip_inactive_port = skip;
@@ -73,6 +189,48 @@ impl DeviceConfig {
         self.roce_v2_skip_link_local_addr = skip;
         self
     }
+
+    /// Sets the partition key ports must carry.
+    pub fn with_pkey(mut self, pkey: u16) -> Self {
+        self.pkey_filter = Some(pkey);
+        self
+    }
+
+    /// Adds a link layer to the filter.
+    pub fn with_link_layer(mut self, link_layer: LinkLayer) -> Self {
+        self.link_layer_filter.insert(link_layer);
+        self
+    }
+
+    /// Sets the minimum active MTU ports must have.
+    pub fn with_min_active_mtu(mut self, mtu: Mtu) -> Self {
+        self.min_active_mtu = Some(mtu);
+        self
+    }
+
+    /// Sets whether to drop duplicate GID addresses within a port.
+    pub fn with_dedup_gids(mut self, dedup: bool) -> Self {
+        self.dedup_gids = dedup;
+        self
+    }
+
+    /// Sets whether to keep null GID table entries.
+    pub fn with_include_null_gids(mut self, include: bool) -> Self {
+        self.include_null_gids = include;
+        self
+    }
+
+    /// Maps a non-standard sysfs GID type string to `gid_type`.
+    pub fn with_gid_type_override(mut self, name: impl Into<String>, gid_type: GidType) -> Self {
+        self.gid_type_overrides.insert(name.into(), gid_type);
+        self
+    }
+
+    /// Sets the completion queue depth used when none is requested.
+    pub fn with_default_cq_size(mut self, cqe: u32) -> Self {
+        self.default_cq_size = cqe;
+        self
+    }
 }
 
 /// Builder for [`DeviceConfig`].
@@ -117,6 +275,12 @@ impl DeviceConfigBuilder {
         self
     }
 
+    /// Appends a GID type to the preference order.
+    pub fn prefer_gid_type(mut self, gid_type: GidType) -> Self {
+        self.config.gid_type_preference.push(gid_type);
+        self
+    }
+
     /// Sets whether to skip inactive ports.
     pub fn skip_inactive(mut self, skip: bool) -> Self {
         self.config.skip_inactive_port = skip;
@@ -129,8 +293,155 @@ impl DeviceConfigBuilder {
         self
     }
 
+    /// Sets the partition key ports must carry.
+    pub fn pkey(mut self, pkey: u16) -> Self {
+        self.config.pkey_filter = Some(pkey);
+        self
+    }
+
+    /// Adds a link layer to the filter.
+    pub fn link_layer(mut self, link_layer: LinkLayer) -> Self {
+        self.config.link_layer_filter.insert(link_layer);
+        self
+    }
+
+    /// Adds multiple link layers to the filter.
+    pub fn link_layers<I>(mut self, link_layers: I) -> Self
+    where
+        I: IntoIterator<Item = LinkLayer>,
+    {
+        self.config.link_layer_filter.extend(link_layers);
+        self
+    }
+
+    /// Sets the minimum active MTU ports must have.
+    pub fn min_active_mtu(mut self, mtu: Mtu) -> Self {
+        self.config.min_active_mtu = Some(mtu);
+        self
+    }
+
+    /// Sets whether to drop duplicate GID addresses within a port.
+    pub fn dedup_gids(mut self, dedup: bool) -> Self {
+        self.config.dedup_gids = dedup;
+        self
+    }
+
+    /// Sets whether to keep null GID table entries.
+    pub fn include_null_gids(mut self, include: bool) -> Self {
+        self.config.include_null_gids = include;
+        self
+    }
+
+    /// Maps a non-standard sysfs GID type string to `gid_type`.
+    pub fn gid_type_override(mut self, name: impl Into<String>, gid_type: GidType) -> Self {
+        self.config.gid_type_overrides.insert(name.into(), gid_type);
+        self
+    }
+
+    /// Sets the completion queue depth used when none is requested.
+    pub fn default_cq_size(mut self, cqe: u32) -> Self {
+        self.config.default_cq_size = cqe;
+        self
+    }
+
     /// Builds the final [`DeviceConfig`].
     pub fn build(self) -> DeviceConfig {
         self.config
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gid_type_as_str() {
+        assert_eq!(GidType::IB.as_str(), "IB");
+        assert_eq!(GidType::RoCEv1.as_str(), "RoCEv1");
+        assert_eq!(GidType::RoCEv2.as_str(), "RoCEv2");
+        assert_eq!(GidType::Other("custom".to_string()).as_str(), "custom");
+    }
+
+    #[test]
+    fn test_gid_type_display() {
+        assert_eq!(GidType::IB.to_string(), "IB");
+        assert_eq!(GidType::RoCEv1.to_string(), "RoCEv1");
+        assert_eq!(GidType::RoCEv2.to_string(), "RoCEv2");
+        assert_eq!(GidType::Other("custom".to_string()).to_string(), "custom");
+    }
+
+    #[test]
+    fn test_builder_all_fields() {
+        let config = DeviceConfig::builder()
+            .device("mlx5_0")
+            .devices(["mlx5_1", "mlx5_0"])
+            .gid_type(GidType::RoCEv2)
+            .gid_types([GidType::RoCEv1])
+            .prefer_gid_type(GidType::RoCEv2)
+            .prefer_gid_type(GidType::IB)
+            .skip_inactive(true)
+            .skip_link_local(true)
+            .pkey(0x8001)
+            .link_layer(LinkLayer::Ethernet)
+            .link_layers([LinkLayer::InfiniBand, LinkLayer::Ethernet])
+            .min_active_mtu(Mtu(crate::ibv_mtu::IBV_MTU_4096))
+            .dedup_gids(true)
+            .include_null_gids(true)
+            .gid_type_override("RoCE v2 (vendor)", GidType::RoCEv2)
+            .default_cq_size(256)
+            .build();
+
+        let DeviceConfig {
+            device_filter,
+            gid_type_filter,
+            gid_type_preference,
+            skip_inactive_port,
+            roce_v2_skip_link_local_addr,
+            pkey_filter,
+            link_layer_filter,
+            min_active_mtu,
+            dedup_gids,
+            include_null_gids,
+            gid_type_overrides,
+            default_cq_size,
+        } = config;
+        assert_eq!(device_filter, ["mlx5_0", "mlx5_1"].map(String::from).into());
+        assert_eq!(gid_type_filter, [GidType::RoCEv1, GidType::RoCEv2].into());
+        assert_eq!(gid_type_preference, [GidType::RoCEv2, GidType::IB]);
+        assert!(skip_inactive_port);
+        assert!(roce_v2_skip_link_local_addr);
+        assert_eq!(pkey_filter, Some(0x8001));
+        assert_eq!(
+            link_layer_filter,
+            [LinkLayer::InfiniBand, LinkLayer::Ethernet].into()
+        );
+        assert_eq!(min_active_mtu.map(|mtu| mtu.bytes()), Some(4096));
+        assert!(dedup_gids);
+        assert!(include_null_gids);
+        assert_eq!(
+            gid_type_overrides,
+            [("RoCE v2 (vendor)".to_string(), GidType::RoCEv2)].into()
+        );
+        assert_eq!(default_cq_size, 256);
+    }
+
+    #[test]
+    fn test_for_gid_types() {
+        let config = DeviceConfig::for_gid_types(&[GidType::RoCEv2, GidType::IB]);
+        let expected = DeviceConfig::builder()
+            .gid_types([GidType::IB, GidType::RoCEv2])
+            .skip_inactive(true)
+            .build();
+        assert_eq!(config, expected);
+        assert_ne!(config, DeviceConfig::for_gid_types(&[GidType::RoCEv2]));
+    }
+
+    #[test]
+    fn test_default_cq_size() {
+        assert_eq!(DeviceConfig::default().default_cq_size, DEFAULT_CQ_SIZE);
+        let config = DeviceConfig::builder().default_cq_size(64).build();
+        assert_eq!(config.default_cq_size, 64);
+        let config = DeviceConfig::default().with_default_cq_size(4096);
+        assert_eq!(config.default_cq_size, 4096);
+    }
+}
@@ -0,0 +1,507 @@
+//! # Reliable-connected connections
+//!
+//! [`RcConnection`] ties a [`QueuePair`], its completion queues and a pool of
+//! registered buffers into a message-oriented connection. It covers the
+//! common case of exchanging small messages over one RC QP; use the
+//! lower-level wrappers for anything more specialised.
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use ruapc_rdma_sys::{ConnectionInfo, Devices, Event, RcConnection, RcConnectionConfig};
+//!
+//! # fn exchange(info: ConnectionInfo) -> ConnectionInfo { info }
+//! # fn main() -> ruapc_rdma_sys::Result<()> {
+//! let devices = Devices::available()?;
+//! let device = &devices[0];
+//! let port = &device.info().ports[0];
+//!
+//! let mut conn = RcConnection::new(device, &RcConnectionConfig::default())?;
+//! // Exchange connection info with the peer out of band, e.g. over TCP
+//! let remote = exchange(conn.local_info(port)?);
+//! conn.connect(port, &remote)?;
+//!
+//! conn.post_send(b"hello")?;
+//! loop {
+//!     for event in conn.poll()? {
+//!         if let Event::Received(data) = event {
+//!             println!("received {data:?}");
+//!         }
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CompletionQueue, Device, Error, ErrorKind, GidType, LinkLayer, Port, QpInitAttrBuilder,
+    QueuePair, RegisteredBuffer, Result, RtrParams, RtsParams, SendWr, WCType, WRID,
+    ibv_access_flags, ibv_gid, ibv_recv_wr, ibv_wc, ibv_wc_status, ibv_wr_opcode,
+};
+
+/// Endpoint details exchanged out of band to connect two [`RcConnection`]s.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ConnectionInfo {
+    /// Queue pair number.
+    pub qp_num: u32,
+    /// Port LID, used on InfiniBand.
+    pub lid: u16,
+    /// Port GID, used on RoCE.
+    pub gid: ibv_gid,
+    /// GID index on the local port; not needed by the peer.
+    #[serde(skip)]
+    pub gid_index: u16,
+    /// Starting send packet sequence number.
+    pub psn: u32,
+}
+
+impl ConnectionInfo {
+    /// Checks that this remote descriptor can address a peer on `link_layer`.
+    ///
+    /// The QP number must be a non-zero 24-bit value. RoCE (Ethernet) routes
+    /// by GID, so the GID must be non-null; InfiniBand routes by LID, so the
+    /// LID must be set. Checking at the handshake boundary turns the bare
+    /// `EINVAL` from the RTR transition into a message naming the field.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidConnectionInfo`] describing the first
+    /// invalid field.
+    pub fn validate(&self, link_layer: LinkLayer) -> Result<()> {
+        let invalid = |msg: String| Err(Error::new(ErrorKind::InvalidConnectionInfo, msg));
+        if self.qp_num == 0 {
+            return invalid("remote QP number is zero".to_string());
+        }
+        if self.qp_num > 0xff_ffff {
+            return invalid(format!(
+                "remote QP number {:#x} exceeds 24 bits",
+                self.qp_num
+            ));
+        }
+        if link_layer == LinkLayer::Ethernet {
+            if self.gid.is_null() {
+                return invalid(format!(
+                    "remote GID {:?} is null, but RoCE requires a GID",
+                    self.gid
+                ));
+            }
+        } else if self.lid == 0 {
+            return invalid("remote LID is zero, but InfiniBand requires a LID".to_string());
+        }
+        Ok(())
+    }
+
+    /// Describes the local QP `qp_num` on port `local`.
+    ///
+    /// RoCE ports advertise their first RoCEv2 GID, falling back to the
+    /// first GID of any type.
+    pub(crate) fn local(qp_num: u32, psn: u32, local: &Port) -> Result<Self> {
+        let mut info = ConnectionInfo {
+            qp_num,
+            lid: local.lid(),
+            psn,
+            ..Default::default()
+        };
+        let gid = local
+            .preferred_gid(&[GidType::RoCEv2])
+            .or_else(|| local.gids.first());
+        match gid {
+            Some(gid) => {
+                info.gid = gid.gid;
+                info.gid_index = gid.index;
+            }
+            None if local.port_attr.link_layer == LinkLayer::Ethernet => {
+                return Err(Error::new(
+                    ErrorKind::IBQueryGidFail,
+                    format!("port {} has no GID", local.port_num),
+                ));
+            }
+            None => {}
+        }
+        Ok(info)
+    }
+
+    /// Builds the RTR parameters for reaching this remote endpoint from
+    /// `local`, whose own GID index is `sgid_index`.
+    ///
+    /// The path MTU is the active MTU of `local`.
+    pub(crate) fn rtr_params(&self, local: &Port, sgid_index: u16) -> RtrParams {
+        RtrParams {
+            dest_qp_num: self.qp_num,
+            rq_psn: self.psn,
+            dest_lid: self.lid,
+            // RoCE needs a global route header; IB within a subnet does not
+            dest_gid: if local.port_attr.link_layer == LinkLayer::Ethernet {
+                self.gid
+            } else {
+                ibv_gid::default()
+            },
+            sgid_index: sgid_index as u8,
+            path_mtu: local.port_attr.active_mtu.0,
+            ..Default::default()
+        }
+    }
+}
+
+/// Sizing of an [`RcConnection`].
+#[derive(Debug, Clone, Copy)]
+pub struct RcConnectionConfig {
+    /// Size of each send and receive buffer, and so the largest message.
+    pub buf_size: usize,
+    /// Number of outstanding sends.
+    pub send_depth: u32,
+    /// Number of posted receive buffers.
+    pub recv_depth: u32,
+}
+
+impl Default for RcConnectionConfig {
+    fn default() -> Self {
+        Self {
+            buf_size: 4096,
+            send_depth: 64,
+            recv_depth: 64,
+        }
+    }
+}
+
+/// Completion event reported by [`RcConnection::poll`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A message posted with [`RcConnection::post_send`] completed.
+    Sent,
+    /// A message arrived from the peer.
+    Received(Vec<u8>),
+    /// A work request failed; the connection is unusable afterwards.
+    Error {
+        /// Completion status reported by the device.
+        status: ibv_wc_status,
+        /// Work request that failed.
+        wr_id: WRID,
+    },
+}
+
+/// Message-oriented reliable-connected connection.
+///
+/// Owns the QP, a send and a receive CQ, and one registered buffer per send
+/// and receive slot. Received messages are copied out and their buffers are
+/// reposted immediately.
+pub struct RcConnection {
+    // Declared before the CQs and buffers so the QP is destroyed first.
+    qp: QueuePair,
+    send_cq: CompletionQueue,
+    recv_cq: CompletionQueue,
+    send_bufs: Vec<RegisteredBuffer>,
+    recv_bufs: Vec<RegisteredBuffer>,
+    free_send: Vec<usize>,
+    psn: u32,
+}
+
+impl RcConnection {
+    /// Largest `poll` batch.
+    const POLL_BATCH: usize = 16;
+
+    /// Creates the QP, CQs and buffers of a connection in the RESET state.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if creating any of the resources fails, including
+    /// when the depths exceed the device limits.
+    pub fn new(device: &Arc<Device>, config: &RcConnectionConfig) -> Result<Self> {
+        let send_cq = device.create_cq(config.send_depth)?;
+        let recv_cq = device.create_cq(config.recv_depth)?;
+        let init_attr = QpInitAttrBuilder::default()
+            .send_cq(unsafe { send_cq.cq_ptr() })
+            .recv_cq(unsafe { recv_cq.cq_ptr() })
+            .max_send_wr(config.send_depth)
+            .max_recv_wr(config.recv_depth)
+            .build(&device.info().device_attr)?;
+        // SAFETY: the CQs are owned by the connection and outlive the QP
+        let qp = unsafe { device.create_qp(&init_attr)? };
+
+        let register = |_| {
+            device.register_memory(
+                vec![0u8; config.buf_size],
+                ibv_access_flags::IBV_ACCESS_LOCAL_WRITE,
+            )
+        };
+        let send_bufs = (0..config.send_depth)
+            .map(register)
+            .collect::<Result<Vec<_>>>()?;
+        let recv_bufs = (0..config.recv_depth)
+            .map(register)
+            .collect::<Result<Vec<_>>>()?;
+
+        let psn = crate::random_psn();
+        Ok(Self {
+            free_send: (0..send_bufs.len()).rev().collect(),
+            qp,
+            send_cq,
+            recv_cq,
+            send_bufs,
+            recv_bufs,
+            psn,
+        })
+    }
+
+    /// Returns the queue pair of the connection.
+    pub fn qp(&self) -> &QueuePair {
+        &self.qp
+    }
+
+    /// Returns the details the peer needs to connect to `local`.
+    ///
+    /// RoCE ports advertise their first RoCEv2 GID, falling back to the
+    /// first GID of any type.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::IBQueryGidFail`] if a RoCE port has no GID.
+    pub fn local_info(&self, local: &Port) -> Result<ConnectionInfo> {
+        ConnectionInfo::local(self.qp.qp_num(), self.psn, local)
+    }
+
+    /// Connects to the peer described by `remote` through the `local` port.
+    ///
+    /// Drives the QP through INIT, posts every receive buffer, and then
+    /// moves it through RTR to RTS. The path MTU is the active MTU of
+    /// `local`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidConnectionInfo`] if `remote` fails
+    /// [`ConnectionInfo::validate`], or an error if a state transition or
+    /// posting a receive fails.
+    pub fn connect(&mut self, local: &Port, remote: &ConnectionInfo) -> Result<()> {
+        remote.validate(local.port_attr.link_layer)?;
+        let info = self.local_info(local)?;
+        let qp = unsafe { self.qp.qp_ptr() };
+        unsafe {
+            crate::modify_to_init(qp, local.port_num, ibv_access_flags::IBV_ACCESS_LOCAL_WRITE)?
+        };
+        for slot in 0..self.recv_bufs.len() {
+            self.post_recv(slot)?;
+        }
+
+        let rtr = remote.rtr_params(local, info.gid_index);
+        let rts = RtsParams {
+            sq_psn: self.psn,
+            ..Default::default()
+        };
+        unsafe {
+            crate::modify_to_rtr(qp, local.port_num, &rtr)?;
+            crate::modify_to_rts(qp, &rts)
+        }
+    }
+
+    /// Copies `data` into a free send buffer and posts it as a send.
+    ///
+    /// Messages that fit in the QP's `max_inline_data` are sent inline.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidArgument`] if `data` is larger than the
+    /// buffer size, [`ErrorKind::InsufficientBuffer`] if every send slot is
+    /// in flight, or an error from `ibv_post_send`.
+    pub fn post_send(&mut self, data: &[u8]) -> Result<()> {
+        let buf_size = self.send_bufs.first().map_or(0, |buf| buf.len());
+        if data.len() > buf_size {
+            return Err(Error::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "message of {} bytes exceeds buffer size {buf_size}",
+                    data.len()
+                ),
+            ));
+        }
+        let Some(slot) = self.free_send.pop() else {
+            return Err(Error::new(
+                ErrorKind::InsufficientBuffer,
+                "all send slots are in flight; poll for completions".to_string(),
+            ));
+        };
+
+        let buf = &mut self.send_bufs[slot];
+        buf[..data.len()].copy_from_slice(data);
+        let max_inline_data = self.qp.max_inline_data();
+        let mut wr = SendWr::new(ibv_wr_opcode::IBV_WR_SEND, WRID::send_data(slot as u64))
+            .push_sge(buf.sge(0..data.len()))
+            .signaled(true)
+            .inline(data.len() <= max_inline_data as usize);
+        let result = unsafe { wr.post(self.qp.qp_ptr(), max_inline_data) };
+        if result.is_err() {
+            self.free_send.push(slot);
+        }
+        result
+    }
+
+    /// Polls both CQs and returns the completed events.
+    ///
+    /// Received messages are copied out and their buffers reposted. Failed
+    /// completions, including flushes after the QP enters the error state,
+    /// are reported as [`Event::Error`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if polling a CQ or reposting a receive fails.
+    pub fn poll(&mut self) -> Result<Vec<Event>> {
+        let mut events = Vec::new();
+        let mut wcs = [ibv_wc::default(); Self::POLL_BATCH];
+
+        for wc in self.send_cq.poll(&mut wcs)? {
+            let slot = wc.wr_id.get_id() as usize;
+            if wc.wr_id.get_type() == WCType::SendData && slot < self.send_bufs.len() {
+                self.free_send.push(slot);
+            }
+            events.push(if wc.succ() {
+                Event::Sent
+            } else {
+                Event::Error {
+                    status: wc.status,
+                    wr_id: wc.wr_id,
+                }
+            });
+        }
+
+        let mut wcs = [ibv_wc::default(); Self::POLL_BATCH];
+        for wc in self.recv_cq.poll(&mut wcs)? {
+            if !wc.succ() {
+                events.push(Event::Error {
+                    status: wc.status,
+                    wr_id: wc.wr_id,
+                });
+                continue;
+            }
+            let slot = wc.wr_id.get_id() as usize;
+            let data = self.recv_bufs[slot][..wc.byte_len as usize].to_vec();
+            self.post_recv(slot)?;
+            events.push(Event::Received(data));
+        }
+
+        Ok(events)
+    }
+
+    /// Posts the receive buffer of `slot`.
+    fn post_recv(&mut self, slot: usize) -> Result<()> {
+        let buf = &self.recv_bufs[slot];
+        let mut sge = buf.sge(0..buf.len());
+        let mut wr = ibv_recv_wr {
+            wr_id: WRID::recv(slot as u64),
+            sg_list: &mut sge,
+            num_sge: 1,
+            ..Default::default()
+        };
+        let mut bad_wr = std::ptr::null_mut();
+        let ret = unsafe { crate::ibv_post_recv(self.qp.qp_ptr(), &mut wr, &mut bad_wr) };
+        if ret != 0 {
+            return Err(Error::from_errno(
+                unsafe { crate::ffi::post_recv_error_kind(self.qp.qp_ptr(), ret) },
+                ret,
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for RcConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RcConnection")
+            .field("qp", &self.qp)
+            .field("send_depth", &self.send_bufs.len())
+            .field("recv_depth", &self.recv_bufs.len())
+            .field("in_flight", &(self.send_bufs.len() - self.free_send.len()))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Devices;
+
+    #[test]
+    fn connection_info_serde() {
+        let info = ConnectionInfo {
+            qp_num: 0x1234,
+            lid: 7,
+            gid: serde_json::from_str("\"fe80::1\"").unwrap(),
+            gid_index: 3,
+            psn: 42,
+        };
+        let json = serde_json::to_string(&info).unwrap();
+        assert!(!json.contains("gid_index"));
+        let der: ConnectionInfo = serde_json::from_str(&json).unwrap();
+        assert_eq!(der.qp_num, info.qp_num);
+        assert_eq!(der.lid, info.lid);
+        assert_eq!(der.gid.as_raw(), info.gid.as_raw());
+        assert_eq!(der.psn, info.psn);
+    }
+
+    #[test]
+    fn connection_info_validate() {
+        let valid = ConnectionInfo {
+            qp_num: 0x1234,
+            lid: 7,
+            gid: serde_json::from_str("\"fe80::1\"").unwrap(),
+            ..Default::default()
+        };
+        valid.validate(LinkLayer::Ethernet).unwrap();
+        valid.validate(LinkLayer::InfiniBand).unwrap();
+
+        let check = |info: ConnectionInfo, link_layer: LinkLayer, needle: &str| {
+            let err = info.validate(link_layer).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::InvalidConnectionInfo);
+            assert!(err.msg.contains(needle), "{}", err.msg);
+        };
+        let zero_qpn = ConnectionInfo { qp_num: 0, ..valid };
+        check(zero_qpn, LinkLayer::Ethernet, "QP number is zero");
+        check(zero_qpn, LinkLayer::InfiniBand, "QP number is zero");
+        let wide_qpn = ConnectionInfo {
+            qp_num: 0x100_0000,
+            ..valid
+        };
+        check(wide_qpn, LinkLayer::InfiniBand, "exceeds 24 bits");
+        let null_gid = ConnectionInfo {
+            gid: ibv_gid::default(),
+            ..valid
+        };
+        check(null_gid, LinkLayer::Ethernet, "GID");
+        null_gid.validate(LinkLayer::InfiniBand).unwrap();
+        let zero_lid = ConnectionInfo { lid: 0, ..valid };
+        check(zero_lid, LinkLayer::InfiniBand, "LID");
+        zero_lid.validate(LinkLayer::Ethernet).unwrap();
+    }
+
+    #[test]
+    fn rc_connection_loopback() {
+        let devices = Devices::available().unwrap();
+        let device = &devices[0];
+        let port = device.info().active_ports().next().unwrap();
+        let config = RcConnectionConfig {
+            send_depth: 4,
+            recv_depth: 4,
+            ..Default::default()
+        };
+
+        let mut a = RcConnection::new(device, &config).unwrap();
+        let mut b = RcConnection::new(device, &config).unwrap();
+        let (info_a, info_b) = (a.local_info(port).unwrap(), b.local_info(port).unwrap());
+        a.connect(port, &info_b).unwrap();
+        b.connect(port, &info_a).unwrap();
+        assert_eq!(a.qp().state().unwrap(), crate::QpState::Rts);
+
+        a.post_send(b"ping").unwrap();
+        let mut received = None;
+        for _ in 0..1_000_000 {
+            a.poll().unwrap();
+            if let Some(Event::Received(data)) = b.poll().unwrap().pop() {
+                received = Some(data);
+                break;
+            }
+        }
+        assert_eq!(received.as_deref(), Some(&b"ping"[..]));
+    }
+}
@@ -0,0 +1,207 @@
+//! # Completion channels
+//!
+//! [`CompletionChannel`] wraps an `ibv_comp_channel`, the file descriptor
+//! through which completion queues deliver notification events.
+
+use std::{
+    os::fd::{AsRawFd, RawFd},
+    sync::Arc,
+};
+
+#[cfg(feature = "tokio")]
+use {crate::Error, std::sync::OnceLock};
+
+use crate::{Device, ErrorKind, Result};
+
+/// Completion event channel with automatic cleanup.
+///
+/// Holds a reference to its [`Device`] so the context outlives the channel.
+/// Completion queues created with this channel must be destroyed before it.
+pub struct CompletionChannel {
+    channel: *mut crate::ibv_comp_channel,
+    #[cfg(feature = "tokio")]
+    async_fd: OnceLock<std::io::Result<tokio::io::unix::AsyncFd<RawFd>>>,
+    device: Arc<Device>,
+}
+
+unsafe impl Send for CompletionChannel {}
+unsafe impl Sync for CompletionChannel {}
+
+impl CompletionChannel {
+    /// Creates a completion channel on the device's context.
+    pub(crate) fn new(device: &Arc<Device>) -> Result<Self> {
+        let channel = unsafe { crate::ibv_create_comp_channel(device.context_ptr()) };
+        if channel.is_null() {
+            return Err(ErrorKind::IBCreateCompChannelFail.with_errno());
+        }
+        Ok(Self {
+            channel,
+            #[cfg(feature = "tokio")]
+            async_fd: OnceLock::new(),
+            device: device.clone(),
+        })
+    }
+
+    /// Returns the file descriptor of the channel.
+    ///
+    /// Same as [`AsRawFd::as_raw_fd`].
+    pub fn fd(&self) -> RawFd {
+        unsafe { (*self.channel).fd }
+    }
+
+    /// Returns the device this channel belongs to.
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    /// Switches the channel to non-blocking mode.
+    ///
+    /// [`get_cq_event`](Self::get_cq_event) then fails with `EAGAIN` instead
+    /// of blocking when no event is pending.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `fcntl` fails.
+    pub fn set_nonblocking(&self) -> Result<()> {
+        let fd = self.fd();
+        let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+        if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } < 0 {
+            return Err(ErrorKind::IBSetCompChannelNonBlockFail.with_errno());
+        }
+        Ok(())
+    }
+
+    /// Reads the next completion event and returns the notifying CQ.
+    ///
+    /// Every event must be acknowledged with `ibv_ack_cq_events` before the
+    /// CQ is destroyed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ibv_get_cq_event` fails, including `EAGAIN` on a
+    /// non-blocking channel with no pending event.
+    pub fn get_cq_event(&self) -> Result<*mut crate::ibv_cq> {
+        let mut cq = std::ptr::null_mut();
+        let mut cq_context = std::ptr::null_mut();
+        let ret = unsafe { crate::ibv_get_cq_event(self.channel, &mut cq, &mut cq_context) };
+        if ret == 0 {
+            Ok(cq)
+        } else {
+            Err(ErrorKind::IBGetCompQueueEventFail.with_errno())
+        }
+    }
+
+    /// Waits until a completion event can be read without blocking.
+    ///
+    /// The first call switches the channel to non-blocking mode and registers
+    /// its fd with the tokio reactor, so it must run inside a tokio runtime.
+    /// After this resolves, [`get_cq_event`](Self::get_cq_event) returns an
+    /// event.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the fd cannot be registered with the reactor.
+    #[cfg(feature = "tokio")]
+    pub async fn readable(&self) -> Result<()> {
+        let async_fd = self
+            .async_fd
+            .get_or_init(|| {
+                self.set_nonblocking()
+                    .map_err(|err| std::io::Error::other(err.to_string()))?;
+                tokio::io::unix::AsyncFd::with_interest(self.fd(), tokio::io::Interest::READABLE)
+            })
+            .as_ref()
+            .map_err(|err| Error::new(ErrorKind::IBGetCompQueueEventFail, err.to_string()))?;
+
+        loop {
+            let mut guard = async_fd
+                .readable()
+                .await
+                .map_err(|err| Error::new(ErrorKind::IBGetCompQueueEventFail, err.to_string()))?;
+            // Readiness is edge-triggered, so it's only cleared once the fd
+            // has been observed empty; otherwise queued events would be lost.
+            if self.has_pending_event() {
+                return Ok(());
+            }
+            guard.clear_ready();
+        }
+    }
+
+    /// Returns true if the fd has data to read, without blocking.
+    #[cfg(feature = "tokio")]
+    fn has_pending_event(&self) -> bool {
+        let mut pollfd = libc::pollfd {
+            fd: self.fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        unsafe { libc::poll(&mut pollfd, 1, 0) > 0 }
+    }
+
+    /// Returns the raw completion channel pointer.
+    ///
+    /// # Safety
+    ///
+    /// The returned pointer is only valid as long as this channel exists.
+    pub unsafe fn channel_ptr(&self) -> *mut crate::ibv_comp_channel {
+        self.channel
+    }
+}
+
+/// Exposes the channel fd for registration with an event loop such as
+/// epoll, mio or tokio's `AsyncFd`.
+///
+/// The fd stays owned by the channel and is closed when it is dropped.
+/// Closing it externally, or using it after the channel is gone, is
+/// undefined behavior for libibverbs; deregister it from the event loop
+/// before dropping the channel.
+impl AsRawFd for CompletionChannel {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd()
+    }
+}
+
+impl Drop for CompletionChannel {
+    fn drop(&mut self) {
+        // Deregister from the reactor before the fd is closed.
+        #[cfg(feature = "tokio")]
+        drop(self.async_fd.take());
+        let ret = unsafe { crate::ibv_destroy_comp_channel(self.channel) };
+        crate::check_cleanup("ibv_destroy_comp_channel", ret);
+    }
+}
+
+impl std::fmt::Debug for CompletionChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompletionChannel")
+            .field("fd", &self.fd())
+            .field("device", &self.device.info().name)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::fd::AsRawFd;
+
+    use crate::Devices;
+
+    #[test]
+    fn comp_channel_as_raw_fd() {
+        let devices = Devices::available().unwrap();
+        let channel = devices[0].create_comp_channel().unwrap();
+        let fd = channel.as_raw_fd();
+        assert_eq!(fd, channel.fd());
+        assert!(unsafe { libc::fcntl(fd, libc::F_GETFD) } >= 0);
+    }
+
+    #[test]
+    fn comp_channel_nonblocking() {
+        let devices = Devices::available().unwrap();
+        let channel = devices[0].create_comp_channel().unwrap();
+        assert!(channel.fd() >= 0);
+        channel.set_nonblocking().unwrap();
+        let err = channel.get_cq_event().unwrap_err();
+        assert_eq!(err.kind, crate::ErrorKind::IBGetCompQueueEventFail);
+    }
+}
@@ -0,0 +1,312 @@
+//! # Completion queues
+//!
+//! [`CompletionQueue`] wraps a plain `ibv_cq` polled with `ibv_poll_cq`,
+//! optionally attached to a [`CompletionChannel`] for event-driven waits.
+
+use std::{sync::Arc, time::Instant};
+
+use crate::{CompletionChannel, Device, Error, ErrorKind, Result, ibv_cq, ibv_wc};
+
+/// Completion queue with automatic cleanup.
+///
+/// Holds a reference to its [`Device`] so the context outlives the queue,
+/// and to its [`CompletionChannel`], if any, so the channel does too.
+/// Queue pairs using this CQ must be destroyed before it.
+pub struct CompletionQueue {
+    cq: *mut ibv_cq,
+    channel: Option<Arc<CompletionChannel>>,
+    device: Arc<Device>,
+}
+
+unsafe impl Send for CompletionQueue {}
+unsafe impl Sync for CompletionQueue {}
+
+impl CompletionQueue {
+    /// Creates a CQ with at least `cqe` entries, reporting events to `channel`.
+    ///
+    /// `cqe` is clamped to the device's `max_cqe`.
+    pub(crate) fn new(
+        device: &Arc<Device>,
+        cqe: u32,
+        channel: Option<&Arc<CompletionChannel>>,
+    ) -> Result<Self> {
+        let channel_ptr = channel.map_or(std::ptr::null_mut(), |channel| unsafe {
+            channel.channel_ptr()
+        });
+        let cq = unsafe {
+            crate::ibv_create_cq(
+                device.context_ptr(),
+                device.info().clamp_cqe(cqe) as _,
+                std::ptr::null_mut(),
+                channel_ptr,
+                0,
+            )
+        };
+        if cq.is_null() {
+            return Err(ErrorKind::IBCreateCompQueueFail.with_errno());
+        }
+        Ok(Self {
+            cq,
+            channel: channel.cloned(),
+            device: device.clone(),
+        })
+    }
+
+    /// Returns the number of entries the device actually allocated.
+    ///
+    /// At least the requested depth, unless that exceeded the device's
+    /// `max_cqe`.
+    pub fn cqe(&self) -> u32 {
+        unsafe { (*self.cq).cqe as u32 }
+    }
+
+    /// Returns the device this queue belongs to.
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    /// Polls up to `wcs.len()` completions and returns the polled prefix.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::IBPollCompQueueFail`] if `ibv_poll_cq` fails, or
+    /// [`ErrorKind::Unsupported`] if the provider has no `poll_cq`.
+    pub fn poll<'a>(&self, wcs: &'a mut [ibv_wc]) -> Result<&'a [ibv_wc]> {
+        let ret = unsafe { crate::ibv_poll_cq(self.cq, wcs.len() as _, wcs.as_mut_ptr()) };
+        if ret < 0 {
+            return Err(poll_error(ret));
+        }
+        Ok(&wcs[..ret as usize])
+    }
+
+    /// Busy-polls until `n` completions are in `wcs` or `deadline` passes.
+    ///
+    /// The CQ is polled at least once, even if `deadline` has already
+    /// passed. `n` is capped at `wcs.len()`.
+    ///
+    /// # Returns
+    ///
+    /// The number of completions written to the front of `wcs`, which is
+    /// less than `n` if the deadline passed first.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::IBPollCompQueueFail`] if `ibv_poll_cq` fails, or
+    /// [`ErrorKind::Unsupported`] if the provider has no `poll_cq`.
+    pub fn poll_n(&self, wcs: &mut [ibv_wc], n: usize, deadline: Instant) -> Result<usize> {
+        poll_until(wcs, n, deadline, |wcs| unsafe {
+            crate::ibv_poll_cq(self.cq, wcs.len() as _, wcs.as_mut_ptr())
+        })
+    }
+
+    /// Polls completions, sleeping on `channel` while the CQ is empty.
+    ///
+    /// Implements the canonical event-driven loop: poll, and if nothing is
+    /// there, arm the CQ with `ibv_req_notify_cq` and poll again before
+    /// waiting. The second poll closes the race where a completion arrives
+    /// after the first poll but before the CQ is armed; such a completion
+    /// raises no event, so waiting right away could sleep forever with work
+    /// pending. Each event is acknowledged with `ibv_ack_cq_events` before
+    /// polling again.
+    ///
+    /// The CQ must have been created with `channel`, and the channel should
+    /// be dedicated to it: events for other CQs are acknowledged and
+    /// dropped. On a non-blocking channel this returns the `EAGAIN` error
+    /// of [`CompletionChannel::get_cq_event`] instead of sleeping.
+    ///
+    /// # Returns
+    ///
+    /// The number of completions written to the front of `wcs`, at least
+    /// one unless `wcs` is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CQ was not created with `channel`, or if
+    /// polling, arming or reading an event fails.
+    pub fn wait_and_poll(&self, channel: &CompletionChannel, wcs: &mut [ibv_wc]) -> Result<usize> {
+        if unsafe { (*self.cq).channel != channel.channel_ptr() } {
+            return Err(Error::new(
+                ErrorKind::IBGetCompQueueEventFail,
+                "CQ was not created with this completion channel".to_string(),
+            ));
+        }
+        if wcs.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let count = self.poll(wcs)?.len();
+            if count > 0 {
+                return Ok(count);
+            }
+
+            // Returns the errno instead of setting it
+            let ret = unsafe { crate::ibv_req_notify_cq(self.cq, 0) };
+            if ret != 0 {
+                return Err(Error::from_errno(ErrorKind::IBReqNotifyCompQueueFail, ret));
+            }
+            // Completions that landed before arming raise no event
+            let count = self.poll(wcs)?.len();
+            if count > 0 {
+                return Ok(count);
+            }
+
+            let cq = channel.get_cq_event()?;
+            unsafe { crate::ibv_ack_cq_events(cq, 1) };
+        }
+    }
+
+    /// Returns the raw completion queue pointer.
+    ///
+    /// # Safety
+    ///
+    /// The returned pointer is only valid as long as this `CompletionQueue` exists.
+    pub unsafe fn cq_ptr(&self) -> *mut ibv_cq {
+        self.cq
+    }
+}
+
+/// Converts a negative `ibv_poll_cq` result into an error.
+///
+/// [`ibv_poll_cq`](crate::ibv_poll_cq) returns `-EOPNOTSUPP` if the
+/// provider leaves the op null.
+fn poll_error(ret: libc::c_int) -> Error {
+    if ret == -libc::EOPNOTSUPP {
+        return Error::from_errno(
+            ErrorKind::Unsupported("poll_cq".to_string()),
+            libc::EOPNOTSUPP,
+        );
+    }
+    Error::new(
+        ErrorKind::IBPollCompQueueFail,
+        format!("ibv_poll_cq returned {ret}"),
+    )
+}
+
+/// Accumulates completions from `poll` until `n` or `deadline`.
+fn poll_until<F>(wcs: &mut [ibv_wc], n: usize, deadline: Instant, mut poll: F) -> Result<usize>
+where
+    F: FnMut(&mut [ibv_wc]) -> libc::c_int,
+{
+    let n = n.min(wcs.len());
+    let mut count = 0;
+    loop {
+        let ret = poll(&mut wcs[count..n]);
+        if ret < 0 {
+            return Err(poll_error(ret));
+        }
+        count += ret as usize;
+        if count >= n || Instant::now() >= deadline {
+            return Ok(count);
+        }
+        std::hint::spin_loop();
+    }
+}
+
+impl Drop for CompletionQueue {
+    fn drop(&mut self) {
+        crate::check_cleanup("ibv_destroy_cq", unsafe { crate::ibv_destroy_cq(self.cq) });
+    }
+}
+
+impl std::fmt::Debug for CompletionQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompletionQueue")
+            .field("cqe", &unsafe { (*self.cq).cqe })
+            .field("has_channel", &self.channel.is_some())
+            .field("device", &self.device.info().name)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn poll_until_count() {
+        let mut wcs = [ibv_wc::default(); 4];
+        let deadline = Instant::now() + Duration::from_secs(60);
+        let mut calls = 0;
+        let count = poll_until(&mut wcs, 3, deadline, |wcs| {
+            calls += 1;
+            wcs[0].byte_len = calls;
+            1
+        })
+        .unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(calls, 3);
+        assert_eq!(wcs.map(|wc| wc.byte_len), [1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn poll_until_deadline() {
+        let mut wcs = [ibv_wc::default(); 4];
+        let deadline = Instant::now() + Duration::from_millis(10);
+        let count = poll_until(&mut wcs, 2, deadline, |_| 0).unwrap();
+        assert_eq!(count, 0);
+        assert!(Instant::now() >= deadline);
+
+        // An expired deadline still polls once
+        let count = poll_until(&mut wcs, 2, deadline, |_| 1).unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn poll_until_error() {
+        let mut wcs = [ibv_wc::default(); 1];
+        let err = poll_until(&mut wcs, 1, Instant::now(), |_| -1).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::IBPollCompQueueFail);
+
+        // A provider without poll_cq
+        let err = poll_until(&mut wcs, 1, Instant::now(), |_| -libc::EOPNOTSUPP).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Unsupported("poll_cq".to_string()));
+        assert_eq!(err.errno, Some(libc::EOPNOTSUPP));
+    }
+
+    #[test]
+    fn wait_and_poll_nonblocking() {
+        let devices = crate::Devices::available().unwrap();
+        let device = &devices[0];
+        let channel = Arc::new(device.create_comp_channel().unwrap());
+        channel.set_nonblocking().unwrap();
+        let cq = device.create_cq_with_channel(16, &channel).unwrap();
+
+        // Nothing is pending, so the armed CQ would sleep; non-blocking fails
+        let mut wcs = [ibv_wc::default(); 4];
+        let err = cq.wait_and_poll(&channel, &mut wcs).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::IBGetCompQueueEventFail);
+
+        let other = device.create_cq(16).unwrap();
+        assert!(other.wait_and_poll(&channel, &mut wcs).is_err());
+    }
+
+    #[test]
+    fn create_cq_clamped() {
+        let devices = crate::Devices::available().unwrap();
+        let device = &devices[0];
+        let max_cqe = device.info().max_cqe();
+        assert!(max_cqe > 0);
+
+        let cq = device.create_cq(u32::MAX).unwrap();
+        assert!(cq.cqe() <= max_cqe);
+        let cq = device.create_cq(16).unwrap();
+        assert!(cq.cqe() >= 16);
+    }
+
+    #[test]
+    fn create_cq_default_size() {
+        let devices = crate::Devices::available().unwrap();
+        let device = &devices[0];
+        assert_eq!(device.default_cq_size(), crate::DEFAULT_CQ_SIZE);
+        let cq = device.create_cq(None).unwrap();
+        let expected = device.info().clamp_cqe(crate::DEFAULT_CQ_SIZE);
+        assert!(cq.cqe() >= expected);
+
+        let config = crate::DeviceConfig::default().with_default_cq_size(u32::MAX);
+        let device = crate::Device::open_first(&config).unwrap();
+        let cq = device.create_cq(None).unwrap();
+        assert!(cq.cqe() <= device.info().max_cqe());
+    }
+}
@@ -0,0 +1,233 @@
+//! # Extended completion queues
+//!
+//! [`CompletionQueueEx`] wraps an `ibv_cq_ex` created with completion
+//! timestamps enabled, for measuring latency with hardware clocks.
+
+use std::sync::Arc;
+
+use crate::{
+    Device, Error, ErrorKind, Result, WRID, ibv_cq_ex, ibv_create_cq_wc_flags, ibv_wc_opcode,
+    ibv_wc_status,
+};
+
+/// Extended completion queue with hardware completion timestamps.
+///
+/// Holds a reference to its [`Device`] so the context outlives the queue.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use ruapc_rdma_sys::Devices;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let devices = Devices::available()?;
+/// let mut cq = devices[0].create_cq_ex(256)?;
+/// if let Some(mut poll) = cq.poll()? {
+///     loop {
+///         let ts = poll.read_timestamp();
+///         println!("{:?} at {:?}ns", poll.wr_id(), poll.cq().ticks_to_ns(ts));
+///         if !poll.advance()? {
+///             break;
+///         }
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct CompletionQueueEx {
+    cq: *mut ibv_cq_ex,
+    hca_core_clock: u64,
+    device: Arc<Device>,
+}
+
+unsafe impl Send for CompletionQueueEx {}
+unsafe impl Sync for CompletionQueueEx {}
+
+impl CompletionQueueEx {
+    /// Creates an extended CQ with at least `cqe` entries.
+    ///
+    /// `cqe` is clamped to the device's `max_cqe`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::Unsupported`] if the device cannot timestamp
+    /// completions or the provider lacks `create_cq_ex`, or
+    /// [`ErrorKind::IBCreateCompQueueFail`] if `ibv_create_cq_ex` fails.
+    pub(crate) fn new(device: &Arc<Device>, cqe: u32) -> Result<Self> {
+        let attr = device.query_device_ex()?;
+        if attr.completion_timestamp_mask == 0 || attr.hca_core_clock == 0 {
+            return Err(Error::new(
+                ErrorKind::Unsupported("completion timestamps".to_string()),
+                format!("device {} does not report a core clock", device.info().name),
+            ));
+        }
+
+        let mut init_attr = crate::ibv_cq_init_attr_ex {
+            cqe: device.info().clamp_cqe(cqe),
+            wc_flags: (ibv_create_cq_wc_flags::IBV_WC_EX_WITH_BYTE_LEN
+                | ibv_create_cq_wc_flags::IBV_WC_EX_WITH_COMPLETION_TIMESTAMP)
+                .0 as u64,
+            ..Default::default()
+        };
+        let cq = unsafe { crate::ibv_create_cq_ex(device.context_ptr(), &mut init_attr) };
+        if cq.is_null() {
+            let err = ErrorKind::IBCreateCompQueueFail.with_errno();
+            if err.errno == Some(libc::EOPNOTSUPP) {
+                // The provider lacks the create_cq_ex op
+                return Err(Error {
+                    kind: ErrorKind::Unsupported("create_cq_ex".to_string()),
+                    ..err
+                });
+            }
+            return Err(err);
+        }
+        Ok(Self {
+            cq,
+            hca_core_clock: attr.hca_core_clock,
+            device: device.clone(),
+        })
+    }
+
+    /// Returns the device this queue belongs to.
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    /// Returns the HCA clock frequency in kHz.
+    pub fn hca_core_clock(&self) -> u64 {
+        self.hca_core_clock
+    }
+
+    /// Converts a timestamp from HCA clock ticks to nanoseconds.
+    ///
+    /// Returns `None` on overflow.
+    pub fn ticks_to_ns(&self, ticks: u64) -> Option<u64> {
+        let ns = u128::from(ticks) * 1_000_000 / u128::from(self.hca_core_clock);
+        u64::try_from(ns).ok()
+    }
+
+    /// Starts a polling session.
+    ///
+    /// Returns `None` if the queue is empty. Otherwise the returned
+    /// [`CqExPoll`] points at the first completion and ends the session when
+    /// dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ibv_start_poll` fails.
+    pub fn poll(&mut self) -> Result<Option<CqExPoll<'_>>> {
+        let mut attr = crate::ibv_poll_cq_attr::default();
+        match unsafe { crate::ibv_start_poll(self.cq, &mut attr) } {
+            0 => Ok(Some(CqExPoll { cq: self })),
+            libc::ENOENT => Ok(None),
+            ret => Err(Error::from_errno(ErrorKind::IBPollCompQueueFail, ret)),
+        }
+    }
+
+    /// Returns the raw extended completion queue pointer.
+    ///
+    /// # Safety
+    ///
+    /// The returned pointer is only valid as long as this queue exists.
+    pub unsafe fn cq_ptr(&self) -> *mut ibv_cq_ex {
+        self.cq
+    }
+}
+
+impl Drop for CompletionQueueEx {
+    fn drop(&mut self) {
+        let ret = unsafe { crate::ibv_destroy_cq(crate::ibv_cq_ex_to_cq(self.cq)) };
+        crate::check_cleanup("ibv_destroy_cq", ret);
+    }
+}
+
+impl std::fmt::Debug for CompletionQueueEx {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompletionQueueEx")
+            .field("hca_core_clock", &self.hca_core_clock)
+            .field("device", &self.device.info().name)
+            .finish()
+    }
+}
+
+/// An active polling session on a [`CompletionQueueEx`].
+///
+/// Reads refer to the current completion; [`advance`](Self::advance) advances.
+/// The session is ended with `ibv_end_poll` on drop.
+pub struct CqExPoll<'a> {
+    cq: &'a mut CompletionQueueEx,
+}
+
+impl CqExPoll<'_> {
+    /// Returns the queue being polled.
+    pub fn cq(&self) -> &CompletionQueueEx {
+        self.cq
+    }
+
+    /// Returns the status of the current completion.
+    pub fn status(&self) -> ibv_wc_status {
+        unsafe { (*self.cq.cq).status }
+    }
+
+    /// Returns the work request ID of the current completion.
+    pub fn wr_id(&self) -> WRID {
+        unsafe { (*self.cq.cq).wr_id }
+    }
+
+    /// Returns the opcode of the current completion.
+    pub fn opcode(&self) -> ibv_wc_opcode {
+        unsafe { crate::ibv_wc_read_opcode(self.cq.cq) }
+    }
+
+    /// Returns the byte length of the current completion.
+    pub fn byte_len(&self) -> u32 {
+        unsafe { crate::ibv_wc_read_byte_len(self.cq.cq) }
+    }
+
+    /// Returns the hardware timestamp of the current completion in HCA clock
+    /// ticks, see [`CompletionQueueEx::ticks_to_ns`].
+    pub fn read_timestamp(&self) -> u64 {
+        unsafe { crate::ibv_wc_read_completion_ts(self.cq.cq) }
+    }
+
+    /// Advances to the next completion.
+    ///
+    /// Returns `false` if there are no more completions.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ibv_next_poll` fails.
+    pub fn advance(&mut self) -> Result<bool> {
+        match unsafe { crate::ibv_next_poll(self.cq.cq) } {
+            0 => Ok(true),
+            libc::ENOENT => Ok(false),
+            ret => Err(Error::from_errno(ErrorKind::IBPollCompQueueFail, ret)),
+        }
+    }
+}
+
+impl Drop for CqExPoll<'_> {
+    fn drop(&mut self) {
+        unsafe { crate::ibv_end_poll(self.cq.cq) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Devices;
+
+    #[test]
+    fn create_cq_ex() {
+        let devices = Devices::available().unwrap();
+        let device = &devices[0];
+        match device.create_cq_ex(16) {
+            Ok(mut cq) => {
+                assert!(device.supports_completion_timestamps());
+                assert!(cq.poll().unwrap().is_none());
+            }
+            Err(err) => {
+                assert!(!device.supports_completion_timestamps());
+                assert!(err.kind.is_unsupported(), "{err}");
+            }
+        }
+    }
+}
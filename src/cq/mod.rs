@@ -0,0 +1,38 @@
+//! # Completion queues
+//!
+//! This module wraps the completion side of the verbs API.
+//!
+//! ## Module Organization
+//!
+//! - [`channel.rs`](channel): Completion event channel with optional async wait
+//! - [`completion_queue.rs`](completion_queue): Plain completion queue polled with `ibv_poll_cq`
+//! - [`cq_ex.rs`](cq_ex): Extended completion queue with hardware timestamps
+//!
+//! ## Example
+//!
+//! With the `tokio` feature enabled, completion events can be awaited
+//! instead of blocking a thread:
+//!
+//! ```rust,no_run
+//! # #[cfg(feature = "tokio")]
+//! # async fn run() -> ruapc_rdma_sys::Result<()> {
+//! use ruapc_rdma_sys::Devices;
+//!
+//! let devices = Devices::available()?;
+//! let channel = devices[0].create_comp_channel()?;
+//! loop {
+//!     channel.readable().await?;
+//!     let cq = channel.get_cq_event()?;
+//!     // Acknowledge the event, re-arm with ibv_req_notify_cq and poll `cq`.
+//!     unsafe { ruapc_rdma_sys::ibv_ack_cq_events(cq, 1) };
+//! }
+//! # }
+//! ```
+
+mod channel;
+mod completion_queue;
+mod cq_ex;
+
+pub use channel::CompletionChannel;
+pub use completion_queue::CompletionQueue;
+pub use cq_ex::{CompletionQueueEx, CqExPoll};
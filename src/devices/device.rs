@@ -9,12 +9,24 @@
 //! - `ibv_context` is closed via `ibv_close_device` on drop
 //! - `ibv_pd` is deallocated via `ibv_dealloc_pd` on drop
 //!
+//! The PD is always deallocated before its context is closed; `Drop for Device`
+//! enforces this explicitly instead of relying on field declaration order.
 //! This ensures proper cleanup even when errors occur during initialization or use.
 
-use std::{ffi::CStr, os::unix::ffi::OsStrExt, path::Path};
+use std::{
+    ffi::{CStr, c_void},
+    mem::ManuallyDrop,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use super::{raw::*, types::*};
-use crate::{DeviceConfig, ErrorKind, GidType, Guid, Result};
+use crate::{
+    CompletionChannel, CompletionQueue, CompletionQueueEx, DeviceConfig, ErrorKind, GidType, Guid,
+    MemoryRegion, MemoryRegistry, OpsAvailability, QueuePair, RegisteredBuffer, Result,
+    TransportType, ibv_access_flags, ibv_device_attr, ibv_gid, ibv_qp_init_attr,
+};
 
 /// RDMA device handle.
 ///
@@ -34,10 +46,13 @@ use crate::{DeviceConfig, ErrorKind, GidType, Guid, Result};
 /// # }
 /// ```
 pub struct Device {
-    protection_domain: RawProtectionDomain,
-    context: RawContext,
+    // Released in `Drop for Device`, PD before context
+    protection_domain: ManuallyDrop<RawProtectionDomain>,
+    context: ManuallyDrop<RawContext>,
     device: *mut crate::ibv_device,
     info: DeviceInfo,
+    memory_registry: MemoryRegistry,
+    default_cq_size: u32,
 }
 
 unsafe impl Send for Device {}
@@ -58,6 +73,40 @@ impl Device {
         }
     }
 
+    /// Returns the sysfs path of the device from a raw device pointer.
+    ///
+    /// # Safety
+    ///
+    /// The `device` pointer must be valid and obtained from `ibv_get_device_list`.
+    pub(crate) unsafe fn device_path(device: *mut crate::ibv_device) -> PathBuf {
+        // SAFETY: caller guarantees device pointer is valid
+        unsafe {
+            Path::new(std::ffi::OsStr::from_bytes(
+                CStr::from_ptr((*device).ibdev_path.as_ptr()).to_bytes(),
+            ))
+        }
+        .to_path_buf()
+    }
+
+    /// Returns the transport type from a raw device pointer.
+    ///
+    /// The field is read as a plain integer, so values added by a newer
+    /// libibverbs map to [`TransportType::Unknown`] instead of an invalid
+    /// enum.
+    ///
+    /// # Safety
+    ///
+    /// The `device` pointer must be valid and obtained from `ibv_get_device_list`.
+    pub(crate) unsafe fn transport_type(device: *mut crate::ibv_device) -> TransportType {
+        // SAFETY: caller guarantees device pointer is valid; the C enum is an int
+        let raw = unsafe {
+            std::ptr::addr_of!((*device).transport_type)
+                .cast::<i32>()
+                .read()
+        };
+        TransportType::from_raw(raw)
+    }
+
     /// Opens a device by raw pointer and initializes its protection domain.
     pub(crate) fn open(
         device: *mut crate::ibv_device,
@@ -66,12 +115,9 @@ impl Device {
     ) -> Result<Self> {
         let name = unsafe { Self::device_name(device) };
         let guid = Guid::from_be(unsafe { crate::ibv_get_device_guid(device) });
-        let ibdev_path = unsafe {
-            Path::new(std::ffi::OsStr::from_bytes(
-                CStr::from_ptr((*device).ibdev_path.as_ptr()).to_bytes(),
-            ))
-        }
-        .to_path_buf();
+        let ibdev_path = unsafe { Self::device_path(device) };
+        let board_id = DeviceInfo::read_board_id(&ibdev_path);
+        let transport_type = unsafe { Self::transport_type(device) };
 
         let context = RawContext(unsafe {
             let ctx = crate::ibv_open_device(device);
@@ -89,23 +135,52 @@ impl Device {
             pd
         });
 
+        let mut info = DeviceInfo::default();
+        info.index = index;
+        info.name = name;
+        info.guid = guid;
+        info.ibdev_path = ibdev_path;
+        info.board_id = board_id;
+        info.transport_type = transport_type;
+
         let mut this = Self {
-            protection_domain,
-            context,
+            protection_domain: ManuallyDrop::new(protection_domain),
+            context: ManuallyDrop::new(context),
             device,
-            info: DeviceInfo {
-                index,
-                name,
-                guid,
-                ibdev_path,
-                ..Default::default()
-            },
+            info,
+            memory_registry: MemoryRegistry::default(),
+            default_cq_size: config.default_cq_size,
         };
         this.update_attr(config)?;
 
         Ok(this)
     }
 
+    /// Opens the first device matching `config`.
+    ///
+    /// Enumeration stops at the first match, so the remaining devices are
+    /// never opened. This is the simplest entry point for single-NIC
+    /// applications; use [`Devices::open`](super::Devices::open) to get all
+    /// matching devices.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::IBDeviceNotFound`] if no device matches, or an
+    /// error if enumeration or opening fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ruapc_rdma_sys::{Device, DeviceConfig};
+    /// let device = Device::open_first(&DeviceConfig::default().with_device("mlx5_0"))?;
+    /// println!("Opened {}", device.info().name);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn open_first(config: &DeviceConfig) -> Result<Arc<Device>> {
+        let mut devices = super::Devices::open_matching(config, |_| true, true)?;
+        Ok(devices.swap_remove(0))
+    }
+
     /// Updates device attributes by querying the hardware.
     pub fn update_attr(&mut self, config: &DeviceConfig) -> Result<()> {
         let device_attr = self.context.query_device()?;
@@ -113,26 +188,47 @@ impl Device {
         let mut ports = Vec::with_capacity(device_attr.phys_port_cnt as usize);
         for port_num in 1..=device_attr.phys_port_cnt {
             let port_attr = self.context.query_port(port_num)?;
-            if port_attr.state != crate::ibv_port_state::IBV_PORT_ACTIVE
-                && config.skip_inactive_port
-            {
+            if !config.accepts_port(&port_attr) {
                 continue;
             }
 
-            let gids = self.collect_port_gids(port_num, &port_attr, config);
-            ports.push(Port {
+            let pkeys = self.collect_port_pkeys(port_num, &port_attr);
+            let mut port = Port {
                 port_num,
                 port_attr,
-                gids,
-            });
+                gids: self.collect_port_gids(port_num, &port_attr, config),
+                pkeys,
+            };
+            if config.dedup_gids {
+                port.dedup_gids();
+            }
+            if let Some(pkey) = config.pkey_filter
+                && !port.has_pkey(pkey)
+            {
+                continue;
+            }
+            ports.push(port);
         }
 
         self.info.device_attr = device_attr;
-        self.info.ports = ports;
+        // Providers without query_device_ex report no clock
+        self.info.hca_core_clock = self
+            .context
+            .query_device_ex()
+            .map_or(0, |attr| attr.hca_core_clock);
+        self.info.set_ports(ports);
 
         Ok(())
     }
 
+    /// Collects the valid (non-zero) P_Keys of a port.
+    fn collect_port_pkeys(&self, port_num: u8, port_attr: &crate::ibv_port_attr) -> Vec<u16> {
+        (0..port_attr.pkey_tbl_len)
+            .filter_map(|pkey_index| self.context.query_pkey(port_num, pkey_index).ok())
+            .filter(|&pkey| pkey != 0)
+            .collect()
+    }
+
     /// Collects GIDs for a port after applying filters.
     fn collect_port_gids(
         &self,
@@ -140,38 +236,12 @@ impl Device {
         port_attr: &crate::ibv_port_attr,
         config: &DeviceConfig,
     ) -> Vec<Gid> {
-        let mut gids = Vec::with_capacity(port_attr.gid_tbl_len as usize);
-        for gid_index in 0..port_attr.gid_tbl_len as u16 {
-            let Ok(gid) = self.context.query_gid(port_num, gid_index) else {
-                continue;
-            };
-            let Ok(gid_type) =
-                self.context
-                    .query_gid_type(port_num, gid_index, &self.info.ibdev_path, port_attr)
-            else {
-                continue;
-            };
-
-            // Apply GID type filter
-            if !config.gid_type_filter.is_empty() && !config.gid_type_filter.contains(&gid_type) {
-                continue;
-            }
-
-            // Skip RoCEv2 link-local addresses if configured
-            if config.roce_v2_skip_link_local_addr && gid_type == GidType::RoCEv2 {
-                let ip = gid.as_ipv6();
-                if ip.is_unicast_link_local() {
-                    continue;
-                }
-            }
-
-            gids.push(Gid {
-                index: gid_index,
-                gid,
-                gid_type,
-            })
-        }
-        gids
+        let gids = (0..port_attr.gid_tbl_len as u16).filter_map(|gid_index| {
+            let gid = self.context.query_gid(port_num, gid_index).ok()?;
+            Some((gid_index, gid))
+        });
+        let mut types = GidTypeTable::new(&self.info.ibdev_path, port_num, port_attr.link_layer);
+        select_gids(gids, &mut types, config)
     }
 
     /// Returns the raw device pointer.
@@ -192,6 +262,16 @@ impl Device {
         self.context.0
     }
 
+    /// Reports which provider ops the device context implements.
+    ///
+    /// The fast-path wrappers such as [`ibv_poll_cq`](crate::ibv_poll_cq)
+    /// fail with `EOPNOTSUPP` when their op is missing; check
+    /// [`OpsAvailability::data_path`] once to reject an unsuitable provider
+    /// up front.
+    pub fn ops_available(&self) -> OpsAvailability {
+        unsafe { OpsAvailability::from_context(self.context.0) }
+    }
+
     /// Returns the raw protection domain pointer.
     ///
     /// # Safety
@@ -205,7 +285,8 @@ impl Device {
     ///
     /// # Returns
     ///
-    /// The zero-based index of this device in the system.
+    /// The zero-based position of this device in the `ibv_get_device_list`
+    /// result, see [`DeviceInfo::index`].
     pub fn index(&self) -> usize {
         self.info.index
     }
@@ -218,6 +299,249 @@ impl Device {
     pub fn info(&self) -> &DeviceInfo {
         &self.info
     }
+
+    /// Returns the device attributes, see [`DeviceInfo::device_attr`].
+    pub fn device_attr(&self) -> &ibv_device_attr {
+        self.info.device_attr()
+    }
+
+    /// Returns an owned copy of the device information.
+    ///
+    /// The snapshot is plain data: it holds no reference to the device, so
+    /// it can be sent to other threads or kept after the device is closed.
+    /// Only the [`Device`] itself, and the resources created from it, keep
+    /// the verbs context and protection domain open. Later calls to
+    /// [`update_attr`](Self::update_attr) are not reflected in it.
+    pub fn snapshot(&self) -> DeviceInfo {
+        self.info.clone()
+    }
+
+    /// Returns the registry of live memory registrations on this device.
+    pub fn memory_registry(&self) -> &MemoryRegistry {
+        &self.memory_registry
+    }
+
+    /// Registers an owned buffer with this device's protection domain.
+    ///
+    /// The buffer is moved into the returned [`RegisteredBuffer`], which keeps
+    /// it alive while registered and records it in the
+    /// [`memory_registry`](Self::memory_registry).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ibv_reg_mr` fails.
+    pub fn register_memory(
+        self: &Arc<Self>,
+        buf: impl Into<Box<[u8]>>,
+        access: ibv_access_flags,
+    ) -> Result<RegisteredBuffer> {
+        RegisteredBuffer::new(self, buf.into(), access)
+    }
+
+    /// Registers `len` bytes at `addr` with this device's protection domain.
+    ///
+    /// For memory that is not a Rust-owned buffer, e.g. from another
+    /// allocator or a C library; prefer
+    /// [`register_memory`](Self::register_memory) otherwise. The region is
+    /// recorded in the [`memory_registry`](Self::memory_registry) like any
+    /// other, but the crate never frees the memory: release it yourself
+    /// after dropping the region.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ibv_reg_mr` fails.
+    ///
+    /// # Safety
+    ///
+    /// `addr..addr + len` must be valid memory that stays allocated and
+    /// mapped until the returned [`MemoryRegion`] is dropped. With remote
+    /// access flags, peers may write to it at any time while registered.
+    pub unsafe fn register_raw(
+        self: &Arc<Self>,
+        addr: *mut c_void,
+        len: usize,
+        access: ibv_access_flags,
+    ) -> Result<MemoryRegion> {
+        unsafe { MemoryRegion::register(self, addr, len, access) }
+    }
+
+    /// Queries the extended device attributes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ibv_query_device_ex` fails.
+    pub fn query_device_ex(&self) -> Result<crate::ibv_device_attr_ex> {
+        self.context.query_device_ex()
+    }
+
+    /// Returns true if the device can timestamp completions.
+    pub fn supports_completion_timestamps(&self) -> bool {
+        self.query_device_ex()
+            .is_ok_and(|attr| attr.completion_timestamp_mask != 0 && attr.hca_core_clock != 0)
+    }
+
+    /// Creates a completion queue with at least `cqe` entries.
+    ///
+    /// `None` uses the [`DeviceConfig::default_cq_size`] the device was
+    /// opened with. Requests beyond [`DeviceInfo::max_cqe`] are clamped to it
+    /// instead of failing; check [`CompletionQueue::cqe`] for the allocated
+    /// depth.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ibv_create_cq` fails.
+    pub fn create_cq(self: &Arc<Self>, cqe: impl Into<Option<u32>>) -> Result<CompletionQueue> {
+        CompletionQueue::new(self, cqe.into().unwrap_or(self.default_cq_size), None)
+    }
+
+    /// Returns the completion queue depth used when none is requested.
+    pub fn default_cq_size(&self) -> u32 {
+        self.default_cq_size
+    }
+
+    /// Creates a completion queue that reports events to `channel`.
+    ///
+    /// The queue keeps `channel` alive. Wait for completions with
+    /// [`CompletionQueue::wait_and_poll`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ibv_create_cq` fails.
+    pub fn create_cq_with_channel(
+        self: &Arc<Self>,
+        cqe: u32,
+        channel: &Arc<CompletionChannel>,
+    ) -> Result<CompletionQueue> {
+        CompletionQueue::new(self, cqe, Some(channel))
+    }
+
+    /// Creates an extended completion queue that timestamps completions.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::Unsupported`] if the device does not support
+    /// completion timestamps, or an error if `ibv_create_cq_ex` fails.
+    pub fn create_cq_ex(self: &Arc<Self>, cqe: u32) -> Result<CompletionQueueEx> {
+        CompletionQueueEx::new(self, cqe)
+    }
+
+    /// Registers an owned buffer that peers and SGEs address as `iova`.
+    ///
+    /// Uses `ibv_reg_mr_iova2`, so the region can be placed at any I/O
+    /// virtual address regardless of where the buffer lives; the
+    /// [`memory_registry`](Self::memory_registry) is keyed by `iova`.
+    ///
+    /// For on-demand paging, combine `IBV_ACCESS_ON_DEMAND` with
+    /// `IBV_ACCESS_LOCAL_WRITE` and any remote access flags needed; pages are
+    /// then faulted in on access instead of being pinned at registration.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::Unsupported`] if `IBV_ACCESS_ON_DEMAND` is
+    /// requested on a device without ODP support, or
+    /// [`ErrorKind::IBRegMemoryRegionFail`] if registration fails.
+    pub fn register_memory_iova(
+        self: &Arc<Self>,
+        buf: impl Into<Box<[u8]>>,
+        iova: u64,
+        access: ibv_access_flags,
+    ) -> Result<RegisteredBuffer> {
+        RegisteredBuffer::with_iova(self, buf.into(), iova, access)
+    }
+
+    /// Returns true if the device supports on-demand paging.
+    pub fn supports_odp(&self) -> bool {
+        self.query_device_ex().is_ok_and(|attr| {
+            attr.odp_caps.general_caps & crate::ibv_odp_general_caps::IBV_ODP_SUPPORT as u64 != 0
+        })
+    }
+
+    /// Creates a queue pair on this device's protection domain.
+    ///
+    /// `init_attr` is typically produced by
+    /// [`QpInitAttrBuilder::build`](crate::QpInitAttrBuilder::build).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ibv_create_qp` or the follow-up `ibv_query_qp`
+    /// fails.
+    ///
+    /// # Safety
+    ///
+    /// The CQs and SRQ referenced by `init_attr` must be valid and outlive
+    /// the returned queue pair.
+    pub unsafe fn create_qp(self: &Arc<Self>, init_attr: &ibv_qp_init_attr) -> Result<QueuePair> {
+        unsafe { QueuePair::new(self, init_attr) }
+    }
+
+    /// Creates a completion event channel on this device.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ibv_create_comp_channel` fails.
+    pub fn create_comp_channel(self: &Arc<Self>) -> Result<CompletionChannel> {
+        CompletionChannel::new(self)
+    }
+}
+
+/// Classifies the queried `gids` of a port and applies the GID filters of
+/// `config`.
+///
+/// Unused entries read back as the null GID and, unless
+/// [`DeviceConfig::include_null_gids`] is set, are skipped before their type
+/// is looked up, so a mostly empty 256-entry table costs one sysfs read per
+/// populated entry rather than one per index. Included null GIDs are still
+/// dropped if their type cannot be read.
+fn select_gids(
+    gids: impl IntoIterator<Item = (u16, ibv_gid)>,
+    types: &mut GidTypeTable,
+    config: &DeviceConfig,
+) -> Vec<Gid> {
+    let mut selected = Vec::new();
+    for (gid_index, gid) in gids {
+        if gid.is_null() && !config.include_null_gids {
+            continue;
+        }
+        let Ok(gid_type) = types.get(gid_index, &config.gid_type_overrides) else {
+            continue;
+        };
+
+        // Apply GID type filter
+        if !config.gid_type_filter.is_empty() && !config.gid_type_filter.contains(&gid_type) {
+            continue;
+        }
+
+        // Skip RoCEv2 link-local addresses if configured
+        if config.roce_v2_skip_link_local_addr && gid_type == GidType::RoCEv2 && gid.is_link_local()
+        {
+            continue;
+        }
+
+        selected.push(Gid {
+            index: gid_index,
+            gid,
+            gid_type,
+        })
+    }
+    selected
+}
+
+impl From<&Device> for DeviceInfo {
+    /// Equivalent to [`Device::snapshot`].
+    fn from(device: &Device) -> Self {
+        device.snapshot()
+    }
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        // SAFETY: each field is dropped exactly once, here. The PD belongs to
+        // the context, so it must be deallocated before the context is closed.
+        unsafe {
+            ManuallyDrop::drop(&mut self.protection_domain);
+            ManuallyDrop::drop(&mut self.context);
+        }
+    }
 }
 
 impl std::fmt::Debug for Device {
@@ -225,3 +549,181 @@ impl std::fmt::Debug for Device {
         std::fmt::Debug::fmt(&self.info, f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{GidTypeTable, select_gids};
+    use crate::devices::fixtures::TempSysfs;
+    use crate::{
+        Device, DeviceConfig, Devices, ErrorKind, GidType, LinkLayer, ibv_access_flags, ibv_gid,
+    };
+
+    #[test]
+    fn test_select_gids_sysfs_reads() {
+        let sysfs = TempSysfs::new("gid-types");
+        for index in 0..256u16 {
+            let content = if index % 2 == 0 {
+                "IB/RoCE v1\n"
+            } else {
+                "RoCE v2\n"
+            };
+            sysfs.write(format!("ports/1/gid_attrs/types/{index}"), content);
+        }
+        let ibdev_path = sysfs.path();
+
+        // A 256-entry table with four populated entries
+        let gid =
+            |addr: &str| -> ibv_gid { serde_json::from_value(serde_json::json!(addr)).unwrap() };
+        let populated = [
+            (0, gid("fe80::1")),
+            (1, gid("fe80::1")),
+            (2, gid("::ffff:192.168.1.2")),
+            (3, gid("::ffff:192.168.1.2")),
+        ];
+        let table = || {
+            (0..256u16).map(|index| {
+                populated
+                    .iter()
+                    .find(|&&(i, _)| i == index)
+                    .map_or((index, ibv_gid::default()), |&entry| entry)
+            })
+        };
+
+        let mut types = GidTypeTable::new(ibdev_path, 1, LinkLayer::Ethernet);
+        let gids = select_gids(table(), &mut types, &DeviceConfig::default());
+        assert_eq!(types.reads(), 4);
+        let kept: Vec<_> = gids.iter().map(|g| (g.index, g.gid_type.clone())).collect();
+        assert_eq!(
+            kept,
+            [
+                (0, GidType::RoCEv1),
+                (1, GidType::RoCEv2),
+                (2, GidType::RoCEv1),
+                (3, GidType::RoCEv2)
+            ]
+        );
+
+        let config = DeviceConfig::default()
+            .with_gid_type(GidType::RoCEv2)
+            .with_skip_link_local(true);
+        let mut types = GidTypeTable::new(ibdev_path, 1, LinkLayer::Ethernet);
+        let gids = select_gids(table(), &mut types, &config);
+        assert_eq!(types.reads(), 4);
+        assert_eq!(gids.len(), 1);
+        assert_eq!(gids[0].index, 3);
+
+        // Opting in reads the padding too; every fixture entry has a type
+        let config = DeviceConfig::default().with_include_null_gids(true);
+        let mut types = GidTypeTable::new(ibdev_path, 1, LinkLayer::Ethernet);
+        let gids = select_gids(table(), &mut types, &config);
+        assert_eq!(types.reads(), 256);
+        assert_eq!(gids.len(), 256);
+        assert!(gids[4].gid.is_null());
+    }
+
+    #[test]
+    fn test_gid_type_overrides() {
+        let sysfs = TempSysfs::new("gid-overrides");
+        for (index, content) in ["RoCE v2\n", "RoCE v2 (vendor)\n", "IB/RoCE v1\n"]
+            .iter()
+            .enumerate()
+        {
+            sysfs.write(format!("ports/1/gid_attrs/types/{index}"), content);
+        }
+        let ibdev_path = sysfs.path();
+        let gid =
+            |addr: &str| -> ibv_gid { serde_json::from_value(serde_json::json!(addr)).unwrap() };
+        let table = || (0..3u16).map(|index| (index, gid("::ffff:192.168.1.2")));
+        let types = |config: &DeviceConfig| {
+            let mut types = GidTypeTable::new(ibdev_path, 1, LinkLayer::Ethernet);
+            select_gids(table(), &mut types, config)
+                .into_iter()
+                .map(|g| g.gid_type)
+                .collect::<Vec<_>>()
+        };
+
+        let vendor = GidType::Other("RoCE v2 (vendor)".to_string());
+        assert_eq!(
+            types(&DeviceConfig::default()),
+            [GidType::RoCEv2, vendor, GidType::RoCEv1]
+        );
+
+        // The key has no trailing newline; standard strings are not overridden
+        let config = DeviceConfig::default()
+            .with_gid_type_override("RoCE v2 (vendor)", GidType::RoCEv2)
+            .with_gid_type_override("IB/RoCE v1", GidType::RoCEv2)
+            .with_gid_type(GidType::RoCEv2);
+        assert_eq!(types(&config), [GidType::RoCEv2, GidType::RoCEv2]);
+    }
+
+    #[test]
+    fn open_first_device() {
+        let devices = Devices::available().unwrap();
+        let first = Device::open_first(&Default::default()).unwrap();
+        assert_eq!(first.info().guid, devices[0].info().guid);
+
+        let last = devices.last().unwrap().info();
+        let config = DeviceConfig::default().with_device(last.qualified_name());
+        let device = Device::open_first(&config).unwrap();
+        assert_eq!(device.info().guid, last.guid);
+        assert_eq!(device.index(), last.index);
+
+        let config = DeviceConfig::default().with_device("no_such_device");
+        let err = Device::open_first(&config).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::IBDeviceNotFound);
+    }
+
+    #[test]
+    fn device_ops_available() {
+        let devices = Devices::available().unwrap();
+        for device in &devices {
+            let ops = device.ops_available();
+            assert!(ops.data_path(), "{}: {ops:?}", device.info().name);
+
+            // The snapshot matches the op table the ffi wrappers dispatch on
+            let raw = unsafe { &(*device.context_ptr()).ops };
+            assert_eq!(ops.post_send, raw.post_send.is_some());
+            assert_eq!(ops.post_recv, raw.post_recv.is_some());
+            assert_eq!(ops.poll_cq, raw.poll_cq.is_some());
+
+            if !ops.create_cq_ex {
+                let err = device.create_cq_ex(16).unwrap_err();
+                assert!(err.kind.is_unsupported(), "{err}");
+            }
+        }
+    }
+
+    #[test]
+    fn device_snapshot() {
+        let devices = Devices::available().unwrap();
+        let name = devices[0].info().name.clone();
+        let snapshot = devices[0].snapshot();
+        let converted = crate::DeviceInfo::from(&*devices[0]);
+        drop(devices);
+
+        // The snapshots outlive the closed device
+        assert_eq!(snapshot.name, name);
+        assert_eq!(converted.guid, snapshot.guid);
+        let handle = std::thread::spawn(move || snapshot.ports.len());
+        assert_eq!(handle.join().unwrap(), converted.ports.len());
+    }
+
+    #[test]
+    fn device_teardown() {
+        let devices = Devices::available().unwrap();
+        let device = devices[0].clone();
+        let name = device.info().name.clone();
+        let buffer = device
+            .register_memory(vec![0u8; 4096], ibv_access_flags::IBV_ACCESS_LOCAL_WRITE)
+            .unwrap();
+
+        // The registration keeps the device alive until it is dropped last
+        drop(devices);
+        drop(device);
+        drop(buffer);
+
+        // Resources were released exactly once, so the device reopens cleanly
+        let devices = Devices::available().unwrap();
+        assert!(devices.iter().any(|device| device.info().name == name));
+    }
+}
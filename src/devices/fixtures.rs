@@ -0,0 +1,79 @@
+//! Test fixtures for ports, GIDs and sysfs trees.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::{Gid, Port};
+use crate::{GidType, LinkLayer, ibv_gid, ibv_mtu, ibv_port_attr, ibv_port_state};
+
+/// Builds a port with valid enum fields and no GIDs or P_Keys.
+///
+/// `ibv_port_attr::default()` zero-fills the struct, which is not a valid
+/// `ibv_mtu`, so the MTU fields are written before the value is assumed
+/// initialized.
+pub(crate) fn synthetic_port(port_num: u8, state: ibv_port_state, link_layer: LinkLayer) -> Port {
+    let mut attr = std::mem::MaybeUninit::<ibv_port_attr>::zeroed();
+    let port_attr = unsafe {
+        let ptr = attr.as_mut_ptr();
+        std::ptr::addr_of_mut!((*ptr).max_mtu).write(ibv_mtu::IBV_MTU_4096.into());
+        std::ptr::addr_of_mut!((*ptr).active_mtu).write(ibv_mtu::IBV_MTU_1024.into());
+        std::ptr::addr_of_mut!((*ptr).state).write(state.into());
+        std::ptr::addr_of_mut!((*ptr).link_layer).write(link_layer);
+        attr.assume_init()
+    };
+    Port {
+        port_num,
+        port_attr,
+        gids: vec![],
+        pkeys: vec![],
+    }
+}
+
+/// Builds a GID from IPv6 text, e.g. `"fe80::1"`, or `"::"` for a null GID.
+pub(crate) fn synthetic_gid(index: u16, addr: &str, gid_type: GidType) -> Gid {
+    let addr: std::net::Ipv6Addr = addr.parse().unwrap();
+    Gid {
+        index,
+        gid: ibv_gid { raw: addr.octets() },
+        gid_type,
+    }
+}
+
+/// A scratch directory standing in for `/sys/class/infiniband/<dev>`.
+///
+/// Each instance gets its own directory, removed on drop so a failing
+/// assertion does not leave it behind.
+pub(crate) struct TempSysfs(PathBuf);
+
+impl TempSysfs {
+    /// Creates an empty directory whose name includes `name` and the pid.
+    pub(crate) fn new(name: &str) -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "ruapc-rdma-{name}-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    /// Returns the device directory.
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Writes `content` to `relative`, creating parent directories.
+    pub(crate) fn write(&self, relative: impl AsRef<Path>, content: &str) {
+        let path = self.0.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+}
+
+impl Drop for TempSysfs {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,161 @@
+//! Canned device descriptions for testing without RDMA hardware.
+//!
+//! Discovery-only: [`Devices::available_mock`] returns [`DeviceInfo`]
+//! snapshots, not opened devices, so nothing here can create queues,
+//! register memory or transfer data.
+
+use std::path::PathBuf;
+
+use super::{DeviceInfo, Devices, Gid, Port};
+use crate::{
+    GidType, Guid, LinkLayer, TransportType, ibv_device_attr, ibv_gid, ibv_mtu, ibv_port_attr,
+    ibv_port_state,
+};
+
+impl Devices {
+    /// Returns a fixed description of one RoCE device, for tests in CI.
+    ///
+    /// The device `mock_0` has two active Ethernet ports, each with a
+    /// link-local and an IPv4-mapped RoCEv2 GID and the default P_Key. The
+    /// values never change, so downstream crates can test device selection
+    /// and the JSON/serde paths against them.
+    ///
+    /// This only mocks discovery: no device is opened, so the result is a
+    /// list of [`DeviceInfo`] rather than a [`Devices`] collection.
+    pub fn available_mock() -> Vec<DeviceInfo> {
+        let mut device_attr = ibv_device_attr {
+            node_guid: Guid::from_be(0x506b_0b03_0039_e8a4u64.to_be()),
+            sys_image_guid: Guid::from_be(0x506b_0b03_0039_e8a4u64.to_be()),
+            vendor_id: 0x02c9,
+            vendor_part_id: 4119,
+            max_qp: 1 << 18,
+            max_qp_wr: 1 << 15,
+            max_cq: 1 << 24,
+            max_cqe: (1 << 22) - 1,
+            max_mr: 1 << 24,
+            max_pd: 1 << 24,
+            max_sge: 30,
+            max_pkeys: 128,
+            phys_port_cnt: 2,
+            ..Default::default()
+        };
+        let fw_ver = b"16.35.1012";
+        device_attr.fw_ver.0[..fw_ver.len()].copy_from_slice(fw_ver);
+
+        let mut info = DeviceInfo::default();
+        info.name = "mock_0".to_string();
+        info.guid = device_attr.node_guid;
+        info.ibdev_path = PathBuf::from("/sys/class/infiniband/mock_0");
+        info.board_id = "MOCK_0000000001".to_string();
+        info.transport_type = TransportType::InfiniBand;
+        info.device_attr = device_attr;
+        info.hca_core_clock = 156_250;
+        info.set_ports((1..=2).map(mock_port).collect());
+        vec![info]
+    }
+}
+
+/// Builds an active RoCE port with two RoCEv2 GIDs.
+fn mock_port(port_num: u8) -> Port {
+    let mut attr = std::mem::MaybeUninit::<ibv_port_attr>::zeroed();
+    let mut port_attr = unsafe {
+        let ptr = attr.as_mut_ptr();
+        std::ptr::addr_of_mut!((*ptr).state).write(ibv_port_state::IBV_PORT_ACTIVE.into());
+        std::ptr::addr_of_mut!((*ptr).max_mtu).write(ibv_mtu::IBV_MTU_4096.into());
+        std::ptr::addr_of_mut!((*ptr).active_mtu).write(ibv_mtu::IBV_MTU_1024.into());
+        std::ptr::addr_of_mut!((*ptr).link_layer).write(LinkLayer::Ethernet);
+        attr.assume_init()
+    };
+    port_attr.gid_tbl_len = 2;
+    port_attr.pkey_tbl_len = 1;
+
+    let gid = |index: u16, addr: String| {
+        let bits = addr.parse::<std::net::Ipv6Addr>().unwrap().to_bits();
+        Gid {
+            index,
+            gid: ibv_gid {
+                raw: bits.to_be_bytes(),
+            },
+            gid_type: GidType::RoCEv2,
+        }
+    };
+    Port {
+        port_num,
+        port_attr,
+        gids: vec![
+            gid(0, format!("fe80::526b:bff:fe39:e8a{port_num}")),
+            gid(1, format!("::ffff:192.168.{port_num}.10")),
+        ],
+        pkeys: vec![0xffff],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::{is_roce_device, roce_config};
+    use crate::{DeviceConfig, ErrorKind};
+
+    #[test]
+    fn mock_devices() {
+        let devices = Devices::available_mock();
+        assert_eq!(devices.len(), 1);
+        let info = &devices[0];
+        assert_eq!(info.name, "mock_0");
+        assert!(info.is_roce());
+        assert_eq!(info.active_ports().count(), 2);
+        assert_eq!(info.device_attr.fw_ver.to_string(), "16.35.1012");
+        assert_eq!(info.best_gid_index(2, &GidType::RoCEv2), Some(0));
+        assert_eq!(info.best_gid_index(1, &GidType::IB), None);
+        assert!(info.ports[0].gids[0].gid.is_link_local());
+        assert!(info.ports[1].gids[1].gid.is_ipv4_mapped());
+
+        let json = serde_json::to_value(&devices).unwrap();
+        let der: Vec<DeviceInfo> = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&der).unwrap(), json);
+        assert_eq!(json[0]["ports"][1]["gids"][1]["gid"], "::ffff:192.168.2.10");
+    }
+
+    #[test]
+    fn mock_devices_open_roce() {
+        let config = roce_config(&DeviceConfig::default()).unwrap();
+        assert!(config.skip_inactive_port);
+        assert_eq!(config.link_layer_filter, [LinkLayer::Ethernet].into());
+        assert_eq!(
+            config.gid_type_filter,
+            [GidType::RoCEv1, GidType::RoCEv2].into()
+        );
+
+        // A mixed device: port 2 is switched to InfiniBand
+        let mut info = Devices::available_mock().remove(0);
+        info.ports[1].port_attr.link_layer = LinkLayer::InfiniBand;
+        for gid in &mut info.ports[1].gids {
+            gid.gid_type = GidType::IB;
+        }
+        let kept: Vec<_> = info
+            .ports
+            .iter()
+            .filter(|port| config.accepts_port(&port.port_attr))
+            .map(|port| port.port_num)
+            .collect();
+        assert_eq!(kept, [1]);
+        assert!(is_roce_device(&info));
+
+        info.ports.remove(0);
+        assert!(!is_roce_device(&info));
+    }
+
+    #[test]
+    fn roce_config_gid_type_filter() {
+        let config = DeviceConfig::default()
+            .with_gid_type(GidType::IB)
+            .with_gid_type(GidType::RoCEv2);
+        let config = roce_config(&config).unwrap();
+        assert_eq!(config.gid_type_filter, [GidType::RoCEv2].into());
+
+        // An IB-only filter must not widen to all types
+        let config = DeviceConfig::default().with_gid_type(GidType::IB);
+        let err = roce_config(&config).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::IBDeviceNotFound);
+    }
+}
@@ -7,8 +7,12 @@
 //!
 //! - [`mod.rs`](self): Devices collection and public API
 //! - [`device.rs`](device): Single Device handle implementation
-//! - [`types.rs`](types): Public data types (DeviceInfo, Port, Gid)
+//! - [`protection_domain.rs`](protection_domain): Shared protection domain handle
+//! - [`types.rs`](types): Public data types (DeviceInfo, Port, Gid, DeviceLimits, DeviceChange, Endpoint, DevicesReport)
 //! - [`raw.rs`](raw): FFI wrappers with RAII cleanup
+//! - [`mock.rs`](mock): Canned device descriptions for tests (`mock` feature)
+//! - [`netns.rs`](netns): Enumeration inside another network namespace (`netns` feature)
+//! - [`watch.rs`](watch): Re-enumeration on device hotplug (`watch` feature)
 //!
 //! ## Example
 //!
@@ -26,7 +30,7 @@
 //!     for port in &info.ports {
 //!         println!("  Port {}: {} GIDs", port.port_num, port.gids.len());
 //!         for gid in &port.gids {
-//!             println!("    GID[{}]: {:?}", gid.index, gid.gid_type);
+//!             println!("    GID[{}]: {}", gid.index, gid.gid_type);
 //!         }
 //!     }
 //! }
@@ -34,15 +38,27 @@
 //! ```
 
 mod device;
+#[cfg(test)]
+mod fixtures;
+#[cfg(feature = "mock")]
+mod mock;
+#[cfg(all(feature = "netns", target_os = "linux"))]
+mod netns;
+mod protection_domain;
 mod raw;
 mod types;
+#[cfg(all(feature = "watch", target_os = "linux"))]
+mod watch;
 
 pub use device::Device;
-pub use types::{DeviceInfo, Gid, Port};
+pub use protection_domain::ProtectionDomainRef;
+pub use types::{DeviceChange, DeviceInfo, DeviceLimits, DevicesReport, Endpoint, Gid, Port};
+
+use types::name_matches;
 
-use std::{ops::Deref, sync::Arc};
+use std::{ops::Deref, path::Path, sync::Arc};
 
-use crate::{DeviceConfig, ErrorKind, Result};
+use crate::{DeviceConfig, Error, ErrorKind, GidType, Guid, LinkLayer, Result};
 
 use raw::RawDeviceList;
 
@@ -83,6 +99,84 @@ impl Devices {
         self.0.is_empty()
     }
 
+    /// Returns the device with the given name or qualified name.
+    ///
+    /// If several devices share a plain name (see
+    /// [`find_duplicate_names`](Self::find_duplicate_names)), the first one
+    /// is returned; pass [`DeviceInfo::qualified_name`] to pick a specific
+    /// one. Returns `None` if no device in this collection matches.
+    pub fn by_name(&self, name: &str) -> Option<&Arc<Device>> {
+        self.iter().find(|device| device.info().matches_name(name))
+    }
+
+    /// Returns the device names shared by more than one device, sorted.
+    ///
+    /// Bonding and virtual functions can expose devices under the same name,
+    /// in which case a plain name in [`DeviceConfig::device_filter`] matches
+    /// all of them.
+    pub fn find_duplicate_names(&self) -> Vec<String> {
+        DeviceInfo::duplicate_names(self.iter().map(|device| device.info()))
+    }
+
+    /// Returns the device with the given GUID.
+    ///
+    /// Returns `None` if no device in this collection has that GUID.
+    pub fn by_guid(&self, guid: &Guid) -> Option<&Arc<Device>> {
+        self.iter().find(|device| device.info().guid == *guid)
+    }
+
+    /// Returns the usable endpoints of all devices, see
+    /// [`DeviceInfo::endpoints`].
+    pub fn endpoints(&self) -> Vec<Endpoint> {
+        self.iter()
+            .flat_map(|device| device.info().endpoints())
+            .collect()
+    }
+
+    /// Returns a timestamped report of the device information.
+    pub fn to_report(&self) -> DevicesReport {
+        DevicesReport::new(self.iter().map(|device| device.info().clone()).collect())
+    }
+
+    /// Returns the devices sorted by [`DeviceInfo::sort_key`].
+    pub fn sorted(&self) -> Vec<Arc<Device>> {
+        let mut devices = self.0.clone();
+        devices.sort_by(|a, b| a.info().sort_key().cmp(&b.info().sort_key()));
+        devices
+    }
+
+    /// Opens the device whose sysfs path matches `path`.
+    ///
+    /// Both paths are canonicalized before comparison, so
+    /// `/sys/class/infiniband/mlx5_0` matches the device even though it is a
+    /// symlink. This avoids name-based matching when device names are
+    /// remapped inside a namespace.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::IBDeviceNotFound`] if `path` does not exist or no
+    /// device matches it, or an error if opening the device fails.
+    pub fn open_by_path(path: &Path, config: &DeviceConfig) -> Result<Device> {
+        let target = path.canonicalize().map_err(|err| {
+            Error::new(
+                ErrorKind::IBDeviceNotFound,
+                format!("{}: {err}", path.display()),
+            )
+        })?;
+
+        let list = RawDeviceList::available()?;
+        for (index, &device) in list.iter().enumerate() {
+            let ibdev_path = unsafe { Device::device_path(device) };
+            if ibdev_path.canonicalize().is_ok_and(|p| p == target) {
+                return Device::open(device, index, config);
+            }
+        }
+        Err(Error::new(
+            ErrorKind::IBDeviceNotFound,
+            format!("no device at {}", path.display()),
+        ))
+    }
+
     /// Opens RDMA devices based on the provided configuration.
     ///
     /// Allows filtering devices by name, GID type, and other criteria.
@@ -95,33 +189,132 @@ impl Devices {
     ///
     /// Returns an error if device enumeration or opening fails.
     pub fn open(config: &DeviceConfig) -> Result<Devices> {
+        Self::open_filtered(config, |_| true)
+    }
+
+    /// Opens the devices with at least one usable RoCE port.
+    ///
+    /// Applies the RoCE defaults on top of `config`: inactive ports are
+    /// skipped, only Ethernet ports are kept, and the GID type filter is
+    /// limited to RoCEv1 and RoCEv2 (an empty filter becomes both). Devices
+    /// are kept only if an active Ethernet port still carries a RoCE GID,
+    /// see [`DeviceInfo::roce_ports`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if device enumeration or opening fails, or
+    /// [`ErrorKind::IBDeviceNotFound`] if no RoCE device is found or the
+    /// GID type filter of `config` contains no RoCE type.
+    pub fn open_roce(config: &DeviceConfig) -> Result<Devices> {
+        Self::open_filtered(&roce_config(config)?, is_roce_device)
+    }
+
+    /// Opens RDMA devices matching `config` and then `predicate`.
+    ///
+    /// Each device is opened and queried with `config` first, so the
+    /// predicate sees a fully populated [`DeviceInfo`]; devices it rejects
+    /// are closed again. Use this for selection logic that the config
+    /// fields cannot express.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if device enumeration or opening fails, or
+    /// [`ErrorKind::IBDeviceNotFound`] if no device matches.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ruapc_rdma_sys::Devices;
+    /// let devices = Devices::open_filtered(&Default::default(), |info| {
+    ///     info.is_roce() && info.device_attr.max_qp >= 4096
+    /// })?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn open_filtered<F>(config: &DeviceConfig, predicate: F) -> Result<Devices>
+    where
+        F: Fn(&DeviceInfo) -> bool,
+    {
+        Self::open_matching(config, predicate, false).map(Devices)
+    }
+
+    /// Opens the devices matching `config` and `predicate`, stopping after
+    /// the first one if `first_only` is set.
+    ///
+    /// Returns [`ErrorKind::IBDeviceNotFound`] instead of an empty list.
+    fn open_matching<F>(
+        config: &DeviceConfig,
+        predicate: F,
+        first_only: bool,
+    ) -> Result<Vec<Arc<Device>>>
+    where
+        F: Fn(&DeviceInfo) -> bool,
+    {
         let list = RawDeviceList::available()?;
         let mut devices = Vec::with_capacity(list.len());
-        for &device in list.iter() {
+        for (index, &device) in list.iter().enumerate() {
             // Early filter by device name to avoid expensive device opening
             if !config.device_filter.is_empty() {
                 let name = unsafe { Device::device_name(device) };
-                if !config.device_filter.contains(&name) {
+                let guid = Guid::from_be(unsafe { crate::ibv_get_device_guid(device) });
+                if !config
+                    .device_filter
+                    .iter()
+                    .any(|pattern| name_matches(&name, &guid, pattern))
+                {
                     continue;
                 }
             }
 
-            let index = devices.len();
             let device = Device::open(device, index, config)?;
             if config.skip_inactive_port && device.info().ports.is_empty() {
                 continue;
             }
+            if !predicate(device.info()) {
+                continue;
+            }
 
             devices.push(Arc::new(device));
+            if first_only {
+                break;
+            }
         }
         if devices.is_empty() {
             Err(ErrorKind::IBDeviceNotFound.into())
         } else {
-            Ok(Devices(devices))
+            Ok(devices)
         }
     }
 }
 
+/// Returns `config` restricted to active Ethernet ports with RoCE GIDs.
+///
+/// Fails instead of returning an empty GID type filter, which would select
+/// every type.
+fn roce_config(config: &DeviceConfig) -> Result<DeviceConfig> {
+    let mut config = config.clone();
+    config.skip_inactive_port = true;
+    config.link_layer_filter = [LinkLayer::Ethernet].into();
+    if config.gid_type_filter.is_empty() {
+        config.gid_type_filter = [GidType::RoCEv1, GidType::RoCEv2].into();
+    } else {
+        config
+            .gid_type_filter
+            .retain(|gid_type| matches!(gid_type, GidType::RoCEv1 | GidType::RoCEv2));
+        if config.gid_type_filter.is_empty() {
+            return Err(Error::new(
+                ErrorKind::IBDeviceNotFound,
+                "GID type filter contains no RoCE type".to_string(),
+            ));
+        }
+    }
+    Ok(config)
+}
+
+/// Returns true if `info` has a port [`Devices::open_roce`] can use.
+fn is_roce_device(info: &DeviceInfo) -> bool {
+    info.roce_ports().next().is_some()
+}
+
 impl Deref for Devices {
     type Target = [Arc<Device>];
 
@@ -156,4 +349,83 @@ mod tests {
             assert_eq!(json, ser);
         }
     }
+
+    #[test]
+    fn open_by_missing_path() {
+        let err = Devices::open_by_path(
+            Path::new("/nonexistent/infiniband/rxe_0"),
+            &Default::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::IBDeviceNotFound);
+    }
+
+    #[test]
+    fn lookup_devices() {
+        let devices = Devices::available().unwrap();
+        for device in &devices {
+            let info = device.info();
+            let found = devices.by_name(&info.name).unwrap();
+            assert_eq!(found.index(), device.index());
+            let found = devices.by_guid(&info.guid).unwrap();
+            assert_eq!(found.info().guid, info.guid);
+        }
+        assert!(devices.by_name("no_such_device").is_none());
+
+        for device in &devices {
+            let info = device.info();
+            let opened = Devices::open_by_path(&info.ibdev_path, &Default::default()).unwrap();
+            assert_eq!(opened.info().guid, info.guid);
+        }
+
+        let sorted = devices.sorted();
+        assert_eq!(sorted.len(), devices.len());
+        assert!(
+            sorted
+                .windows(2)
+                .all(|w| w[0].info().sort_key() <= w[1].info().sort_key())
+        );
+    }
+
+    #[test]
+    fn open_filtered_devices() {
+        let devices = Devices::available().unwrap();
+        let first = devices[0].info().name.clone();
+        let filtered =
+            Devices::open_filtered(&Default::default(), |info| info.name == first).unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].info().name, first);
+
+        let err = Devices::open_filtered(&Default::default(), |_| false)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind, ErrorKind::IBDeviceNotFound);
+    }
+
+    #[test]
+    fn open_roce_devices() {
+        let devices = Devices::available().unwrap();
+        let expected = devices
+            .iter()
+            .filter(|d| d.info().roce_ports().next().is_some());
+        match Devices::open_roce(&Default::default()) {
+            Ok(roce) => {
+                assert_eq!(roce.len(), expected.count());
+                for device in &roce {
+                    let info = device.info();
+                    assert!(info.roce_ports().next().is_some());
+                    assert_eq!(info.active_ports().count(), info.ports.len());
+                    assert!(
+                        info.available_gid_types().iter().all(|gid_type| {
+                            matches!(gid_type, GidType::RoCEv1 | GidType::RoCEv2)
+                        })
+                    );
+                }
+            }
+            Err(err) => {
+                assert_eq!(err.kind, ErrorKind::IBDeviceNotFound);
+                assert_eq!(expected.count(), 0);
+            }
+        }
+    }
 }
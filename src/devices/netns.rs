@@ -0,0 +1,90 @@
+//! # Network namespace enumeration
+//!
+//! RDMA device visibility depends on the network namespace of the calling
+//! thread. [`Devices::open_in_netns`] temporarily moves the thread into
+//! another namespace to enumerate the devices visible there.
+
+use std::os::fd::{AsRawFd, OwnedFd, RawFd};
+
+use super::Devices;
+use crate::{DeviceConfig, Error, ErrorKind, Result};
+
+/// Restores the original namespace of the thread, also on unwind.
+struct NetnsGuard(Option<OwnedFd>);
+
+impl NetnsGuard {
+    /// Saves the current namespace and enters `ns_fd`.
+    fn enter(ns_fd: RawFd) -> Result<Self> {
+        let original = std::fs::File::open("/proc/thread-self/ns/net")
+            .map_err(|err| Error::new(ErrorKind::SetNetnsFailed, err.to_string()))?;
+        if unsafe { libc::setns(ns_fd, libc::CLONE_NEWNET) } != 0 {
+            return Err(ErrorKind::SetNetnsFailed.with_errno());
+        }
+        Ok(Self(Some(original.into())))
+    }
+
+    /// Returns the thread to the original namespace.
+    fn restore(mut self) -> Result<()> {
+        match self.0.take() {
+            Some(fd) if unsafe { libc::setns(fd.as_raw_fd(), libc::CLONE_NEWNET) } != 0 => {
+                Err(ErrorKind::SetNetnsFailed.with_errno())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Drop for NetnsGuard {
+    fn drop(&mut self) {
+        if let Some(fd) = self.0.take() {
+            unsafe { libc::setns(fd.as_raw_fd(), libc::CLONE_NEWNET) };
+        }
+    }
+}
+
+impl Devices {
+    /// Opens the RDMA devices visible in the network namespace `ns_fd`.
+    ///
+    /// The calling thread enters the namespace with `setns`, enumerates and
+    /// opens the devices, and always returns to its original namespace,
+    /// including when opening fails. Opened devices stay usable afterwards.
+    /// Entering a namespace requires `CAP_SYS_ADMIN`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::SetNetnsFailed`] if the namespace cannot be
+    /// entered or restored, or any error from [`Devices::open`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ruapc_rdma_sys::Devices;
+    /// use std::os::fd::AsRawFd;
+    ///
+    /// let ns = std::fs::File::open("/proc/1234/ns/net")?;
+    /// let devices = Devices::open_in_netns(ns.as_raw_fd(), &Default::default())?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn open_in_netns(ns_fd: RawFd, config: &DeviceConfig) -> Result<Devices> {
+        let guard = NetnsGuard::enter(ns_fd)?;
+        let devices = Self::open(config);
+        guard.restore()?;
+        devices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_in_invalid_netns() {
+        let before = std::fs::read_link("/proc/thread-self/ns/net").unwrap();
+        let Err(err) = Devices::open_in_netns(-1, &Default::default()) else {
+            panic!("entering an invalid namespace fd should fail");
+        };
+        assert_eq!(err.kind, ErrorKind::SetNetnsFailed);
+        let after = std::fs::read_link("/proc/thread-self/ns/net").unwrap();
+        assert_eq!(before, after);
+    }
+}
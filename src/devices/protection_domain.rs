@@ -0,0 +1,131 @@
+//! # Protection domain handles
+//!
+//! Every [`Device`] allocates one protection domain when it is opened, and
+//! all memory regions and queue pairs created through it share that PD.
+//! [`ProtectionDomainRef`] names it explicitly: a cheap, cloneable handle
+//! that keeps the device alive and creates regions and queue pairs in the
+//! PD, so a peer's rkey is accepted by every QP created from the handle.
+
+use std::sync::Arc;
+
+use super::Device;
+use crate::{QueuePair, RegisteredBuffer, Result, ibv_access_flags, ibv_qp_init_attr};
+
+/// Shared reference to the protection domain of a [`Device`].
+///
+/// Obtained from [`Device::protection_domain`]. Cloning it clones the
+/// `Arc<Device>`, so the PD stays allocated while any handle, region or
+/// queue pair created from it exists.
+#[derive(Clone)]
+pub struct ProtectionDomainRef {
+    device: Arc<Device>,
+}
+
+impl ProtectionDomainRef {
+    /// Returns the device owning the protection domain.
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    /// Registers an owned buffer in this protection domain.
+    ///
+    /// Same as [`Device::register_memory`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ibv_reg_mr` fails.
+    pub fn register_memory(
+        &self,
+        buf: impl Into<Box<[u8]>>,
+        access: ibv_access_flags,
+    ) -> Result<RegisteredBuffer> {
+        self.device.register_memory(buf, access)
+    }
+
+    /// Creates a queue pair in this protection domain.
+    ///
+    /// Same as [`Device::create_qp`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `ibv_create_qp` or the follow-up `ibv_query_qp`
+    /// fails.
+    ///
+    /// # Safety
+    ///
+    /// The CQs and SRQ referenced by `init_attr` must be valid and outlive
+    /// the returned queue pair.
+    pub unsafe fn create_qp(&self, init_attr: &ibv_qp_init_attr) -> Result<QueuePair> {
+        unsafe { self.device.create_qp(init_attr) }
+    }
+
+    /// Returns the raw protection domain pointer.
+    ///
+    /// # Safety
+    ///
+    /// The returned pointer is only valid as long as this handle exists.
+    pub unsafe fn pd_ptr(&self) -> *mut crate::ibv_pd {
+        unsafe { self.device.pd_ptr() }
+    }
+}
+
+impl PartialEq for ProtectionDomainRef {
+    /// Returns true if both handles refer to the same protection domain.
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.device, &other.device)
+    }
+}
+
+impl Eq for ProtectionDomainRef {}
+
+impl std::fmt::Debug for ProtectionDomainRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProtectionDomainRef")
+            .field("device", &self.device.info().name)
+            .field("handle", &unsafe { (*self.pd_ptr()).handle })
+            .finish()
+    }
+}
+
+impl Device {
+    /// Returns a handle to the protection domain of this device.
+    ///
+    /// All regions and queue pairs created through the device already share
+    /// this PD; the handle lets code that only creates them depend on the
+    /// PD instead of the whole device.
+    pub fn protection_domain(self: &Arc<Self>) -> ProtectionDomainRef {
+        ProtectionDomainRef {
+            device: self.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Devices, QpInitAttrBuilder, ibv_access_flags};
+
+    #[test]
+    fn shared_protection_domain() {
+        let devices = Devices::available().unwrap();
+        let pd = devices[0].protection_domain();
+        assert_eq!(pd, pd.clone());
+        assert_eq!(pd, devices[0].protection_domain());
+        let cq = pd.device().create_cq(16).unwrap();
+
+        let init_attr = QpInitAttrBuilder::default()
+            .cq(unsafe { cq.cq_ptr() })
+            .build(&pd.device().info().device_attr)
+            .unwrap();
+        let qps = [(); 2].map(|_| unsafe { pd.create_qp(&init_attr) }.unwrap());
+        assert_ne!(qps[0].qp_num(), qps[1].qp_num());
+        for qp in &qps {
+            assert_eq!(qp.protection_domain(), pd);
+            assert_eq!(unsafe { (*qp.qp_ptr()).pd }, unsafe { pd.pd_ptr() });
+        }
+
+        let buf = pd
+            .register_memory(vec![0u8; 64], ibv_access_flags::IBV_ACCESS_LOCAL_WRITE)
+            .unwrap();
+        assert_eq!(unsafe { (*buf.mr().mr_ptr()).pd }, unsafe { pd.pd_ptr() });
+    }
+}
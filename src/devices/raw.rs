@@ -8,13 +8,18 @@
 //! - [`RawDeviceList`]: Wrapper for device list from `ibv_get_device_list`
 //! - [`RawContext`]: Wrapper for `ibv_context` from `ibv_open_device`
 //! - [`RawProtectionDomain`]: Wrapper for `ibv_pd` from `ibv_alloc_pd`
+//! - [`GidTypeTable`]: Reader for the sysfs GID types of one port
 //!
 //! ## Resource Safety
 //!
 //! All wrapper types automatically clean up their underlying FFI resources when
 //! dropped, preventing resource leaks even during error conditions.
 
-use std::{ops::Deref, path::Path};
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
 
 use crate::{Error, ErrorKind, GidType, LinkLayer, Result};
 
@@ -78,7 +83,9 @@ pub struct RawContext(pub *mut crate::ibv_context);
 
 impl Drop for RawContext {
     fn drop(&mut self) {
-        let _ = unsafe { crate::ibv_close_device(self.0) };
+        crate::check_cleanup("ibv_close_device", unsafe {
+            crate::ibv_close_device(self.0)
+        });
     }
 }
 
@@ -87,7 +94,8 @@ impl RawContext {
     ///
     /// # Arguments
     ///
-    /// * `query_fn` - FFI query function that returns 0 on success
+    /// * `query_fn` - FFI query function that returns 0 on success and the
+    ///   errno on failure, like `ibv_query_device` and `ibv_query_port`
     /// * `error_kind` - Error kind to use on failure
     ///
     /// # Safety
@@ -101,7 +109,7 @@ impl RawContext {
         if ret == 0 {
             Ok(())
         } else {
-            Err(error_kind.with_errno())
+            Err(Error::from_errno(error_kind, ret))
         }
     }
 
@@ -121,6 +129,25 @@ impl RawContext {
         Ok(device_attr)
     }
 
+    /// Queries extended device attributes.
+    ///
+    /// Providers without `query_device_ex` report only the basic attributes,
+    /// with the extended fields zeroed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query operation fails.
+    pub fn query_device_ex(&self) -> Result<crate::ibv_device_attr_ex> {
+        let input = crate::ibv_query_device_ex_input::default();
+        let mut device_attr = crate::ibv_device_attr_ex::default();
+        let ret = unsafe { crate::ibv_query_device_ex(self.0, &input, &mut device_attr) };
+        if ret == 0 {
+            Ok(device_attr)
+        } else {
+            Err(Error::from_errno(ErrorKind::IBQueryDeviceFail, ret))
+        }
+    }
+
     /// Queries port attributes.
     ///
     /// # Errors
@@ -152,34 +179,21 @@ impl RawContext {
         }
     }
 
-    /// Queries the GID type from sysfs.
+    /// Queries a partition key for the specified port and index.
+    ///
+    /// The returned P_Key is in host byte order.
     ///
     /// # Errors
     ///
-    /// Returns an error if reading from sysfs fails.
-    pub fn query_gid_type(
-        &self,
-        port_num: u8,
-        gid_index: u16,
-        ibdev_path: &Path,
-        port_attr: &crate::ibv_port_attr,
-    ) -> Result<GidType> {
-        let path = ibdev_path.join(format!("ports/{port_num}/gid_attrs/types/{gid_index}"));
-        match std::fs::read_to_string(path) {
-            Ok(content) => {
-                if content == GID_TYPE_IB_ROCE_V1 {
-                    match port_attr.link_layer {
-                        LinkLayer::InfiniBand => Ok(GidType::IB),
-                        LinkLayer::Ethernet => Ok(GidType::RoCEv1),
-                        _ => Ok(GidType::Other(content.trim().to_string())),
-                    }
-                } else if content == GID_TYPE_ROCE_V2 {
-                    Ok(GidType::RoCEv2)
-                } else {
-                    Ok(GidType::Other(content.trim().to_string()))
-                }
-            }
-            Err(err) => Err(Error::new(ErrorKind::IBQueryGidTypeFail, err.to_string())),
+    /// Returns an error if the query operation fails.
+    pub fn query_pkey(&self, port_num: u8, pkey_index: u16) -> Result<u16> {
+        let mut pkey: u16 = 0;
+        let ret =
+            unsafe { crate::ibv_query_pkey(self.0, port_num as _, pkey_index as _, &mut pkey) };
+        if ret == 0 {
+            Ok(u16::from_be(pkey))
+        } else {
+            Err(ErrorKind::IBQueryPKeyFail.with_errno())
         }
     }
 }
@@ -187,6 +201,62 @@ impl RawContext {
 unsafe impl Send for RawContext {}
 unsafe impl Sync for RawContext {}
 
+/// Reader for the GID types of one port, from
+/// `{ibdev_path}/ports/{port_num}/gid_attrs/types`.
+///
+/// The directory path is built once per port and each entry costs one
+/// `read`; [`reads`](Self::reads) counts them.
+pub struct GidTypeTable {
+    dir: PathBuf,
+    link_layer: LinkLayer,
+    reads: usize,
+}
+
+impl GidTypeTable {
+    /// Creates a reader for the GID types of `port_num`.
+    pub fn new(ibdev_path: &Path, port_num: u8, link_layer: LinkLayer) -> Self {
+        Self {
+            dir: ibdev_path.join(format!("ports/{port_num}/gid_attrs/types")),
+            link_layer,
+            reads: 0,
+        }
+    }
+
+    /// Reads the type of the GID at `gid_index`.
+    ///
+    /// The standard strings are recognized first. Any other string is
+    /// trimmed of surrounding whitespace, including the trailing newline
+    /// sysfs appends, and looked up in `overrides`; unmatched strings become
+    /// [`GidType::Other`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::IBQueryGidTypeFail`] if reading from sysfs fails,
+    /// as it does for unused entries.
+    pub fn get(&mut self, gid_index: u16, overrides: &HashMap<String, GidType>) -> Result<GidType> {
+        self.reads += 1;
+        let content = std::fs::read_to_string(self.dir.join(gid_index.to_string()))
+            .map_err(|err| Error::new(ErrorKind::IBQueryGidTypeFail, err.to_string()))?;
+        Ok(match content.as_str() {
+            GID_TYPE_IB_ROCE_V1 if self.link_layer == LinkLayer::InfiniBand => GidType::IB,
+            GID_TYPE_IB_ROCE_V1 if self.link_layer == LinkLayer::Ethernet => GidType::RoCEv1,
+            GID_TYPE_ROCE_V2 => GidType::RoCEv2,
+            _ => {
+                let content = content.trim();
+                overrides
+                    .get(content)
+                    .cloned()
+                    .unwrap_or_else(|| GidType::Other(content.to_string()))
+            }
+        })
+    }
+
+    /// Returns the number of sysfs reads made so far.
+    pub fn reads(&self) -> usize {
+        self.reads
+    }
+}
+
 /// Raw protection domain wrapper with automatic cleanup.
 ///
 /// A protection domain (PD) is a security mechanism that isolates
@@ -198,7 +268,7 @@ pub struct RawProtectionDomain(pub *mut crate::ibv_pd);
 
 impl Drop for RawProtectionDomain {
     fn drop(&mut self) {
-        let _ = unsafe { crate::ibv_dealloc_pd(self.0) };
+        crate::check_cleanup("ibv_dealloc_pd", unsafe { crate::ibv_dealloc_pd(self.0) });
     }
 }
 
@@ -8,23 +8,37 @@
 //! - [`DeviceInfo`]: Complete device metadata including name, GUID, attributes, and ports
 //! - [`Port`]: Port information with attributes and GID list
 //! - [`Gid`]: Global Identifier entry with type classification
+//! - [`DeviceLimits`]: Resource limits for sizing queues and pools
+//! - [`DeviceChange`]: One difference between two `DeviceInfo` snapshots
+//! - [`Endpoint`]: One usable (device, port, GID) combination
+//! - [`DevicesReport`]: Timestamped envelope around a device list
 //!
 //! All types derive `Serialize`, `Deserialize`, and `JsonSchema` for use in
 //! configuration and API responses.
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::{GidType, Guid, ibv_device_attr, ibv_gid, ibv_port_attr};
+use crate::{
+    Error, ErrorKind, GidType, Guid, LinkLayer, Mtu, PortState, Result, TransportType,
+    ibv_device_attr, ibv_device_cap_flags, ibv_gid, ibv_port_attr, ibv_port_cap_flags,
+    ibv_port_state,
+};
 
 /// Information about an RDMA device.
 ///
 /// Contains device metadata including name, GUID, attributes,
 /// and available ports with their GIDs.
 #[derive(Debug, Default, Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(remote = "Self")]
 pub struct DeviceInfo {
-    /// Device index in the system.
+    /// Position of the device in the `ibv_get_device_list` result.
+    ///
+    /// Devices skipped by a filter keep their gaps, so the index is stable
+    /// across configurations.
     pub index: usize,
     /// Device name (e.g., "mlx5_0").
     pub name: String,
@@ -32,17 +46,600 @@ pub struct DeviceInfo {
     pub guid: Guid,
     /// Path to the device in sysfs.
     pub ibdev_path: PathBuf,
+    /// Board identifier from sysfs (e.g., "MT_0000000223").
+    ///
+    /// Empty if the device has no `board_id` file.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub board_id: String,
+    /// Transport type of the device; RoCE devices report InfiniBand.
+    #[serde(default)]
+    pub transport_type: TransportType,
     /// Device attributes including capabilities.
     pub device_attr: ibv_device_attr,
+    /// HCA core clock frequency in kHz, from the extended device attributes.
+    ///
+    /// 0 if the provider does not report it; see
+    /// [`DeviceInfo::hca_core_clock_khz`].
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub hca_core_clock: u64,
     /// Available ports on this device.
+    ///
+    /// Prefer [`set_ports`](Self::set_ports), which keeps the GID index
+    /// cache in sync.
     pub ports: Vec<Port>,
+    /// First GID index of each `(port_num, gid_type)`, built with the ports.
+    #[serde(skip)]
+    gid_index_cache: HashMap<(u8, GidType), u16>,
+}
+
+impl Serialize for DeviceInfo {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        DeviceInfo::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DeviceInfo {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let mut info = DeviceInfo::deserialize(deserializer)?;
+        info.rebuild_gid_index_cache();
+        Ok(info)
+    }
+}
+
+impl DeviceInfo {
+    /// Reads the board identifier from `{ibdev_path}/board_id`.
+    ///
+    /// Returns an empty string if the file is missing, as for soft devices.
+    pub fn read_board_id(ibdev_path: &Path) -> String {
+        std::fs::read_to_string(ibdev_path.join("board_id"))
+            .map(|id| id.trim().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Returns the maximum number of entries of a completion queue.
+    ///
+    /// Returns 0 if unknown, e.g. for a default-constructed `DeviceInfo`.
+    pub fn max_cqe(&self) -> u32 {
+        self.device_attr.max_cqe.max(0) as u32
+    }
+
+    /// Returns the vendor-assigned part ID (e.g., 4119 for a ConnectX-5).
+    pub fn vendor_part_id(&self) -> u32 {
+        self.device_attr.vendor_part_id
+    }
+
+    /// Returns the HCA core clock frequency in kHz.
+    ///
+    /// The clock comes from the extended attribute query
+    /// (`ibv_query_device_ex`) made when the device is opened. Returns
+    /// `None` if the provider does not support it or reports no clock.
+    pub fn hca_core_clock_khz(&self) -> Option<u64> {
+        (self.hca_core_clock != 0).then_some(self.hca_core_clock)
+    }
+
+    /// Converts a raw hardware timestamp, in HCA clock ticks, to nanoseconds.
+    ///
+    /// Use this on completion timestamps read from an extended CQ to get
+    /// real latencies. Returns `None` without a known core clock, see
+    /// [`hca_core_clock_khz`](Self::hca_core_clock_khz).
+    pub fn timestamp_to_ns(&self, raw: u64) -> Option<f64> {
+        self.hca_core_clock_khz()
+            .map(|khz| raw as f64 * 1_000_000.0 / khz as f64)
+    }
+
+    /// Returns the device attributes queried when the device was opened.
+    pub fn device_attr(&self) -> &ibv_device_attr {
+        &self.device_attr
+    }
+
+    /// Returns the maximum number of outstanding RDMA reads and atomics a
+    /// QP can receive as the responder.
+    ///
+    /// Bounds `max_dest_rd_atomic` in the RTR transition.
+    pub fn max_qp_rd_atom(&self) -> u32 {
+        self.device_attr.max_qp_rd_atom.max(0) as u32
+    }
+
+    /// Returns the maximum number of RDMA reads and atomics the device can
+    /// serve as the responder across all QPs.
+    pub fn max_res_rd_atom(&self) -> u32 {
+        self.device_attr.max_res_rd_atom.max(0) as u32
+    }
+
+    /// Returns the maximum number of queue pairs.
+    pub fn max_qp(&self) -> u32 {
+        self.device_attr.max_qp.max(0) as u32
+    }
+
+    /// Returns the maximum number of outstanding work requests per queue.
+    pub fn max_qp_wr(&self) -> u32 {
+        self.device_attr.max_qp_wr.max(0) as u32
+    }
+
+    /// Returns the maximum number of memory regions.
+    pub fn max_mr(&self) -> u32 {
+        self.device_attr.max_mr.max(0) as u32
+    }
+
+    /// Returns the maximum number of protection domains.
+    pub fn max_pd(&self) -> u32 {
+        self.device_attr.max_pd.max(0) as u32
+    }
+
+    /// Returns the maximum number of scatter/gather entries per work request.
+    pub fn max_sge(&self) -> u32 {
+        self.device_attr.max_sge.max(0) as u32
+    }
+
+    /// Returns the resource limits relevant for sizing connection pools.
+    ///
+    /// Like the individual accessors, a limit is 0 if unknown.
+    pub fn limits(&self) -> DeviceLimits {
+        DeviceLimits {
+            max_qp: self.max_qp(),
+            max_qp_wr: self.max_qp_wr(),
+            max_cqe: self.max_cqe(),
+            max_mr: self.max_mr(),
+            max_pd: self.max_pd(),
+            max_sge: self.max_sge(),
+        }
+    }
+
+    /// Clamps a requested completion queue depth to [`max_cqe`](Self::max_cqe).
+    ///
+    /// The request is returned unchanged if the limit is unknown.
+    pub fn clamp_cqe(&self, cqe: u32) -> u32 {
+        match self.max_cqe() {
+            0 => cqe,
+            max_cqe => cqe.min(max_cqe),
+        }
+    }
+
+    /// Returns true if the device reports the given capability flag.
+    ///
+    /// If `flag` combines several flags, all of them must be set.
+    pub fn has_cap(&self, flag: ibv_device_cap_flags) -> bool {
+        self.device_attr.device_cap_flags & flag == flag
+    }
+
+    /// Returns the ports whose state is `IBV_PORT_ACTIVE`.
+    ///
+    /// This filters the snapshot regardless of whether inactive ports were
+    /// skipped when the device was opened.
+    pub fn active_ports(&self) -> impl Iterator<Item = &Port> {
+        self.ports
+            .iter()
+            .filter(|port| port.port_attr.state == ibv_port_state::IBV_PORT_ACTIVE)
+    }
+
+    /// Returns the active Ethernet ports that carry a RoCE GID.
+    ///
+    /// These are the ports a RoCE connection can use right away.
+    pub fn roce_ports(&self) -> impl Iterator<Item = &Port> {
+        self.active_ports().filter(|port| {
+            port.port_attr.link_layer == LinkLayer::Ethernet
+                && port
+                    .gids
+                    .iter()
+                    .any(|gid| matches!(gid.gid_type, GidType::RoCEv1 | GidType::RoCEv2))
+        })
+    }
+
+    /// Returns the link layer shared by all active ports.
+    ///
+    /// Returns [`LinkLayer::Unspecified`] if no port is active or active
+    /// ports disagree.
+    pub fn fabric_kind(&self) -> LinkLayer {
+        let mut link_layers = self.active_ports().map(|port| port.port_attr.link_layer);
+        match link_layers.next() {
+            Some(first) if link_layers.all(|link_layer| link_layer == first) => first,
+            _ => LinkLayer::Unspecified,
+        }
+    }
+
+    /// Returns true if the device is a RoCE (Ethernet) device.
+    pub fn is_roce(&self) -> bool {
+        self.fabric_kind() == LinkLayer::Ethernet
+    }
+
+    /// Returns true if the device is an InfiniBand device.
+    pub fn is_infiniband(&self) -> bool {
+        self.fabric_kind() == LinkLayer::InfiniBand
+    }
+
+    /// Returns the name qualified with the GUID, e.g. `mlx5_0@506b:0b03:0039:e8a4`.
+    ///
+    /// Unlike the name alone, this identifies a device even when bonding or
+    /// virtual functions expose several devices under the same name.
+    pub fn qualified_name(&self) -> String {
+        format!("{}@{}", self.name, self.guid)
+    }
+
+    /// Returns true if `name` is this device's name or qualified name.
+    pub fn matches_name(&self, name: &str) -> bool {
+        name_matches(&self.name, &self.guid, name)
+    }
+
+    /// Returns the names shared by more than one of `infos`, sorted.
+    ///
+    /// A plain name in [`DeviceConfig::device_filter`](crate::DeviceConfig)
+    /// or [`Devices::by_name`](crate::Devices::by_name) matches every device
+    /// listed here; use [`qualified_name`](Self::qualified_name) instead.
+    pub fn duplicate_names<'a>(infos: impl IntoIterator<Item = &'a DeviceInfo>) -> Vec<String> {
+        let mut counts = HashMap::<&str, usize>::new();
+        for info in infos {
+            *counts.entry(&info.name).or_default() += 1;
+        }
+        let mut names: Vec<_> = counts
+            .into_iter()
+            .filter(|&(_, count)| count > 1)
+            .map(|(name, _)| name.to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Returns the canonical sort key: name, then GUID.
+    ///
+    /// Sorting by this key gives a stable order for reports and JSON output
+    /// regardless of enumeration order.
+    pub fn sort_key(&self) -> (&str, Guid) {
+        (&self.name, self.guid)
+    }
+
+    /// Returns the distinct GID types across all ports.
+    pub fn available_gid_types(&self) -> HashSet<GidType> {
+        self.ports
+            .iter()
+            .flat_map(|port| &port.gids)
+            .map(|gid| gid.gid_type.clone())
+            .collect()
+    }
+
+    /// Replaces the ports and rebuilds the GID index cache.
+    pub fn set_ports(&mut self, ports: Vec<Port>) {
+        self.ports = ports;
+        self.rebuild_gid_index_cache();
+    }
+
+    /// Rebuilds the GID index cache behind
+    /// [`best_gid_index`](Self::best_gid_index) from the ports.
+    ///
+    /// Call this after modifying [`ports`](Self::ports) in place.
+    pub fn rebuild_gid_index_cache(&mut self) {
+        self.gid_index_cache.clear();
+        for port in &self.ports {
+            for gid in &port.gids {
+                self.gid_index_cache
+                    .entry((port.port_num, gid.gid_type.clone()))
+                    .or_insert(gid.index);
+            }
+        }
+    }
+
+    /// Returns the first GID index of `gid_type` on the given port.
+    ///
+    /// Looks up the cache built with the ports, so it is cheap enough for
+    /// per-connection setup. The cached index is checked against the port's
+    /// GIDs; if the ports changed since the cache was built, the GIDs are
+    /// scanned instead.
+    pub fn best_gid_index(&self, port_num: u8, gid_type: &GidType) -> Option<u16> {
+        let port = self.ports.iter().find(|port| port.port_num == port_num)?;
+        if let Some(&index) = self.gid_index_cache.get(&(port_num, gid_type.clone()))
+            && let Ok(pos) = port.gids.binary_search_by_key(&index, |gid| gid.index)
+            && &port.gids[pos].gid_type == gid_type
+        {
+            return Some(index);
+        }
+        port.gids
+            .iter()
+            .find(|gid| &gid.gid_type == gid_type)
+            .map(|gid| gid.index)
+    }
+
+    /// Returns the endpoint id of the given port, see [`Port::endpoint_id`].
+    ///
+    /// Returns `None` if the device has no such port.
+    pub fn endpoint_id(&self, port_num: u8) -> Option<String> {
+        self.ports
+            .iter()
+            .find(|port| port.port_num == port_num)
+            .map(|port| port.endpoint_id(&self.guid))
+    }
+
+    /// Returns one [`Endpoint`] per non-null GID of each active port.
+    ///
+    /// Endpoints are ordered by port, then by GID table order.
+    pub fn endpoints(&self) -> impl Iterator<Item = Endpoint> + '_ {
+        self.active_ports().flat_map(move |port| {
+            port.gids
+                .iter()
+                .filter(|gid| !gid.gid.is_null())
+                .map(move |gid| Endpoint {
+                    device_name: self.name.clone(),
+                    guid: self.guid,
+                    port_num: port.port_num,
+                    gid_index: gid.index,
+                    gid: gid.gid,
+                    gid_type: gid.gid_type.clone(),
+                })
+        })
+    }
+
+    /// Renders the device as Prometheus text exposition format.
+    ///
+    /// Emits the gauges below. Every sample carries the `device` label (the
+    /// device name); port gauges add `port`, the 1-based port number:
+    ///
+    /// | Metric                       | Extra labels                 | Value                        |
+    /// |------------------------------|------------------------------|------------------------------|
+    /// | `rdma_device_info`           | `guid`, `fw_ver`, `board_id` | always 1                     |
+    /// | `rdma_device_max_qp`         |                              | [`DeviceInfo::max_qp`]       |
+    /// | `rdma_device_max_cqe`        |                              | [`DeviceInfo::max_cqe`]      |
+    /// | `rdma_device_max_mr`         |                              | [`DeviceInfo::max_mr`]       |
+    /// | `rdma_port_state`            | `port`                       | `ibv_port_state`, 4 = ACTIVE |
+    /// | `rdma_port_active_mtu_bytes` | `port`                       | active MTU in bytes          |
+    /// | `rdma_port_link_speed_gbps`  | `port`                       | [`Port::link_speed_gbps`]    |
+    /// | `rdma_port_gids`             | `port`                       | [`Port::valid_gid_count`]    |
+    ///
+    /// The names and label sets are stable. Port counters are not included
+    /// since they are read on demand, see [`Port::read_counters`]. To export
+    /// several devices, use [`DevicesReport::to_metrics`], which writes each
+    /// family header only once.
+    pub fn to_metrics(&self) -> String {
+        metrics_text(std::slice::from_ref(self))
+    }
+
+    /// Lists what changed from this snapshot to the newer `other`.
+    ///
+    /// Ports are matched by port number and GIDs by index, address and
+    /// type, so a GID whose address changes in place shows up as removed
+    /// and added. Changes are ordered by port number: the added or removed
+    /// port, its state, MTU and LID, then removed and added GIDs. Device
+    /// attributes are not compared. Take snapshots with
+    /// [`Device::snapshot`](crate::Device::snapshot) after
+    /// [`update_attr`](crate::Device::update_attr).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ruapc_rdma_sys::{DeviceChange, Devices};
+    /// let devices = Devices::available()?;
+    /// let before = devices[0].snapshot();
+    /// // ... later, after re-opening or updating the device
+    /// let after = Devices::available()?[0].snapshot();
+    /// for change in before.diff(&after) {
+    ///     if let DeviceChange::PortStateChanged { port, to, .. } = change {
+    ///         println!("port {port} is now {to}");
+    ///     }
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn diff(&self, other: &DeviceInfo) -> Vec<DeviceChange> {
+        let mut port_nums: Vec<_> = self
+            .ports
+            .iter()
+            .chain(&other.ports)
+            .map(|port| port.port_num)
+            .collect();
+        port_nums.sort_unstable();
+        port_nums.dedup();
+
+        let mut changes = vec![];
+        for port in port_nums {
+            let old = self.ports.iter().find(|p| p.port_num == port);
+            let new = other.ports.iter().find(|p| p.port_num == port);
+            let (old, new) = match (old, new) {
+                (Some(old), Some(new)) => (old, new),
+                (Some(_), None) => {
+                    changes.push(DeviceChange::PortRemoved { port });
+                    continue;
+                }
+                (None, Some(_)) => {
+                    changes.push(DeviceChange::PortAdded { port });
+                    continue;
+                }
+                (None, None) => unreachable!(),
+            };
+
+            let (from, to) = (old.port_attr.state, new.port_attr.state);
+            if from != to {
+                changes.push(DeviceChange::PortStateChanged { port, from, to });
+            }
+            let (from, to) = (old.port_attr.active_mtu, new.port_attr.active_mtu);
+            if from != to {
+                changes.push(DeviceChange::ActiveMtuChanged { port, from, to });
+            }
+            let (from, to) = (old.lid(), new.lid());
+            if from != to {
+                changes.push(DeviceChange::LidChanged { port, from, to });
+            }
+            for gid in old.gids.iter().filter(|gid| !new.gids.contains(gid)) {
+                changes.push(DeviceChange::GidRemoved {
+                    port,
+                    gid: gid.clone(),
+                });
+            }
+            for gid in new.gids.iter().filter(|gid| !old.gids.contains(gid)) {
+                changes.push(DeviceChange::GidAdded {
+                    port,
+                    gid: gid.clone(),
+                });
+            }
+        }
+        changes
+    }
+}
+
+/// A change between two [`DeviceInfo`] snapshots, see [`DeviceInfo::diff`].
+///
+/// `port` is the 1-based port number.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum DeviceChange {
+    /// A port appeared.
+    PortAdded {
+        /// Port number.
+        port: u8,
+    },
+    /// A port disappeared.
+    PortRemoved {
+        /// Port number.
+        port: u8,
+    },
+    /// The logical port state changed, e.g. from `ACTIVE` to `DOWN`.
+    PortStateChanged {
+        /// Port number.
+        port: u8,
+        /// State in the older snapshot.
+        from: PortState,
+        /// State in the newer snapshot.
+        to: PortState,
+    },
+    /// The active MTU changed.
+    ActiveMtuChanged {
+        /// Port number.
+        port: u8,
+        /// MTU in the older snapshot.
+        from: Mtu,
+        /// MTU in the newer snapshot.
+        to: Mtu,
+    },
+    /// The subnet manager assigned a different LID.
+    LidChanged {
+        /// Port number.
+        port: u8,
+        /// LID in the older snapshot.
+        from: u16,
+        /// LID in the newer snapshot.
+        to: u16,
+    },
+    /// A GID entry appeared, e.g. an address was configured.
+    GidAdded {
+        /// Port number.
+        port: u8,
+        /// The new GID entry.
+        gid: Gid,
+    },
+    /// A GID entry disappeared.
+    GidRemoved {
+        /// Port number.
+        port: u8,
+        /// The removed GID entry.
+        gid: Gid,
+    },
+}
+
+/// Gauge family: metric name, help text and value of one sample.
+type Gauge<T> = (&'static str, &'static str, fn(&T) -> f64);
+
+/// Device gauge families, in output order.
+const DEVICE_GAUGES: &[Gauge<DeviceInfo>] = &[
+    (
+        "rdma_device_max_qp",
+        "Maximum number of queue pairs.",
+        |info| info.max_qp().into(),
+    ),
+    (
+        "rdma_device_max_cqe",
+        "Maximum number of entries of a completion queue.",
+        |info| info.max_cqe().into(),
+    ),
+    (
+        "rdma_device_max_mr",
+        "Maximum number of memory regions.",
+        |info| info.max_mr().into(),
+    ),
+];
+
+/// Port gauge families, in output order.
+const PORT_GAUGES: &[Gauge<Port>] = &[
+    (
+        "rdma_port_state",
+        "Logical port state (1 DOWN, 2 INIT, 3 ARMED, 4 ACTIVE).",
+        |port| port.port_attr.state.0 as u32 as f64,
+    ),
+    (
+        "rdma_port_active_mtu_bytes",
+        "Active path MTU in bytes.",
+        |port| port.port_attr.active_mtu.bytes().into(),
+    ),
+    (
+        "rdma_port_link_speed_gbps",
+        "Approximate link rate in Gbps.",
+        Port::link_speed_gbps,
+    ),
+    (
+        "rdma_port_gids",
+        "Number of populated GID table entries.",
+        |port| port.valid_gid_count() as f64,
+    ),
+];
+
+/// Renders `infos` as Prometheus text, grouping samples by metric family.
+fn metrics_text(infos: &[DeviceInfo]) -> String {
+    let mut out = String::new();
+    let family = |out: &mut String, name: &str, help: &str| {
+        out.push_str(&format!("# HELP {name} {help}\n# TYPE {name} gauge\n"));
+    };
+    family(
+        &mut out,
+        "rdma_device_info",
+        "Device identity; the value is always 1.",
+    );
+    for info in infos {
+        out.push_str(&format!(
+            "rdma_device_info{{device=\"{}\",guid=\"{}\",fw_ver=\"{}\",board_id=\"{}\"}} 1\n",
+            escape_label(&info.name),
+            info.guid,
+            escape_label(&info.device_attr.fw_ver.to_string()),
+            escape_label(&info.board_id),
+        ));
+    }
+    for (name, help, value) in DEVICE_GAUGES {
+        family(&mut out, name, help);
+        for info in infos {
+            let device = escape_label(&info.name);
+            out.push_str(&format!("{name}{{device=\"{device}\"}} {}\n", value(info)));
+        }
+    }
+    for (name, help, value) in PORT_GAUGES {
+        family(&mut out, name, help);
+        for info in infos {
+            let device = escape_label(&info.name);
+            for port in &info.ports {
+                out.push_str(&format!(
+                    "{name}{{device=\"{device}\",port=\"{}\"}} {}\n",
+                    port.port_num,
+                    value(port)
+                ));
+            }
+        }
+    }
+    out
+}
+
+/// Escapes a Prometheus label value.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
 }
 
 /// Global Identifier (GID) information for a port.
 ///
 /// A GID uniquely identifies a port on an RDMA network and
 /// includes the GID type (IB, RoCEv1, RoCEv2).
-#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct Gid {
     /// GID index on the port.
     pub index: u16,
@@ -52,6 +649,149 @@ pub struct Gid {
     pub gid_type: GidType,
 }
 
+impl Gid {
+    /// Length of a [`to_token`](Self::to_token) token: 17 bytes in
+    /// unpadded base64.
+    const TOKEN_LEN: usize = 23;
+
+    /// URL-safe base64 alphabet (RFC 4648 §5) of the tokens.
+    const TOKEN_ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+    /// Returns the network device backing this GID, read from sysfs.
+    ///
+    /// Reads `{ibdev_path}/ports/{port_num}/gid_attrs/ndevs/{index}`. Returns
+    /// `None` if the file is missing or empty, as for InfiniBand GIDs.
+    pub fn netdev(&self, ibdev_path: &Path, port_num: u8) -> Option<String> {
+        let path = ibdev_path.join(format!("ports/{port_num}/gid_attrs/ndevs/{}", self.index));
+        let name = std::fs::read_to_string(path).ok()?;
+        let name = name.trim();
+        (!name.is_empty()).then(|| name.to_string())
+    }
+
+    /// Returns the interface index of the network device backing this GID.
+    ///
+    /// Resolves the name with [`netdev`](Self::netdev) and passes it to
+    /// `if_nametoindex(3)`, e.g. for `SO_BINDTOIFINDEX` or the scope id of a
+    /// link-local socket address. Returns `None` if the GID has no netdev or
+    /// the interface does not exist in the current network namespace.
+    pub fn netdev_index(&self, ibdev_path: &Path, port_num: u8) -> Option<u32> {
+        if_nametoindex(&self.netdev(ibdev_path, port_num)?)
+    }
+
+    /// Returns the GID as IPv6 text, scoped with the netdev if link-local.
+    ///
+    /// Link-local addresses such as `fe80::1` are only usable as a bind
+    /// address together with their interface, so they are returned as
+    /// `fe80::1%eth0`. Other addresses, or GIDs without a netdev, are
+    /// returned unscoped.
+    pub fn scoped_addr(&self, ibdev_path: &Path, port_num: u8) -> String {
+        let ip = self.gid.as_ipv6();
+        match self.netdev(ibdev_path, port_num) {
+            Some(netdev) if self.gid.is_link_local() => format!("{ip}%{netdev}"),
+            _ => ip.to_string(),
+        }
+    }
+
+    /// Encodes the GID and its type as a 23-character base64 token.
+    ///
+    /// The token is 17 bytes in unpadded URL-safe base64 (RFC 4648 §5), so
+    /// it fits in a URL or a whitespace-separated handshake line as is: the
+    /// GID bytes in network order, then the type as `1` IB, `2` RoCEv1, `3`
+    /// RoCEv2 or `0` for [`GidType::Other`], whose name is not carried. The
+    /// index is left out since it only means something on the sending host.
+    pub fn to_token(&self) -> String {
+        let type_code: u8 = match self.gid_type {
+            GidType::IB => 1,
+            GidType::RoCEv1 => 2,
+            GidType::RoCEv2 => 3,
+            GidType::Other(_) => 0,
+        };
+        // Zero-padded to whole 3-byte groups; the padding is cut off below
+        let mut bytes = [0u8; 18];
+        bytes[..16].copy_from_slice(self.gid.as_raw());
+        bytes[16] = type_code;
+
+        let mut token = String::with_capacity(24);
+        for group in bytes.chunks_exact(3) {
+            let bits = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+            for shift in [18, 12, 6, 0] {
+                token.push(Self::TOKEN_ALPHABET[(bits >> shift) as usize & 0x3f] as char);
+            }
+        }
+        token.truncate(Self::TOKEN_LEN);
+        token
+    }
+
+    /// Decodes a token written by [`to_token`](Self::to_token).
+    ///
+    /// The returned GID has index 0, and type code `0` decodes as an
+    /// unnamed [`GidType::Other`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidConnectionInfo`] if the token is not 23
+    /// characters of the URL-safe base64 alphabet, has non-zero trailing
+    /// bits, or carries an unknown type code.
+    pub fn from_token(token: &str) -> Result<Gid> {
+        let invalid = |msg: String| Error::new(ErrorKind::InvalidConnectionInfo, msg);
+        let sextet = |c: u8| match c {
+            b'A'..=b'Z' => Some(c - b'A'),
+            b'a'..=b'z' => Some(c - b'a' + 26),
+            b'0'..=b'9' => Some(c - b'0' + 52),
+            b'-' => Some(62),
+            b'_' => Some(63),
+            _ => None,
+        };
+        if token.len() != Self::TOKEN_LEN {
+            return Err(invalid(format!(
+                "GID token {token:?} is not {} characters",
+                Self::TOKEN_LEN
+            )));
+        }
+        let mut sextets = [0u8; 24];
+        for (value, c) in sextets.iter_mut().zip(token.bytes()) {
+            *value = sextet(c)
+                .ok_or_else(|| invalid(format!("GID token {token:?} is not URL-safe base64")))?;
+        }
+
+        let mut bytes = [0u8; 18];
+        for (group, chunk) in bytes.chunks_exact_mut(3).zip(sextets.chunks_exact(4)) {
+            let bits = chunk
+                .iter()
+                .fold(0u32, |bits, &value| bits << 6 | u32::from(value));
+            group.copy_from_slice(&bits.to_be_bytes()[1..]);
+        }
+        // The last character carries two bits past the 17 bytes
+        if bytes[17] != 0 {
+            return Err(invalid(format!(
+                "GID token {token:?} has non-zero trailing bits"
+            )));
+        }
+        let gid_type = match bytes[16] {
+            0 => GidType::Other(String::new()),
+            1 => GidType::IB,
+            2 => GidType::RoCEv1,
+            3 => GidType::RoCEv2,
+            code => return Err(invalid(format!("unknown GID type code {code:#04x}"))),
+        };
+        Ok(Gid {
+            index: 0,
+            gid: ibv_gid {
+                raw: std::array::from_fn(|i| bytes[i]),
+            },
+            gid_type,
+        })
+    }
+}
+
+/// Returns the index of the interface `name`, or `None` if it does not exist.
+fn if_nametoindex(name: &str) -> Option<u32> {
+    let name = std::ffi::CString::new(name).ok()?;
+    let index = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    (index != 0).then_some(index)
+}
+
 /// RDMA device port information.
 ///
 /// Contains port attributes and the list of available GIDs
@@ -64,4 +804,1130 @@ pub struct Port {
     pub port_attr: ibv_port_attr,
     /// The GID (Global Identifier) list of the port.
     pub gids: Vec<Gid>,
+    /// The valid partition keys (P_Keys) of the port, in host byte order.
+    #[serde(default)]
+    pub pkeys: Vec<u16>,
+}
+
+impl Port {
+    /// Mask selecting the partition number, excluding the membership bit.
+    const PKEY_PARTITION_MASK: u16 = 0x7fff;
+
+    /// Returns the LID assigned to the port by the subnet manager.
+    ///
+    /// InfiniBand address handles route by LID; RoCE ports report 0.
+    pub fn lid(&self) -> u16 {
+        self.port_attr.lid
+    }
+
+    /// Returns the subnet timeout, encoded as 4.096us * 2^value.
+    ///
+    /// The subnet manager sets this as the expected subnet propagation
+    /// delay; IB connection setup derives QP ack timeouts from it.
+    pub fn subnet_timeout(&self) -> u8 {
+        self.port_attr.subnet_timeout
+    }
+
+    /// Returns true if the port reports the given capability flag.
+    ///
+    /// Passing several flags or-ed together requires all of them, e.g.
+    /// `IBV_PORT_IP_BASED_GIDS` marks a RoCE port.
+    pub fn has_cap(&self, flag: ibv_port_cap_flags) -> bool {
+        self.port_attr.port_cap_flags & flag == flag
+    }
+
+    /// Returns the approximate link rate in Gbps, or 0.0 if unknown.
+    ///
+    /// Multiplies the per-lane rate encoded in `active_speed` by the lane
+    /// count encoded in `active_width`, using the nominal figures that
+    /// `ibstat` reports:
+    ///
+    /// | `active_speed` | Name  | Gbps/lane |
+    /// |----------------|-------|-----------|
+    /// | 1              | SDR   | 2.5       |
+    /// | 2              | DDR   | 5         |
+    /// | 4              | QDR   | 10        |
+    /// | 8              | FDR10 | 10        |
+    /// | 16             | FDR   | 14        |
+    /// | 32             | EDR   | 25        |
+    /// | 64             | HDR   | 50        |
+    /// | 128            | NDR   | 100       |
+    ///
+    /// | `active_width` | Lanes |
+    /// |----------------|-------|
+    /// | 1              | 1x    |
+    /// | 2              | 4x    |
+    /// | 4              | 8x    |
+    /// | 8              | 12x   |
+    /// | 16             | 2x    |
+    ///
+    /// A 4x EDR port thus reports 100 Gbps. RoCE providers map the Ethernet
+    /// speed onto the same codes.
+    pub fn link_speed_gbps(&self) -> f64 {
+        let lane_gbps = match self.port_attr.active_speed {
+            1 => 2.5,
+            2 => 5.0,
+            4 | 8 => 10.0,
+            16 => 14.0,
+            32 => 25.0,
+            64 => 50.0,
+            128 => 100.0,
+            _ => return 0.0,
+        };
+        let lanes = match self.port_attr.active_width {
+            1 => 1.0,
+            2 => 4.0,
+            4 => 8.0,
+            8 => 12.0,
+            16 => 2.0,
+            _ => return 0.0,
+        };
+        lane_gbps * lanes
+    }
+
+    /// Returns true if the port carries the partition of `pkey`.
+    ///
+    /// Only the low 15 bits are compared, so full (`0x8001`) and limited
+    /// (`0x0001`) membership of the same partition both match.
+    pub fn has_pkey(&self, pkey: u16) -> bool {
+        self.pkeys
+            .iter()
+            .any(|&p| p & Self::PKEY_PARTITION_MASK == pkey & Self::PKEY_PARTITION_MASK)
+    }
+
+    /// Removes GIDs whose address already appeared at a lower index.
+    ///
+    /// Devices often expose one address at several indices, e.g. once as
+    /// RoCEv1 and once as RoCEv2; only the first entry is kept, so the
+    /// types of later duplicates are no longer listed.
+    pub fn dedup_gids(&mut self) {
+        let mut seen = HashSet::with_capacity(self.gids.len());
+        self.gids.retain(|gid| seen.insert(gid.gid));
+    }
+
+    /// Returns the number of non-null GIDs on the port.
+    ///
+    /// Unlike `port_attr.gid_tbl_len`, the size of the hardware table, this
+    /// counts only populated entries. It equals `gids.len()` unless the
+    /// device was opened with [`DeviceConfig::include_null_gids`](crate::DeviceConfig).
+    pub fn valid_gid_count(&self) -> usize {
+        self.gids.iter().filter(|gid| !gid.gid.is_null()).count()
+    }
+
+    /// Returns the first GID of the earliest type listed in `preference`.
+    ///
+    /// Types are tried in order, so `[RoCEv2, RoCEv1]` only falls back to a
+    /// RoCEv1 GID if the port has no RoCEv2 GID. An empty preference
+    /// accepts any type and returns the first GID. Returns `None` if no GID
+    /// has a listed type.
+    pub fn preferred_gid(&self, preference: &[GidType]) -> Option<&Gid> {
+        if preference.is_empty() {
+            return self.gids.first();
+        }
+        preference
+            .iter()
+            .find_map(|gid_type| self.gids.iter().find(|gid| &gid.gid_type == gid_type))
+    }
+
+    /// Returns the GID a RoCE connection should use by default.
+    ///
+    /// Picks the lowest-index RoCEv2 GID that is not link-local, falling
+    /// back to the lowest-index RoCEv2 GID of any scope. Returns `None` if
+    /// the port has no RoCEv2 GID.
+    ///
+    /// The kernel fills the RoCE GID table in pairs per address, RoCEv1 at
+    /// the lower index and RoCEv2 above it, starting with the link-local
+    /// address derived from the MAC. Index 0 is therefore RoCEv1 and index
+    /// 1 is link-local RoCEv2: the first only reaches peers in the same L2
+    /// domain that also speak RoCEv1, the second is not routable. Both
+    /// fail silently, with the QP reaching RTS and every send retrying
+    /// until it times out, when the peer uses a routable RoCEv2 GID.
+    pub fn default_roce_gid(&self) -> Option<&Gid> {
+        let roce_v2 = || {
+            self.gids
+                .iter()
+                .filter(|gid| gid.gid_type == GidType::RoCEv2 && !gid.gid.is_null())
+        };
+        roce_v2()
+            .filter(|gid| !gid.gid.is_link_local())
+            .min_by_key(|gid| gid.index)
+            .or_else(|| roce_v2().min_by_key(|gid| gid.index))
+    }
+
+    /// Returns a stable identifier for this port on the device with `guid`.
+    ///
+    /// The format is the GUID as four colon-separated groups of lowercase
+    /// hex digits, a slash, and the decimal port number, e.g.
+    /// `506b:0b03:0039:e8a4/1`. It does not depend on device names or
+    /// enumeration order, so it stays the same across restarts.
+    pub fn endpoint_id(&self, guid: &Guid) -> String {
+        format!("{guid}/{}", self.port_num)
+    }
+
+    /// Reads the hardware counters of this port from sysfs.
+    ///
+    /// Reads every file under `{ibdev_path}/ports/{port_num}/counters`, e.g.
+    /// `port_xmit_data`, `port_rcv_data` and `port_rcv_errors`, keyed by file
+    /// name. Files that don't contain a single unsigned integer are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::IBReadPortCountersFail`] if the counters directory
+    /// cannot be listed.
+    pub fn read_counters(&self, ibdev_path: &Path) -> Result<HashMap<String, u64>> {
+        let dir = ibdev_path.join(format!("ports/{}/counters", self.port_num));
+        let entries = std::fs::read_dir(&dir)
+            .map_err(|err| Error::new(ErrorKind::IBReadPortCountersFail, err.to_string()))?;
+
+        let mut counters = HashMap::new();
+        for entry in entries.flatten() {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            let Ok(content) = std::fs::read_to_string(entry.path()) else {
+                continue;
+            };
+            if let Ok(value) = content.trim().parse::<u64>() {
+                counters.insert(name, value);
+            }
+        }
+        Ok(counters)
+    }
+}
+
+/// Returns true if `pattern` is `name` or `name@guid`.
+pub(crate) fn name_matches(name: &str, guid: &Guid, pattern: &str) -> bool {
+    match pattern.strip_prefix(name) {
+        Some("") => true,
+        Some(rest) => rest
+            .strip_prefix('@')
+            .is_some_and(|g| g == guid.to_string()),
+        None => false,
+    }
+}
+
+/// Resource limits of a device, read from `ibv_device_attr`.
+///
+/// Exceeding these makes the verbs fail with `ENOMEM` or `EINVAL`, so size
+/// pools and queues against them up front.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct DeviceLimits {
+    /// Maximum number of queue pairs.
+    pub max_qp: u32,
+    /// Maximum number of outstanding work requests per queue.
+    pub max_qp_wr: u32,
+    /// Maximum number of entries of a completion queue.
+    pub max_cqe: u32,
+    /// Maximum number of memory regions.
+    pub max_mr: u32,
+    /// Maximum number of protection domains.
+    pub max_pd: u32,
+    /// Maximum number of scatter/gather entries per work request.
+    pub max_sge: u32,
+}
+
+/// A local address a connection can be made from: one GID of an active
+/// port, see [`DeviceInfo::endpoints`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Endpoint {
+    /// Name of the device, e.g. `mlx5_0`.
+    pub device_name: String,
+    /// Node GUID of the device.
+    pub guid: Guid,
+    /// Port number (1-based).
+    pub port_num: u8,
+    /// Index of the GID in the port's GID table.
+    pub gid_index: u16,
+    /// The GID value.
+    pub gid: ibv_gid,
+    /// The type of the GID.
+    pub gid_type: GidType,
+}
+
+/// A device list with the time it was collected.
+///
+/// This is the JSON envelope emitted by the CLI; its schema is printed by
+/// `ruapc-rdma-sys --schema`.
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema, Clone)]
+pub struct DevicesReport {
+    /// Opened devices, in enumeration order.
+    pub devices: Vec<DeviceInfo>,
+    /// Collection time as an RFC 3339 UTC timestamp (e.g.,
+    /// "2024-01-02T03:04:05Z").
+    pub generated_at: String,
+}
+
+impl DevicesReport {
+    /// Creates a report for `devices`, stamped with the current time.
+    pub fn new(devices: Vec<DeviceInfo>) -> Self {
+        Self {
+            devices,
+            generated_at: rfc3339_utc(SystemTime::now()),
+        }
+    }
+
+    /// Renders all devices as Prometheus text exposition format.
+    ///
+    /// Same metrics as [`DeviceInfo::to_metrics`], with each family's
+    /// `# HELP` and `# TYPE` lines written once ahead of its samples.
+    pub fn to_metrics(&self) -> String {
+        metrics_text(&self.devices)
+    }
+}
+
+/// Formats `time` as an RFC 3339 UTC timestamp with second precision.
+fn rfc3339_utc(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // Civil date from days since 1970-01-01, in 400-year eras from 0000-03-01
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let doe = days % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::fixtures::{TempSysfs, synthetic_gid, synthetic_port};
+    use crate::ibv_mtu;
+
+    #[test]
+    fn test_device_info_has_cap() {
+        let mut info = DeviceInfo::default();
+        info.device_attr.device_cap_flags = ibv_device_cap_flags::IBV_DEVICE_MEM_WINDOW
+            | ibv_device_cap_flags::IBV_DEVICE_RC_RNR_NAK_GEN;
+
+        assert!(info.has_cap(ibv_device_cap_flags::IBV_DEVICE_MEM_WINDOW));
+        assert!(info.has_cap(ibv_device_cap_flags::IBV_DEVICE_RC_RNR_NAK_GEN));
+        assert!(!info.has_cap(ibv_device_cap_flags::IBV_DEVICE_XRC));
+        assert!(info.has_cap(
+            ibv_device_cap_flags::IBV_DEVICE_MEM_WINDOW
+                | ibv_device_cap_flags::IBV_DEVICE_RC_RNR_NAK_GEN
+        ));
+        assert!(!info.has_cap(
+            ibv_device_cap_flags::IBV_DEVICE_MEM_WINDOW | ibv_device_cap_flags::IBV_DEVICE_XRC
+        ));
+    }
+
+    #[test]
+    fn test_active_ports() {
+        let info = DeviceInfo {
+            ports: vec![
+                synthetic_port(1, ibv_port_state::IBV_PORT_DOWN, LinkLayer::Ethernet),
+                synthetic_port(2, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet),
+                synthetic_port(3, ibv_port_state::IBV_PORT_INIT, LinkLayer::Ethernet),
+                synthetic_port(4, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet),
+            ],
+            ..Default::default()
+        };
+        let active: Vec<_> = info.active_ports().map(|port| port.port_num).collect();
+        assert_eq!(active, [2, 4]);
+        assert_eq!(info.ports.len(), 4);
+    }
+
+    #[test]
+    fn test_fabric_kind_single_port() {
+        let info = DeviceInfo {
+            ports: vec![synthetic_port(
+                1,
+                ibv_port_state::IBV_PORT_ACTIVE,
+                LinkLayer::Ethernet,
+            )],
+            ..Default::default()
+        };
+        assert_eq!(info.fabric_kind(), LinkLayer::Ethernet);
+        assert!(info.is_roce());
+        assert!(!info.is_infiniband());
+
+        let info = DeviceInfo::default();
+        assert_eq!(info.fabric_kind(), LinkLayer::Unspecified);
+        assert!(!info.is_roce());
+        assert!(!info.is_infiniband());
+    }
+
+    #[test]
+    fn test_fabric_kind_mixed_ports() {
+        let mut info = DeviceInfo {
+            ports: vec![
+                synthetic_port(1, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::InfiniBand),
+                synthetic_port(2, ibv_port_state::IBV_PORT_DOWN, LinkLayer::Ethernet),
+            ],
+            ..Default::default()
+        };
+        // Inactive ports don't count
+        assert_eq!(info.fabric_kind(), LinkLayer::InfiniBand);
+        assert!(info.is_infiniband());
+
+        info.ports[1].port_attr.state = ibv_port_state::IBV_PORT_ACTIVE.into();
+        assert_eq!(info.fabric_kind(), LinkLayer::Unspecified);
+        assert!(!info.is_roce());
+        assert!(!info.is_infiniband());
+    }
+
+    #[test]
+    fn test_best_gid_index() {
+        let mut port1 = synthetic_port(1, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet);
+        port1.gids = vec![
+            synthetic_gid(0, "::", GidType::RoCEv1),
+            synthetic_gid(1, "::", GidType::RoCEv2),
+            synthetic_gid(2, "::", GidType::RoCEv1),
+            synthetic_gid(3, "::", GidType::RoCEv2),
+        ];
+        let mut port2 = synthetic_port(2, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet);
+        port2.gids = vec![synthetic_gid(5, "::", GidType::RoCEv2)];
+        let mut info = DeviceInfo {
+            ports: vec![port1, port2],
+            ..Default::default()
+        };
+
+        // Without a cache the GIDs are scanned
+        assert_eq!(info.best_gid_index(1, &GidType::RoCEv2), Some(1));
+
+        info.rebuild_gid_index_cache();
+        assert_eq!(info.gid_index_cache.len(), 3);
+        assert_eq!(info.best_gid_index(1, &GidType::RoCEv1), Some(0));
+        assert_eq!(info.best_gid_index(1, &GidType::RoCEv2), Some(1));
+        assert_eq!(info.best_gid_index(2, &GidType::RoCEv2), Some(5));
+        assert_eq!(info.best_gid_index(2, &GidType::RoCEv1), None);
+        assert_eq!(info.best_gid_index(3, &GidType::RoCEv2), None);
+
+        // Deserialized snapshots carry a rebuilt cache
+        let json = serde_json::to_value(&info).unwrap();
+        assert!(json.get("gid_index_cache").is_none());
+        let decoded: DeviceInfo = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.gid_index_cache, info.gid_index_cache);
+
+        // Ports edited in place leave stale entries, which are not trusted
+        info.ports[0].gids.retain(|gid| gid.index != 1);
+        info.ports[1]
+            .gids
+            .push(synthetic_gid(6, "::", GidType::RoCEv1));
+        assert_eq!(info.best_gid_index(1, &GidType::RoCEv2), Some(3));
+        assert_eq!(info.best_gid_index(2, &GidType::RoCEv1), Some(6));
+        info.ports[1].gids.clear();
+        assert_eq!(info.best_gid_index(2, &GidType::RoCEv2), None);
+    }
+
+    #[test]
+    fn test_available_gid_types() {
+        let mut port1 = synthetic_port(1, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::InfiniBand);
+        port1.gids = vec![
+            synthetic_gid(0, "::", GidType::IB),
+            synthetic_gid(1, "::", GidType::IB),
+        ];
+        let mut port2 = synthetic_port(2, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet);
+        port2.gids = vec![synthetic_gid(0, "::", GidType::RoCEv2)];
+        let info = DeviceInfo {
+            ports: vec![port1, port2],
+            ..Default::default()
+        };
+        assert_eq!(
+            info.available_gid_types(),
+            HashSet::from([GidType::IB, GidType::RoCEv2])
+        );
+        assert!(DeviceInfo::default().available_gid_types().is_empty());
+    }
+
+    #[test]
+    fn test_device_info_sort_key() {
+        let info = |name: &str, guid: u64| DeviceInfo {
+            name: name.to_string(),
+            guid: Guid::from_be(guid.to_be()),
+            ..Default::default()
+        };
+        let mut infos = [
+            info("rxe_1", 1),
+            info("mlx5_0", 2),
+            info("rxe_0", 3),
+            info("mlx5_0", 1),
+        ];
+        infos.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+
+        let keys: Vec<_> = infos
+            .iter()
+            .map(|info| (info.name.as_str(), info.guid.to_string()))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                ("mlx5_0", "0000:0000:0000:0001".to_string()),
+                ("mlx5_0", "0000:0000:0000:0002".to_string()),
+                ("rxe_0", "0000:0000:0000:0003".to_string()),
+                ("rxe_1", "0000:0000:0000:0001".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_endpoint_id() {
+        let guid = Guid::from_be(u64::to_be(0x506B0B03_0039E8A4));
+        let port = synthetic_port(2, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet);
+        assert_eq!(port.endpoint_id(&guid), "506b:0b03:0039:e8a4/2");
+
+        let info = DeviceInfo {
+            guid,
+            ports: vec![port],
+            ..Default::default()
+        };
+        assert_eq!(
+            info.endpoint_id(2).as_deref(),
+            Some("506b:0b03:0039:e8a4/2")
+        );
+        assert_eq!(info.endpoint_id(1), None);
+    }
+
+    #[test]
+    fn test_device_info_endpoints() {
+        let mut port1 = synthetic_port(1, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet);
+        port1.gids = vec![
+            synthetic_gid(0, "::ffff:0.0.0.1", GidType::RoCEv2),
+            synthetic_gid(1, "::ffff:0.0.0.0", GidType::RoCEv2),
+            synthetic_gid(2, "::ffff:0.0.0.2", GidType::RoCEv2),
+        ];
+        port1.gids[1].gid = ibv_gid::default();
+        let mut port2 = synthetic_port(2, ibv_port_state::IBV_PORT_DOWN, LinkLayer::Ethernet);
+        port2.gids = vec![synthetic_gid(0, "::ffff:0.0.0.3", GidType::RoCEv2)];
+        let first = DeviceInfo {
+            name: "mlx5_0".to_string(),
+            guid: Guid::from_be(u64::to_be(0x506B0B03_0039E8A4)),
+            ports: vec![port1.clone(), port2],
+            ..Default::default()
+        };
+        let mut port3 = port1;
+        port3.gids.truncate(1);
+        let second = DeviceInfo {
+            name: "mlx5_1".to_string(),
+            ports: vec![port3],
+            ..Default::default()
+        };
+
+        // The null GID and the GIDs of the inactive port are skipped
+        let endpoints: Vec<_> = [&first, &second]
+            .into_iter()
+            .flat_map(DeviceInfo::endpoints)
+            .collect();
+        assert_eq!(endpoints.len(), 3);
+        assert_eq!(
+            endpoints[1],
+            Endpoint {
+                device_name: "mlx5_0".to_string(),
+                guid: first.guid,
+                port_num: 1,
+                gid_index: 2,
+                gid: synthetic_gid(2, "::ffff:0.0.0.2", GidType::RoCEv2).gid,
+                gid_type: GidType::RoCEv2,
+            }
+        );
+        assert_eq!(endpoints[2].device_name, "mlx5_1");
+
+        let json = serde_json::to_value(&endpoints[0]).unwrap();
+        assert_eq!(json["gid"], "::ffff:0.0.0.1");
+        assert_eq!(
+            serde_json::from_value::<Endpoint>(json).unwrap(),
+            endpoints[0]
+        );
+    }
+
+    #[test]
+    fn test_clamp_cqe() {
+        let mut info = DeviceInfo::default();
+        assert_eq!(info.max_cqe(), 0);
+        assert_eq!(info.clamp_cqe(1 << 30), 1 << 30);
+
+        info.device_attr.max_cqe = (1 << 22) - 1;
+        assert_eq!(info.max_cqe(), (1 << 22) - 1);
+        assert_eq!(info.clamp_cqe(u32::MAX), (1 << 22) - 1);
+        assert_eq!(info.clamp_cqe(1 << 22), (1 << 22) - 1);
+        assert_eq!(info.clamp_cqe(256), 256);
+    }
+
+    #[test]
+    fn test_core_clock() {
+        let mut info = DeviceInfo::default();
+        info.device_attr.vendor_part_id = 4119;
+        assert_eq!(info.vendor_part_id(), 4119);
+        assert_eq!(info.hca_core_clock_khz(), None);
+        assert_eq!(info.timestamp_to_ns(1000), None);
+        let json = serde_json::to_string(&info).unwrap();
+        assert!(!json.contains("hca_core_clock"));
+
+        // 156.25 MHz: 6.4ns per tick
+        info.hca_core_clock = 156_250;
+        assert_eq!(info.hca_core_clock_khz(), Some(156_250));
+        assert_eq!(info.timestamp_to_ns(0), Some(0.0));
+        assert_eq!(info.timestamp_to_ns(1000), Some(6400.0));
+        let json = serde_json::to_string(&info).unwrap();
+        let der = serde_json::from_str::<DeviceInfo>(&json).unwrap();
+        assert_eq!(der.hca_core_clock, 156_250);
+    }
+
+    #[test]
+    fn test_device_limits() {
+        let mut info = DeviceInfo::default();
+        assert_eq!(info.limits(), DeviceLimits::default());
+
+        info.device_attr = ibv_device_attr {
+            max_qp: 131072,
+            max_qp_wr: 32768,
+            max_cqe: 4194303,
+            max_mr: 16777216,
+            max_pd: 8388608,
+            max_sge: 30,
+            ..Default::default()
+        };
+        assert_eq!(info.max_qp(), 131072);
+        assert_eq!(info.max_sge(), 30);
+        let limits = info.limits();
+        assert_eq!(
+            limits,
+            DeviceLimits {
+                max_qp: 131072,
+                max_qp_wr: 32768,
+                max_cqe: 4194303,
+                max_mr: 16777216,
+                max_pd: 8388608,
+                max_sge: 30,
+            }
+        );
+
+        let json = serde_json::to_string(&limits).unwrap();
+        assert_eq!(serde_json::from_str::<DeviceLimits>(&json).unwrap(), limits);
+
+        info.device_attr.max_qp_rd_atom = 16;
+        info.device_attr.max_res_rd_atom = 2097152;
+        assert_eq!(info.max_qp_rd_atom(), 16);
+        assert_eq!(info.max_res_rd_atom(), 2097152);
+        assert_eq!(info.device_attr().max_qp, 131072);
+        info.device_attr.max_qp_rd_atom = -1;
+        assert_eq!(info.max_qp_rd_atom(), 0);
+
+        info.device_attr.max_qp = -1;
+        assert_eq!(info.max_qp(), 0);
+    }
+
+    #[test]
+    fn test_roce_ports() {
+        let mut roce = synthetic_port(1, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet);
+        roce.gids = vec![synthetic_gid(0, "fe80::1", GidType::RoCEv2)];
+        let mut down = synthetic_port(2, ibv_port_state::IBV_PORT_DOWN, LinkLayer::Ethernet);
+        down.gids = vec![synthetic_gid(0, "fe80::1", GidType::RoCEv2)];
+        let no_gids = synthetic_port(3, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet);
+        let mut ib = synthetic_port(4, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::InfiniBand);
+        ib.gids = vec![synthetic_gid(0, "fe80::1", GidType::IB)];
+
+        let info = DeviceInfo {
+            ports: vec![roce, down, no_gids, ib],
+            ..Default::default()
+        };
+        let ports: Vec<_> = info.roce_ports().map(|port| port.port_num).collect();
+        assert_eq!(ports, [1]);
+
+        let info = DeviceInfo {
+            ports: info.ports[1..].to_vec(),
+            ..Default::default()
+        };
+        assert_eq!(info.roce_ports().count(), 0);
+    }
+
+    #[test]
+    fn test_port_lid_and_subnet_timeout() {
+        let mut port = synthetic_port(1, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::InfiniBand);
+        port.port_attr.lid = 0x1a;
+        port.port_attr.subnet_timeout = 18;
+        assert_eq!(port.lid(), 0x1a);
+        assert_eq!(port.subnet_timeout(), 18);
+
+        let roce = synthetic_port(1, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet);
+        assert_eq!(roce.lid(), 0);
+    }
+
+    #[test]
+    fn test_port_has_cap() {
+        let mut port = synthetic_port(1, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet);
+        assert!(!port.has_cap(ibv_port_cap_flags::IBV_PORT_IP_BASED_GIDS));
+        port.port_attr.port_cap_flags =
+            ibv_port_cap_flags::IBV_PORT_CM_SUP | ibv_port_cap_flags::IBV_PORT_IP_BASED_GIDS;
+
+        assert!(port.has_cap(ibv_port_cap_flags::IBV_PORT_IP_BASED_GIDS));
+        assert!(port.has_cap(
+            ibv_port_cap_flags::IBV_PORT_CM_SUP | ibv_port_cap_flags::IBV_PORT_IP_BASED_GIDS
+        ));
+        assert!(!port.has_cap(ibv_port_cap_flags::IBV_PORT_SM));
+        assert!(
+            !port.has_cap(ibv_port_cap_flags::IBV_PORT_CM_SUP | ibv_port_cap_flags::IBV_PORT_SM)
+        );
+
+        let json = serde_json::to_value(&port).unwrap();
+        assert_eq!(
+            json["port_attr"]["port_cap_flags"],
+            serde_json::json!(["CM", "IP_BASED_GIDS"])
+        );
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        let info = |name: &str, guid: u64| DeviceInfo {
+            name: name.to_string(),
+            guid: Guid::from_be(guid.to_be()),
+            ..Default::default()
+        };
+        let infos = [
+            info("mlx5_bond_0", 0x506b_0b03_0039_e8a4),
+            info("mlx5_0", 0x506b_0b03_0039_e8a5),
+            info("mlx5_bond_0", 0x506b_0b03_0039_e8a6),
+        ];
+        assert_eq!(DeviceInfo::duplicate_names(&infos), ["mlx5_bond_0"]);
+        assert!(DeviceInfo::duplicate_names(&infos[..2]).is_empty());
+
+        assert_eq!(infos[2].qualified_name(), "mlx5_bond_0@506b:0b03:0039:e8a6");
+        assert!(infos[0].matches_name("mlx5_bond_0"));
+        assert!(infos[2].matches_name("mlx5_bond_0"));
+        let qualified = infos[2].qualified_name();
+        let matched: Vec<_> = infos
+            .iter()
+            .filter(|i| i.matches_name(&qualified))
+            .collect();
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].guid, infos[2].guid);
+
+        assert!(!infos[0].matches_name("mlx5_bond"));
+        assert!(!infos[0].matches_name("mlx5_bond_0@"));
+        assert!(!infos[0].matches_name("mlx5_bond_0x"));
+    }
+
+    #[test]
+    fn test_read_counters() {
+        let sysfs = TempSysfs::new("counters");
+        sysfs.write("ports/1/counters/port_xmit_data", "123456\n");
+        sysfs.write("ports/1/counters/port_rcv_data", "654321\n");
+        sysfs.write("ports/1/counters/port_rcv_errors", "0\n");
+        sysfs.write("ports/1/counters/not_a_counter", "N/A\n");
+        let ibdev_path = sysfs.path();
+
+        let port = synthetic_port(1, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet);
+        let counters = port.read_counters(ibdev_path).unwrap();
+        assert_eq!(counters.len(), 3);
+        assert_eq!(counters["port_xmit_data"], 123456);
+        assert_eq!(counters["port_rcv_data"], 654321);
+        assert_eq!(counters["port_rcv_errors"], 0);
+
+        let json = serde_json::to_value(&counters).unwrap();
+        assert_eq!(json["port_xmit_data"], 123456);
+
+        let port = synthetic_port(2, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet);
+        let err = port.read_counters(ibdev_path).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::IBReadPortCountersFail);
+    }
+
+    #[test]
+    fn test_port_dedup_gids() {
+        let mut port = synthetic_port(1, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet);
+        port.gids = vec![
+            synthetic_gid(0, "fe80::1", GidType::RoCEv1),
+            synthetic_gid(1, "fe80::1", GidType::RoCEv2),
+            synthetic_gid(2, "::ffff:192.168.1.2", GidType::RoCEv1),
+            synthetic_gid(3, "::ffff:192.168.1.2", GidType::RoCEv2),
+            synthetic_gid(4, "::ffff:192.168.1.3", GidType::RoCEv2),
+        ];
+        port.dedup_gids();
+        let kept: Vec<_> = port
+            .gids
+            .iter()
+            .map(|gid| (gid.index, gid.gid_type.clone()))
+            .collect();
+        assert_eq!(
+            kept,
+            [
+                (0, GidType::RoCEv1),
+                (2, GidType::RoCEv1),
+                (4, GidType::RoCEv2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_port_valid_gid_count() {
+        let mut port = synthetic_port(1, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet);
+        port.port_attr.gid_tbl_len = 16;
+        assert_eq!(port.valid_gid_count(), 0);
+
+        // Two populated entries in a table padded with null GIDs
+        port.gids = (0..16u16)
+            .map(|index| {
+                let addr = match index {
+                    0 => "fe80::1",
+                    1 => "::ffff:192.168.1.2",
+                    _ => "::",
+                };
+                synthetic_gid(index, addr, GidType::RoCEv2)
+            })
+            .collect();
+        assert_eq!(port.valid_gid_count(), 2);
+        assert_eq!(port.gids.len(), 16);
+    }
+
+    #[test]
+    fn test_port_link_speed() {
+        let mut port = synthetic_port(1, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::InfiniBand);
+        let mut speed = |active_speed: u8, active_width: u8| {
+            port.port_attr.active_speed = active_speed;
+            port.port_attr.active_width = active_width;
+            port.link_speed_gbps()
+        };
+        assert_eq!(speed(1, 1), 2.5); // 1x SDR
+        assert_eq!(speed(4, 2), 40.0); // 4x QDR
+        assert_eq!(speed(16, 2), 56.0); // 4x FDR
+        assert_eq!(speed(32, 2), 100.0); // 4x EDR
+        assert_eq!(speed(64, 2), 200.0); // 4x HDR
+        assert_eq!(speed(64, 16), 100.0); // 2x HDR100
+        assert_eq!(speed(128, 2), 400.0); // 4x NDR
+        assert_eq!(speed(2, 8), 60.0); // 12x DDR
+        assert_eq!(speed(0, 2), 0.0);
+        assert_eq!(speed(32, 0), 0.0);
+    }
+
+    /// Checks that `line` is a valid sample: `name{labels} value`.
+    fn assert_metric_line(line: &str) {
+        let (series, value) = line.rsplit_once(' ').expect(line);
+        value.parse::<f64>().expect(line);
+        let (name, labels) = match series.split_once('{') {
+            Some((name, labels)) => (name, labels.strip_suffix('}').expect(line)),
+            None => (series, ""),
+        };
+        assert!(
+            name.starts_with("rdma_")
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+            "{line}"
+        );
+        for label in labels.split("\",").filter(|label| !label.is_empty()) {
+            let (key, value) = label.split_once('=').expect(line);
+            assert!(
+                key.chars().all(|c| c.is_ascii_lowercase() || c == '_'),
+                "{line}"
+            );
+            assert!(value.starts_with('"'), "{line}");
+        }
+        assert!(labels.is_empty() || labels.ends_with('"'), "{line}");
+    }
+
+    #[test]
+    fn test_device_info_to_metrics() {
+        let mut port = synthetic_port(1, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet);
+        port.port_attr.active_speed = 32;
+        port.port_attr.active_width = 2;
+        port.gids = vec![synthetic_gid(0, "::", GidType::RoCEv2)];
+        let mut info = DeviceInfo {
+            name: "mlx5_0".to_string(),
+            board_id: r#"MT_"x""#.to_string(),
+            ports: vec![
+                port,
+                synthetic_port(2, ibv_port_state::IBV_PORT_DOWN, LinkLayer::Ethernet),
+            ],
+            ..Default::default()
+        };
+        info.device_attr.max_qp = 1024;
+
+        let text = info.to_metrics();
+        let samples: Vec<_> = text.lines().filter(|l| !l.starts_with('#')).collect();
+        samples.iter().for_each(|line| assert_metric_line(line));
+        for expected in [
+            r#"rdma_device_max_qp{device="mlx5_0"} 1024"#,
+            r#"rdma_port_state{device="mlx5_0",port="1"} 4"#,
+            r#"rdma_port_state{device="mlx5_0",port="2"} 1"#,
+            r#"rdma_port_active_mtu_bytes{device="mlx5_0",port="1"} 1024"#,
+            r#"rdma_port_link_speed_gbps{device="mlx5_0",port="1"} 100"#,
+            r#"rdma_port_gids{device="mlx5_0",port="1"} 0"#,
+        ] {
+            assert!(samples.contains(&expected), "missing {expected} in\n{text}");
+        }
+        assert!(text.contains(r#"board_id="MT_\"x\"""#), "{text}");
+        assert_eq!(
+            samples.len(),
+            1 + DEVICE_GAUGES.len() + 2 * PORT_GAUGES.len()
+        );
+
+        let report = DevicesReport::new(vec![info.clone(), info]);
+        let text = report.to_metrics();
+        let families = text.lines().filter(|l| l.starts_with("# TYPE")).count();
+        assert_eq!(families, 1 + DEVICE_GAUGES.len() + PORT_GAUGES.len());
+        let samples = text.lines().filter(|l| !l.starts_with('#')).count();
+        assert_eq!(
+            samples,
+            2 * (1 + DEVICE_GAUGES.len() + 2 * PORT_GAUGES.len())
+        );
+    }
+
+    #[test]
+    fn test_device_info_diff() {
+        let mut port1 = synthetic_port(1, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet);
+        port1.gids = vec![
+            synthetic_gid(0, "::ffff:0.0.0.1", GidType::RoCEv2),
+            synthetic_gid(1, "::ffff:0.0.0.2", GidType::RoCEv2),
+        ];
+        let port2 = synthetic_port(2, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet);
+        let before = DeviceInfo {
+            ports: vec![port1, port2],
+            ..Default::default()
+        };
+        assert!(before.diff(&before).is_empty());
+
+        let mut after = before.clone();
+        after.ports[0].port_attr.state = ibv_port_state::IBV_PORT_DOWN.into();
+        after.ports[0].port_attr.active_mtu = ibv_mtu::IBV_MTU_4096.into();
+        after.ports[0].gids = vec![
+            synthetic_gid(0, "::ffff:0.0.0.1", GidType::RoCEv2),
+            synthetic_gid(1, "::ffff:0.0.0.3", GidType::RoCEv2),
+            synthetic_gid(2, "::ffff:0.0.0.4", GidType::RoCEv2),
+        ];
+        after.ports[1].port_num = 3;
+        assert_eq!(
+            before.diff(&after),
+            [
+                DeviceChange::PortStateChanged {
+                    port: 1,
+                    from: ibv_port_state::IBV_PORT_ACTIVE.into(),
+                    to: ibv_port_state::IBV_PORT_DOWN.into(),
+                },
+                DeviceChange::ActiveMtuChanged {
+                    port: 1,
+                    from: ibv_mtu::IBV_MTU_1024.into(),
+                    to: ibv_mtu::IBV_MTU_4096.into(),
+                },
+                DeviceChange::GidRemoved {
+                    port: 1,
+                    gid: synthetic_gid(1, "::ffff:0.0.0.2", GidType::RoCEv2),
+                },
+                DeviceChange::GidAdded {
+                    port: 1,
+                    gid: synthetic_gid(1, "::ffff:0.0.0.3", GidType::RoCEv2),
+                },
+                DeviceChange::GidAdded {
+                    port: 1,
+                    gid: synthetic_gid(2, "::ffff:0.0.0.4", GidType::RoCEv2),
+                },
+                DeviceChange::PortRemoved { port: 2 },
+                DeviceChange::PortAdded { port: 3 },
+            ]
+        );
+
+        let changes = after.diff(&before);
+        assert!(changes.contains(&DeviceChange::PortAdded { port: 2 }));
+        assert!(changes.contains(&DeviceChange::GidRemoved {
+            port: 1,
+            gid: synthetic_gid(2, "::ffff:0.0.0.4", GidType::RoCEv2)
+        }));
+        let json = serde_json::to_value(&changes[0]).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"PortStateChanged": {"port": 1, "from": "DOWN", "to": "ACTIVE"}})
+        );
+    }
+
+    #[test]
+    fn test_port_preferred_gid() {
+        let mut port = synthetic_port(1, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet);
+        port.gids = vec![
+            synthetic_gid(0, "::", GidType::RoCEv1),
+            synthetic_gid(1, "::", GidType::RoCEv2),
+            synthetic_gid(2, "::", GidType::RoCEv1),
+            synthetic_gid(3, "::", GidType::RoCEv2),
+        ];
+        let index = |preference: &[GidType]| port.preferred_gid(preference).map(|gid| gid.index);
+
+        assert_eq!(index(&[GidType::RoCEv2, GidType::RoCEv1]), Some(1));
+        assert_eq!(index(&[GidType::RoCEv1, GidType::RoCEv2]), Some(0));
+        assert_eq!(index(&[GidType::IB, GidType::RoCEv2]), Some(1));
+        assert_eq!(index(&[GidType::IB]), None);
+        assert_eq!(index(&[]), Some(0));
+
+        let config = crate::DeviceConfig::builder()
+            .prefer_gid_type(GidType::IB)
+            .prefer_gid_type(GidType::RoCEv2)
+            .build();
+        assert_eq!(config.gid_type_preference, [GidType::IB, GidType::RoCEv2]);
+        assert_eq!(index(&config.gid_type_preference), Some(1));
+    }
+
+    #[test]
+    fn test_port_default_roce_gid() {
+        let mut port = synthetic_port(1, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::Ethernet);
+        port.gids = vec![
+            synthetic_gid(0, "fe80::bace:f6ff:fe2b:6d1c", GidType::RoCEv1),
+            synthetic_gid(1, "fe80::bace:f6ff:fe2b:6d1c", GidType::RoCEv2),
+            synthetic_gid(2, "::ffff:10.0.0.12", GidType::RoCEv1),
+            synthetic_gid(3, "::ffff:10.0.0.12", GidType::RoCEv2),
+            synthetic_gid(4, "fd00::12", GidType::RoCEv1),
+            synthetic_gid(5, "fd00::12", GidType::RoCEv2),
+        ];
+        let index = |port: &Port| port.default_roce_gid().map(|gid| gid.index);
+        assert_eq!(index(&port), Some(3));
+
+        // Only the link-local pair left: fall back to its RoCEv2 entry
+        port.gids.truncate(2);
+        assert_eq!(index(&port), Some(1));
+
+        port.gids.remove(1);
+        assert_eq!(index(&port), None);
+    }
+
+    #[test]
+    fn test_board_id() {
+        let sysfs = TempSysfs::new("board-id");
+        assert_eq!(DeviceInfo::read_board_id(sysfs.path()), "");
+
+        sysfs.write("board_id", "MT_0000000223\n");
+        let board_id = DeviceInfo::read_board_id(sysfs.path());
+        assert_eq!(board_id, "MT_0000000223");
+
+        let info = DeviceInfo {
+            board_id,
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["board_id"], "MT_0000000223");
+
+        // Snapshots without a board id round-trip without the field
+        let json = serde_json::to_value(DeviceInfo::default()).unwrap();
+        assert!(json.get("board_id").is_none());
+        let info: DeviceInfo = serde_json::from_value(json).unwrap();
+        assert!(info.board_id.is_empty());
+    }
+
+    #[test]
+    fn test_gid_token() {
+        let roce = synthetic_gid(3, "::ffff:192.168.1.2", GidType::RoCEv2);
+        let token = roce.to_token();
+        assert_eq!(token, "AAAAAAAAAAAAAP__wKgBAgM");
+        let decoded = Gid::from_token(&token).unwrap();
+        assert_eq!((decoded.gid, &decoded.gid_type), (roce.gid, &roce.gid_type));
+        assert_eq!(decoded.index, 0);
+
+        for gid_type in [GidType::IB, GidType::RoCEv1] {
+            let ib = synthetic_gid(3, "fe80::2:c903:a:1f21", gid_type.clone());
+            assert_eq!(Gid::from_token(&ib.to_token()).unwrap().gid_type, gid_type);
+        }
+        let other = synthetic_gid(3, "fe80::1", GidType::Other("custom".to_string()));
+        let decoded = Gid::from_token(&other.to_token()).unwrap();
+        assert_eq!(decoded.gid_type, GidType::Other(String::new()));
+
+        for token in [
+            "",
+            &token[..22],
+            &format!("{token}="),
+            "AAAAAAAAAAAAAP//wKgBAgM",
+            "AAAAAAAAAAAAAP__wKgBAgé",
+            // Trailing bits set
+            "AAAAAAAAAAAAAP__wKgBAgN",
+            // Type code 0xff
+            "_oAAAAAAAAAAAskDAAofIf8",
+        ] {
+            let err = Gid::from_token(token).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::InvalidConnectionInfo, "{token:?}");
+        }
+    }
+
+    #[test]
+    fn test_gid_netdev_index() {
+        let sysfs = TempSysfs::new("ndev-index");
+        sysfs.write("ports/1/gid_attrs/ndevs/0", "lo\n");
+        sysfs.write("ports/1/gid_attrs/ndevs/1", "ruapc-nodev0\n");
+        let ibdev_path = sysfs.path();
+
+        let lo = if_nametoindex("lo");
+        assert!(lo.is_some());
+        let mut gid = synthetic_gid(0, "::ffff:127.0.0.1", GidType::RoCEv2);
+        assert_eq!(gid.netdev_index(ibdev_path, 1), lo);
+        gid.index = 1;
+        assert_eq!(gid.netdev_index(ibdev_path, 1), None);
+        gid.index = 2;
+        assert_eq!(gid.netdev_index(ibdev_path, 1), None);
+        assert_eq!(if_nametoindex("lo\0"), None);
+    }
+
+    #[test]
+    fn test_gid_netdev() {
+        let sysfs = TempSysfs::new("ndevs");
+        sysfs.write("ports/1/gid_attrs/ndevs/0", "eth0\n");
+        sysfs.write("ports/1/gid_attrs/ndevs/1", "eth0\n");
+        let ibdev_path = sysfs.path();
+
+        let link_local = synthetic_gid(0, "fe80::1", GidType::RoCEv2);
+        assert_eq!(link_local.netdev(ibdev_path, 1).as_deref(), Some("eth0"));
+        assert_eq!(link_local.scoped_addr(ibdev_path, 1), "fe80::1%eth0");
+
+        let global = synthetic_gid(1, "::ffff:192.168.1.2", GidType::RoCEv2);
+        assert_eq!(global.scoped_addr(ibdev_path, 1), "::ffff:192.168.1.2");
+
+        let missing = synthetic_gid(2, "fe80::2", GidType::RoCEv2);
+        assert_eq!(missing.netdev(ibdev_path, 1), None);
+        assert_eq!(missing.scoped_addr(ibdev_path, 1), "fe80::2");
+    }
+
+    #[test]
+    fn test_port_pkeys() {
+        let mut port = synthetic_port(1, ibv_port_state::IBV_PORT_ACTIVE, LinkLayer::InfiniBand);
+        port.pkeys = vec![0xffff, 0x8001];
+        assert!(port.has_pkey(0xffff));
+        assert!(port.has_pkey(0x7fff));
+        assert!(port.has_pkey(0x8001));
+        assert!(port.has_pkey(0x0001));
+        assert!(!port.has_pkey(0x8002));
+
+        let json = serde_json::to_value(&port).unwrap();
+        assert_eq!(json["pkeys"], serde_json::json!([0xffff, 0x8001]));
+        let der: Port = serde_json::from_value(json).unwrap();
+        assert_eq!(der.pkeys, port.pkeys);
+    }
+
+    #[test]
+    fn test_rfc3339_utc() {
+        let at = |secs: u64| rfc3339_utc(UNIX_EPOCH + std::time::Duration::from_secs(secs));
+        assert_eq!(at(0), "1970-01-01T00:00:00Z");
+        assert_eq!(at(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(at(1_704_164_645), "2024-01-02T03:04:05Z");
+        assert_eq!(at(4_107_542_399), "2100-02-28T23:59:59Z");
+    }
+
+    #[test]
+    fn test_devices_report_roundtrip() {
+        let report = DevicesReport::new(vec![DeviceInfo {
+            name: "mlx5_0".to_string(),
+            guid: Guid::from_be(0x0102_0304_0506_0708u64.to_be()),
+            ports: vec![synthetic_port(
+                1,
+                ibv_port_state::IBV_PORT_ACTIVE,
+                LinkLayer::Ethernet,
+            )],
+            ..Default::default()
+        }]);
+        assert_eq!(report.generated_at.len(), "1970-01-01T00:00:00Z".len());
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["devices"][0]["name"], "mlx5_0");
+        let der: DevicesReport = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(der.generated_at, report.generated_at);
+        assert_eq!(der.devices.len(), 1);
+        assert_eq!(der.devices[0].sort_key(), report.devices[0].sort_key());
+        assert_eq!(serde_json::to_value(&der).unwrap(), json);
+
+        let schema = serde_json::to_value(schemars::schema_for!(DevicesReport)).unwrap();
+        assert!(schema["properties"]["devices"].is_object());
+        assert!(schema["properties"]["generated_at"].is_object());
+    }
 }
@@ -0,0 +1,200 @@
+//! # Device topology watch
+//!
+//! [`Devices::watch`] re-enumerates the devices whenever an RDMA device is
+//! added, removed or renamed, e.g. when SR-IOV VFs are created or a NIC is
+//! hot-unplugged.
+//!
+//! Changes are observed through kernel uevents on a `NETLINK_KOBJECT_UEVENT`
+//! socket. Watching `/sys/class/infiniband` with inotify does not work:
+//! sysfs entries are created by the kernel, which emits no inotify events
+//! for them.
+
+use std::{
+    ops::ControlFlow,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+};
+
+use super::Devices;
+use crate::{DeviceConfig, ErrorKind, Guid, Result};
+
+/// Multicast group of the uevents sent by the kernel (udev uses group 2).
+const KERNEL_UEVENT_GROUP: u32 = 1;
+
+/// Size of the uevent receive buffer; the kernel caps a uevent at 2 KiB.
+const UEVENT_BUFFER_SIZE: usize = 8192;
+
+/// Netlink socket subscribed to kernel uevents.
+struct UeventSocket(OwnedFd);
+
+impl UeventSocket {
+    /// Opens the socket and joins the kernel uevent group.
+    fn open() -> Result<Self> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                libc::NETLINK_KOBJECT_UEVENT,
+            )
+        };
+        if fd < 0 {
+            return Err(ErrorKind::WatchDevicesFailed.with_errno());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        addr.nl_groups = KERNEL_UEVENT_GROUP;
+        let ret = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                (&addr as *const libc::sockaddr_nl).cast(),
+                size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if ret != 0 {
+            return Err(ErrorKind::WatchDevicesFailed.with_errno());
+        }
+        Ok(Self(fd))
+    }
+
+    /// Blocks for the next uevent and returns true if it may change the
+    /// RDMA device list.
+    ///
+    /// A receive buffer overrun loses events, so it also returns true.
+    fn next_is_device_event(&self, buf: &mut [u8]) -> Result<bool> {
+        loop {
+            let len =
+                unsafe { libc::recv(self.0.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0) };
+            if len >= 0 {
+                return Ok(is_device_uevent(&buf[..len as usize]));
+            }
+            match std::io::Error::last_os_error().raw_os_error() {
+                Some(libc::EINTR) => continue,
+                Some(libc::ENOBUFS) => return Ok(true),
+                _ => return Err(ErrorKind::WatchDevicesFailed.with_errno()),
+            }
+        }
+    }
+}
+
+/// Returns true if `msg` is a kernel uevent adding, removing or renaming an
+/// RDMA device.
+///
+/// A uevent is a `action@devpath` header followed by NUL-separated
+/// `KEY=value` fields, including `ACTION` and `SUBSYSTEM`.
+fn is_device_uevent(msg: &[u8]) -> bool {
+    let (mut action, mut subsystem) = (None, None);
+    for field in msg.split(|&b| b == 0).skip(1) {
+        if let Some(value) = field.strip_prefix(b"ACTION=") {
+            action = Some(value);
+        } else if let Some(value) = field.strip_prefix(b"SUBSYSTEM=") {
+            subsystem = Some(value);
+        }
+    }
+    subsystem == Some(b"infiniband") && matches!(action, Some(b"add" | b"remove" | b"move"))
+}
+
+/// Returns the sorted names and GUIDs of `devices`.
+fn topology(devices: &Devices) -> Vec<(String, Guid)> {
+    devices
+        .sorted()
+        .iter()
+        .map(|device| (device.info().name.clone(), device.info().guid))
+        .collect()
+}
+
+impl Devices {
+    /// Blocks and calls `callback` with the refreshed devices each time the
+    /// set of devices matching `config` changes.
+    ///
+    /// The devices are re-opened with `config` on every kernel uevent that
+    /// adds, removes or renames an RDMA device; `callback` runs only if the
+    /// names or GUIDs differ from the previous enumeration, and receives an
+    /// empty list once the last matching device is gone. Return
+    /// [`ControlFlow::Break`] from it to stop watching.
+    ///
+    /// Only device hotplug is detected. Port state changes, such as a link
+    /// going down, raise no uevent: consume the device's async events or
+    /// re-open the devices periodically for those. Inside a network
+    /// namespace other than the initial one the kernel may not forward
+    /// RDMA uevents at all.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::WatchDevicesFailed`] if the uevent socket cannot
+    /// be opened or read, or any error from [`Devices::open`] other than
+    /// [`ErrorKind::IBDeviceNotFound`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use ruapc_rdma_sys::Devices;
+    /// use std::ops::ControlFlow;
+    ///
+    /// Devices::watch(&Default::default(), |devices| {
+    ///     println!("{} devices", devices.len());
+    ///     ControlFlow::Continue(())
+    /// })?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn watch<F>(config: &DeviceConfig, mut callback: F) -> Result<()>
+    where
+        F: FnMut(Devices) -> ControlFlow<()>,
+    {
+        let open = || match Self::open(config) {
+            Err(err) if err.kind == ErrorKind::IBDeviceNotFound => Ok(Devices(vec![])),
+            devices => devices,
+        };
+
+        // Subscribe first so a change during the initial enumeration is seen
+        let socket = UeventSocket::open()?;
+        let mut current = topology(&open()?);
+        let mut buf = vec![0u8; UEVENT_BUFFER_SIZE];
+        loop {
+            if !socket.next_is_device_event(&mut buf)? {
+                continue;
+            }
+            let devices = open()?;
+            let next = topology(&devices);
+            if next == current {
+                continue;
+            }
+            current = next;
+            if callback(devices).is_break() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_device_uevent() {
+        let uevent = |action: &str, subsystem: &str| {
+            format!(
+                "{action}@/devices/pci0000:00/0000:00:02.0/infiniband/mlx5_2\0\
+                 ACTION={action}\0DEVPATH=/devices/pci0000:00/0000:00:02.0/infiniband/mlx5_2\0\
+                 SUBSYSTEM={subsystem}\0NAME=mlx5_2\0SEQNUM=4711\0"
+            )
+        };
+        for action in ["add", "remove", "move"] {
+            assert!(is_device_uevent(uevent(action, "infiniband").as_bytes()));
+        }
+        assert!(!is_device_uevent(uevent("change", "infiniband").as_bytes()));
+        assert!(!is_device_uevent(uevent("add", "net").as_bytes()));
+        assert!(!is_device_uevent(
+            uevent("add", "infiniband_verbs").as_bytes()
+        ));
+        assert!(!is_device_uevent(b""));
+        // The header alone is not trusted
+        assert!(!is_device_uevent(b"add@/class/infiniband/mlx5_2"));
+    }
+
+    #[test]
+    fn test_uevent_socket_open() {
+        UeventSocket::open().unwrap();
+    }
+}
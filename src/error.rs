@@ -23,6 +23,10 @@ pub enum ErrorKind {
     IBQueryGidTypeFail,
     /// Failed to query port attributes.
     IBQueryPortFail,
+    /// Failed to query partition key (P_Key).
+    IBQueryPKeyFail,
+    /// Failed to read port counters from sysfs.
+    IBReadPortCountersFail,
     /// Failed to allocate Protection Domain.
     IBAllocPDFail,
     /// Failed to create completion channel.
@@ -43,14 +47,38 @@ pub enum ErrorKind {
     IBCreateQueuePairFail,
     /// Failed to modify queue pair state.
     IBModifyQueuePairFail,
+    /// Failed to query queue pair attributes.
+    IBQueryQueuePairFail,
     /// Failed to post receive work request.
     IBPostRecvFailed,
     /// Failed to post send work request.
     IBPostSendFailed,
+    /// A work completion reported a status other than success.
+    WorkCompletionFailed,
     /// Failed to set non-blocking mode.
     IBSetNonBlockFailed,
-    /// Buffer size insufficient for operation.
+    /// Failed to enter or restore a network namespace.
+    SetNetnsFailed,
+    /// A librdmacm connection manager call failed.
+    RdmaCmFailed,
+    /// Failed to open or read the device hotplug event socket.
+    WatchDevicesFailed,
+    /// Remote connection info cannot address the peer.
+    InvalidConnectionInfo,
+    /// No free slot in a work queue or send buffer pool right now.
     InsufficientBuffer,
+    /// An argument violates a size limit or device capability.
+    InvalidArgument,
+    /// Work request ID does not fit in the bits available beside the type tag.
+    WRIDOverflow,
+    /// Binding struct layout differs from the libibverbs ABI.
+    AbiMismatch,
+    /// The device, driver or process lacks a capability the call requires.
+    ///
+    /// Carries the name of the missing capability, e.g.
+    /// `"completion timestamps"`. Unlike [`Unknown`](Self::Unknown) it is
+    /// tagged on the wire, so peers can tell it apart from other errors.
+    Unsupported(String),
     /// Unknown or unclassified error with a custom message.
     #[serde(untagged)]
     Unknown(String),
@@ -61,12 +89,19 @@ pub enum ErrorKind {
 /// This is the primary error type used throughout the ruapc-rdma library.
 /// It combines an error kind for categorization with an optional message
 /// for additional context.
+///
+/// Serializes as `{"kind":...,"msg":...}`, plus `"errno"` when one was
+/// captured; JSON without `errno` deserializes with `errno: None`, so
+/// errors round-trip between versions with and without the field.
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct Error {
     /// The category of error that occurred.
     pub kind: ErrorKind,
     /// Additional error message providing context.
     pub msg: String,
+    /// OS error number behind the failure, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub errno: Option<i32>,
 }
 
 impl ErrorKind {
@@ -74,9 +109,170 @@ impl ErrorKind {
     ///
     /// # Returns
     ///
-    /// Returns an `Error` with this kind and the OS error message.
+    /// Returns an `Error` with this kind, the OS error message and its
+    /// `errno`.
     pub fn with_errno(self) -> Error {
-        Error::new(self, std::io::Error::last_os_error().to_string())
+        let err = std::io::Error::last_os_error();
+        Error {
+            errno: err.raw_os_error(),
+            ..Error::new(self, err.to_string())
+        }
+    }
+
+    /// Returns true if retrying the failed operation later may succeed.
+    ///
+    /// Transient kinds report a momentarily empty or full queue, or memory
+    /// pressure:
+    /// - [`AllocMemoryFailed`](Self::AllocMemoryFailed)
+    /// - [`IBGetCompQueueEventFail`](Self::IBGetCompQueueEventFail), e.g. `EAGAIN`
+    ///   on a non-blocking channel
+    /// - [`IBReqNotifyCompQueueFail`](Self::IBReqNotifyCompQueueFail)
+    /// - [`IBPollCompQueueFail`](Self::IBPollCompQueueFail)
+    /// - [`InsufficientBuffer`](Self::InsufficientBuffer), e.g. a full send queue
+    ///
+    /// The remaining kinds are either [fatal](Self::is_fatal) or point at a
+    /// configuration or programming error that a plain retry won't fix, such
+    /// as [`InvalidArgument`](Self::InvalidArgument) for a request that
+    /// exceeds a buffer or device limit.
+    /// Check [`Error::errno`] for a finer decision.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::AllocMemoryFailed
+            | Self::IBGetCompQueueEventFail
+            | Self::IBReqNotifyCompQueueFail
+            | Self::IBPollCompQueueFail
+            | Self::InsufficientBuffer => true,
+            Self::IBGetDeviceListFail
+            | Self::IBDeviceNotFound
+            | Self::IBOpenDeviceFail
+            | Self::IBQueryDeviceFail
+            | Self::IBQueryGidFail
+            | Self::IBQueryGidTypeFail
+            | Self::IBQueryPortFail
+            | Self::IBQueryPKeyFail
+            | Self::IBReadPortCountersFail
+            | Self::IBAllocPDFail
+            | Self::IBCreateCompChannelFail
+            | Self::IBSetCompChannelNonBlockFail
+            | Self::IBCreateCompQueueFail
+            | Self::IBRegMemoryRegionFail
+            | Self::IBCreateQueuePairFail
+            | Self::IBModifyQueuePairFail
+            | Self::IBQueryQueuePairFail
+            | Self::IBPostRecvFailed
+            | Self::IBPostSendFailed
+            | Self::WorkCompletionFailed
+            | Self::IBSetNonBlockFailed
+            | Self::SetNetnsFailed
+            | Self::RdmaCmFailed
+            | Self::WatchDevicesFailed
+            | Self::InvalidConnectionInfo
+            | Self::InvalidArgument
+            | Self::WRIDOverflow
+            | Self::AbiMismatch
+            | Self::Unsupported(_)
+            | Self::Unknown(_) => false,
+        }
+    }
+
+    /// Returns true if the device or connection is unusable after this error.
+    ///
+    /// Fatal kinds mean no RDMA device can be used, or a work request
+    /// failed and moved its QP to the error state:
+    /// - [`IBGetDeviceListFail`](Self::IBGetDeviceListFail)
+    /// - [`IBDeviceNotFound`](Self::IBDeviceNotFound)
+    /// - [`IBOpenDeviceFail`](Self::IBOpenDeviceFail)
+    /// - [`IBAllocPDFail`](Self::IBAllocPDFail)
+    /// - [`WorkCompletionFailed`](Self::WorkCompletionFailed)
+    /// - [`AbiMismatch`](Self::AbiMismatch)
+    ///
+    /// Recover by reopening the device or rebuilding the connection rather
+    /// than retrying. No kind is both fatal and
+    /// [transient](Self::is_transient).
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            Self::IBGetDeviceListFail
+            | Self::IBDeviceNotFound
+            | Self::IBOpenDeviceFail
+            | Self::IBAllocPDFail
+            | Self::WorkCompletionFailed
+            | Self::AbiMismatch => true,
+            Self::AllocMemoryFailed
+            | Self::IBQueryDeviceFail
+            | Self::IBQueryGidFail
+            | Self::IBQueryGidTypeFail
+            | Self::IBQueryPortFail
+            | Self::IBQueryPKeyFail
+            | Self::IBReadPortCountersFail
+            | Self::IBCreateCompChannelFail
+            | Self::IBSetCompChannelNonBlockFail
+            | Self::IBGetCompQueueEventFail
+            | Self::IBCreateCompQueueFail
+            | Self::IBReqNotifyCompQueueFail
+            | Self::IBPollCompQueueFail
+            | Self::IBRegMemoryRegionFail
+            | Self::IBCreateQueuePairFail
+            | Self::IBModifyQueuePairFail
+            | Self::IBQueryQueuePairFail
+            | Self::IBPostRecvFailed
+            | Self::IBPostSendFailed
+            | Self::IBSetNonBlockFailed
+            | Self::SetNetnsFailed
+            | Self::RdmaCmFailed
+            | Self::WatchDevicesFailed
+            | Self::InvalidConnectionInfo
+            | Self::InsufficientBuffer
+            | Self::InvalidArgument
+            | Self::WRIDOverflow
+            | Self::Unsupported(_)
+            | Self::Unknown(_) => false,
+        }
+    }
+
+    /// Returns true if the call needs a capability this device, driver or
+    /// process lacks.
+    ///
+    /// Such errors are neither transient nor fatal: fall back to a code
+    /// path that does not need the capability instead of retrying.
+    pub fn is_unsupported(&self) -> bool {
+        matches!(self, Self::Unsupported(_))
+    }
+
+    /// Classifies an errno returned by `ibv_post_send` or `ibv_post_recv`.
+    ///
+    /// The post verbs return the errno directly instead of setting `errno`.
+    /// `ENOMEM` means the work queue is full and maps to
+    /// [`ErrorKind::InsufficientBuffer`]; `EINVAL` and `EFAULT` indicate a
+    /// malformed work request. `EOPNOTSUPP` maps to
+    /// [`ErrorKind::Unsupported`] for an opcode the provider rejects; the
+    /// post paths report a provider without the op itself as
+    /// `Unsupported("post_send")` or `Unsupported("post_recv")` instead.
+    /// Other values map to [`ErrorKind::Unknown`] with a descriptive message.
+    pub fn from_post_errno(errno: i32) -> Self {
+        match errno {
+            libc::ENOMEM => Self::InsufficientBuffer,
+            libc::EINVAL => Self::Unknown("invalid work request (EINVAL)".to_string()),
+            libc::EFAULT => Self::Unknown("invalid work request address (EFAULT)".to_string()),
+            libc::EOPNOTSUPP => Self::Unsupported("work request opcode".to_string()),
+            _ => Self::Unknown(std::io::Error::from_raw_os_error(errno).to_string()),
+        }
+    }
+
+    /// Classifies an errno returned by `ibv_modify_qp`.
+    ///
+    /// `EINVAL` usually means an invalid state transition or attribute mask,
+    /// `ENOMEM` maps to [`ErrorKind::AllocMemoryFailed`], `EOPNOTSUPP` to
+    /// [`ErrorKind::Unsupported`], and any other value falls back to
+    /// [`ErrorKind::IBModifyQueuePairFail`].
+    pub fn from_modify_qp_errno(errno: i32) -> Self {
+        match errno {
+            libc::ENOMEM => Self::AllocMemoryFailed,
+            libc::EOPNOTSUPP => Self::Unsupported("QP attribute".to_string()),
+            libc::EINVAL => {
+                Self::Unknown("invalid QP attribute or state transition (EINVAL)".to_string())
+            }
+            _ => Self::IBModifyQueuePairFail,
+        }
     }
 }
 
@@ -92,7 +288,24 @@ impl Error {
     ///
     /// Returns a new `Error` instance.
     pub fn new(kind: ErrorKind, msg: String) -> Self {
-        Self { kind, msg }
+        Self {
+            kind,
+            msg,
+            errno: None,
+        }
+    }
+
+    /// Creates an error from an errno returned by a verb.
+    ///
+    /// Many verbs return the errno instead of setting `errno`, so
+    /// [`ErrorKind::with_errno`] would report a stale value for them. The
+    /// message is the OS description of `errno`.
+    pub fn from_errno(kind: ErrorKind, errno: i32) -> Self {
+        Self {
+            kind,
+            msg: std::io::Error::from_raw_os_error(errno).to_string(),
+            errno: Some(errno),
+        }
     }
 }
 
@@ -101,6 +314,7 @@ impl From<ErrorKind> for Error {
         Self {
             kind,
             msg: String::new(),
+            errno: None,
         }
     }
 }
@@ -156,7 +370,8 @@ mod tests {
             err,
             Error {
                 kind: ErrorKind::Unknown("NewKindError".to_string()),
-                msg: "new kind error message".to_string()
+                msg: "new kind error message".to_string(),
+                errno: None,
             }
         );
 
@@ -164,6 +379,111 @@ mod tests {
         assert_eq!(err.to_string(), "IBGetDeviceListFail");
     }
 
+    #[test]
+    fn test_error_kind_classification() {
+        use ErrorKind::*;
+        // (kind, transient, fatal); a new variant must be added here
+        let cases = [
+            (AllocMemoryFailed, true, false),
+            (IBGetDeviceListFail, false, true),
+            (IBDeviceNotFound, false, true),
+            (IBOpenDeviceFail, false, true),
+            (IBQueryDeviceFail, false, false),
+            (IBQueryGidFail, false, false),
+            (IBQueryGidTypeFail, false, false),
+            (IBQueryPortFail, false, false),
+            (IBQueryPKeyFail, false, false),
+            (IBReadPortCountersFail, false, false),
+            (IBAllocPDFail, false, true),
+            (IBCreateCompChannelFail, false, false),
+            (IBSetCompChannelNonBlockFail, false, false),
+            (IBGetCompQueueEventFail, true, false),
+            (IBCreateCompQueueFail, false, false),
+            (IBReqNotifyCompQueueFail, true, false),
+            (IBPollCompQueueFail, true, false),
+            (IBRegMemoryRegionFail, false, false),
+            (IBCreateQueuePairFail, false, false),
+            (IBModifyQueuePairFail, false, false),
+            (IBQueryQueuePairFail, false, false),
+            (IBPostRecvFailed, false, false),
+            (IBPostSendFailed, false, false),
+            (WorkCompletionFailed, false, true),
+            (IBSetNonBlockFailed, false, false),
+            (SetNetnsFailed, false, false),
+            (RdmaCmFailed, false, false),
+            (WatchDevicesFailed, false, false),
+            (InvalidConnectionInfo, false, false),
+            (InsufficientBuffer, true, false),
+            (InvalidArgument, false, false),
+            (WRIDOverflow, false, false),
+            (AbiMismatch, false, true),
+            (Unsupported("feature".to_string()), false, false),
+            (Unknown("custom".to_string()), false, false),
+        ];
+        for (kind, transient, fatal) in &cases {
+            assert_eq!(kind.is_transient(), *transient, "{kind:?}");
+            assert_eq!(kind.is_fatal(), *fatal, "{kind:?}");
+        }
+
+        // Every variant is listed above: the schema has one entry per
+        // variant, the untagged Unknown included
+        let schema = serde_json::to_value(schemars::schema_for!(ErrorKind)).unwrap();
+        let variants = schema["anyOf"].as_array().map_or(0, Vec::len);
+        assert_eq!(variants, cases.len(), "{schema}");
+    }
+
+    #[test]
+    fn test_error_errno_serde() {
+        let err = Error {
+            errno: Some(libc::EINVAL),
+            ..Error::new(
+                ErrorKind::IBModifyQueuePairFail,
+                "Invalid argument".to_string(),
+            )
+        };
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "kind": "IBModifyQueuePairFail",
+                "msg": "Invalid argument",
+                "errno": 22
+            })
+        );
+        assert_eq!(serde_json::from_value::<Error>(json).unwrap(), err);
+
+        // Legacy two-field JSON from peers without the errno field
+        let json = serde_json::json!({
+            "kind": "IBAllocPDFail",
+            "msg": "Cannot allocate memory",
+        });
+        let err = serde_json::from_value::<Error>(json.clone()).unwrap();
+        assert_eq!(err.kind, ErrorKind::IBAllocPDFail);
+        assert_eq!(err.errno, None);
+        assert_eq!(serde_json::to_value(&err).unwrap(), json);
+    }
+
+    #[test]
+    fn test_with_errno() {
+        // Opening a missing path sets errno to ENOENT
+        let path = c"/nonexistent/ruapc-rdma-sys";
+        assert_eq!(unsafe { libc::open(path.as_ptr(), libc::O_RDONLY) }, -1);
+        let err = ErrorKind::IBOpenDeviceFail.with_errno();
+        assert_eq!(err.errno, Some(libc::ENOENT));
+        assert!(!err.msg.is_empty());
+    }
+
+    #[test]
+    fn test_from_errno() {
+        let err = Error::from_errno(ErrorKind::IBQueryQueuePairFail, libc::EINVAL);
+        assert_eq!(err.kind, ErrorKind::IBQueryQueuePairFail);
+        assert_eq!(err.errno, Some(libc::EINVAL));
+        assert_eq!(
+            err.msg,
+            std::io::Error::from_raw_os_error(libc::EINVAL).to_string()
+        );
+    }
+
     #[test]
     fn test_error_display() {
         let err = Error::new(ErrorKind::IBOpenDeviceFail, "Device not found".to_string());
@@ -179,4 +499,62 @@ mod tests {
         assert_eq!(err.kind, ErrorKind::IBQueryDeviceFail);
         assert!(err.msg.is_empty());
     }
+
+    #[test]
+    fn test_error_kind_from_errno() {
+        assert_eq!(
+            ErrorKind::from_post_errno(libc::ENOMEM),
+            ErrorKind::InsufficientBuffer
+        );
+        assert!(matches!(
+            ErrorKind::from_post_errno(libc::EINVAL),
+            ErrorKind::Unknown(msg) if msg.contains("EINVAL")
+        ));
+        assert!(matches!(
+            ErrorKind::from_post_errno(libc::EIO),
+            ErrorKind::Unknown(_)
+        ));
+
+        assert_eq!(
+            ErrorKind::from_modify_qp_errno(libc::ENOMEM),
+            ErrorKind::AllocMemoryFailed
+        );
+        assert!(matches!(
+            ErrorKind::from_modify_qp_errno(libc::EINVAL),
+            ErrorKind::Unknown(_)
+        ));
+        assert_eq!(
+            ErrorKind::from_modify_qp_errno(libc::EPERM),
+            ErrorKind::IBModifyQueuePairFail
+        );
+
+        assert!(ErrorKind::from_post_errno(libc::EOPNOTSUPP).is_unsupported());
+        assert!(ErrorKind::from_modify_qp_errno(libc::EOPNOTSUPP).is_unsupported());
+    }
+
+    #[test]
+    fn test_unsupported_serde() {
+        // Unsupported is tagged, so it survives a round trip as itself
+        let err = Error::new(
+            ErrorKind::Unsupported("completion timestamps".to_string()),
+            "device does not report a core clock".to_string(),
+        );
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(
+            json["kind"],
+            serde_json::json!({ "Unsupported": "completion timestamps" })
+        );
+        assert_eq!(serde_json::from_value::<Error>(json).unwrap(), err);
+
+        // A bare string still falls through to the untagged Unknown, even
+        // when it spells the Unsupported tag
+        for name in ["Unsupported", "NewKindError"] {
+            let kind = serde_json::from_value::<ErrorKind>(serde_json::json!(name)).unwrap();
+            assert_eq!(kind, ErrorKind::Unknown(name.to_string()));
+            assert_eq!(
+                serde_json::to_value(&kind).unwrap(),
+                serde_json::json!(name)
+            );
+        }
+    }
 }
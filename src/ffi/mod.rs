@@ -2,46 +2,350 @@
 //!
 //! These wrappers provide inline optimizations over raw libibverbs
 //! function pointers accessed through ops vtable.
+//!
+//! libibverbs defines `ibv_poll_cq`, `ibv_post_send` and friends as
+//! `static inline`, so the library exports no symbol to fall back to. When a
+//! provider leaves one of these ops null, the wrappers fail with
+//! `EOPNOTSUPP` instead of calling through a null pointer;
+//! [`OpsAvailability`] (via `Device::ops_available`) reports this up front.
 
-use crate::{ibv_cq, ibv_qp, ibv_recv_wr, ibv_send_wr, ibv_wc};
-use std::os::raw::c_int;
+use crate::{
+    ErrorKind, ibv_context, ibv_cq, ibv_cq_ex, ibv_cq_init_attr_ex, ibv_device_attr_ex,
+    ibv_poll_cq_attr, ibv_qp, ibv_query_device_ex_input, ibv_recv_wr, ibv_send_wr, ibv_wc,
+    ibv_wc_opcode, verbs_context,
+};
+use std::mem::offset_of;
+use std::os::raw::{c_int, c_void};
 
 /// Requests notification for completion queue events
 ///
 /// This is an inline wrapper that calls through the context's ops vtable
-/// for better performance than calling the global function.
+/// for better performance than the out-of-line call into libibverbs.
+/// Returns `EOPNOTSUPP` if the provider leaves the op null.
 #[inline(always)]
 pub unsafe fn ibv_req_notify_cq(cq: *mut ibv_cq, solicited_only: c_int) -> c_int {
-    unsafe { (*(*cq).context).ops.req_notify_cq.unwrap_unchecked()(cq, solicited_only) }
+    unsafe {
+        match (*(*cq).context).ops.req_notify_cq {
+            Some(req_notify_cq) => req_notify_cq(cq, solicited_only),
+            None => libc::EOPNOTSUPP,
+        }
+    }
 }
 
 /// Polls completion queue for work completions
 ///
-/// Returns the number of completions polled (negative on error)
+/// Returns the number of completions polled (negative on error), or
+/// `-EOPNOTSUPP` if the provider leaves the op null.
 #[inline(always)]
 pub unsafe fn ibv_poll_cq(cq: *mut ibv_cq, num_entries: c_int, wc: *mut ibv_wc) -> c_int {
-    unsafe { (*(*cq).context).ops.poll_cq.unwrap_unchecked()(cq, num_entries, wc) }
+    unsafe {
+        match (*(*cq).context).ops.poll_cq {
+            Some(poll_cq) => poll_cq(cq, num_entries, wc),
+            None => -libc::EOPNOTSUPP,
+        }
+    }
 }
 
 /// Posts a send work request to a queue pair
+///
+/// Returns 0 on success or an errno. If the provider leaves the op null,
+/// returns `EOPNOTSUPP` with `bad_wr` pointing at `wr`.
 #[inline(always)]
 pub unsafe fn ibv_post_send(
     qp: *mut ibv_qp,
     wr: *mut ibv_send_wr,
     bad_wr: *mut *mut ibv_send_wr,
 ) -> c_int {
-    unsafe { (*(*qp).context).ops.post_send.unwrap_unchecked()(qp, wr, bad_wr) }
+    unsafe {
+        match (*(*qp).context).ops.post_send {
+            Some(post_send) => post_send(qp, wr, bad_wr),
+            None => {
+                *bad_wr = wr;
+                libc::EOPNOTSUPP
+            }
+        }
+    }
 }
 
 /// Posts receive work request to queue pair
 ///
 /// Returns 0 on success, negative on error, and sets bad_wr
-/// to point to the first invalid request if the batch fails.
+/// to point to the first invalid request if the batch fails. If the
+/// provider leaves the op null, returns `EOPNOTSUPP` with `bad_wr` pointing
+/// at `wr`.
 #[inline(always)]
 pub unsafe fn ibv_post_recv(
     qp: *mut ibv_qp,
     wr: *mut ibv_recv_wr,
     bad_wr: *mut *mut ibv_recv_wr,
 ) -> c_int {
-    unsafe { (*(*qp).context).ops.post_recv.unwrap_unchecked()(qp, wr, bad_wr) }
+    unsafe {
+        match (*(*qp).context).ops.post_recv {
+            Some(post_recv) => post_recv(qp, wr, bad_wr),
+            None => {
+                *bad_wr = wr;
+                libc::EOPNOTSUPP
+            }
+        }
+    }
+}
+
+/// Classifies an errno returned by [`ibv_post_send`] on `qp`.
+///
+/// `EOPNOTSUPP` from a provider without `post_send` becomes
+/// `Unsupported("post_send")`; anything else, including a provider
+/// rejecting the opcode, goes through [`ErrorKind::from_post_errno`].
+///
+/// # Safety
+///
+/// `qp` must be a valid queue pair.
+pub(crate) unsafe fn post_send_error_kind(qp: *mut ibv_qp, errno: c_int) -> ErrorKind {
+    if errno == libc::EOPNOTSUPP && unsafe { (*(*qp).context).ops.post_send.is_none() } {
+        return ErrorKind::Unsupported("post_send".to_string());
+    }
+    ErrorKind::from_post_errno(errno)
+}
+
+/// Classifies an errno returned by [`ibv_post_recv`] on `qp`.
+///
+/// Like [`post_send_error_kind`], for a provider without `post_recv`.
+///
+/// # Safety
+///
+/// `qp` must be a valid queue pair.
+pub(crate) unsafe fn post_recv_error_kind(qp: *mut ibv_qp, errno: c_int) -> ErrorKind {
+    if errno == libc::EOPNOTSUPP && unsafe { (*(*qp).context).ops.post_recv.is_none() } {
+        return ErrorKind::Unsupported("post_recv".to_string());
+    }
+    ErrorKind::from_post_errno(errno)
+}
+
+/// Returns the extended verbs context of `ctx`, or null for legacy providers
+///
+/// Mirrors `verbs_get_ctx`: the `ibv_context` is embedded at the end of a
+/// `verbs_context` when `abi_compat` holds the extended ABI marker.
+#[inline(always)]
+pub unsafe fn verbs_get_ctx(ctx: *mut ibv_context) -> *mut verbs_context {
+    unsafe {
+        if (*ctx).abi_compat != usize::MAX as *mut c_void {
+            return std::ptr::null_mut();
+        }
+        ctx.byte_sub(offset_of!(verbs_context, context)).cast()
+    }
+}
+
+/// Looks up an extended context op, mirroring `verbs_get_ctx_op`
+///
+/// Evaluates to `None` if the provider is not extended, its context is too
+/// old to contain the op, or the op is not implemented.
+macro_rules! verbs_get_ctx_op {
+    ($ctx:expr, $op:ident) => {{
+        let vctx = verbs_get_ctx($ctx);
+        if vctx.is_null()
+            || (*vctx).sz < size_of::<verbs_context>() - offset_of!(verbs_context, $op)
+        {
+            None
+        } else {
+            (*vctx).$op
+        }
+    }};
+}
+
+/// Which provider ops of a context are implemented.
+///
+/// Providers may leave optional entries of the `ibv_context_ops` vtable and
+/// the extended `verbs_context` null. The wrappers in this module then fail
+/// with `EOPNOTSUPP`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpsAvailability {
+    /// `ops.poll_cq`, used by [`ibv_poll_cq`].
+    pub poll_cq: bool,
+    /// `ops.req_notify_cq`, used by [`ibv_req_notify_cq`].
+    pub req_notify_cq: bool,
+    /// `ops.post_send`, used by [`ibv_post_send`].
+    pub post_send: bool,
+    /// `ops.post_recv`, used by [`ibv_post_recv`].
+    pub post_recv: bool,
+    /// Extended `create_cq_ex`, used by [`ibv_create_cq_ex`].
+    pub create_cq_ex: bool,
+    /// Extended `query_device_ex`, used by [`ibv_query_device_ex`].
+    pub query_device_ex: bool,
+}
+
+impl OpsAvailability {
+    /// Reads the op pointers of `context`.
+    ///
+    /// # Safety
+    ///
+    /// `context` must be a valid, open device context.
+    pub unsafe fn from_context(context: *mut ibv_context) -> Self {
+        unsafe {
+            let ops = &(*context).ops;
+            Self {
+                poll_cq: ops.poll_cq.is_some(),
+                req_notify_cq: ops.req_notify_cq.is_some(),
+                post_send: ops.post_send.is_some(),
+                post_recv: ops.post_recv.is_some(),
+                create_cq_ex: verbs_get_ctx_op!(context, create_cq_ex).is_some(),
+                query_device_ex: verbs_get_ctx_op!(context, query_device_ex).is_some(),
+            }
+        }
+    }
+
+    /// Returns true if every op behind the data-path wrappers
+    /// ([`ibv_poll_cq`], [`ibv_req_notify_cq`], [`ibv_post_send`] and
+    /// [`ibv_post_recv`]) is present.
+    pub fn data_path(&self) -> bool {
+        self.poll_cq && self.req_notify_cq && self.post_send && self.post_recv
+    }
+}
+
+/// Creates an extended completion queue
+///
+/// Returns null and sets `errno` to `EOPNOTSUPP` if the provider does not
+/// implement `create_cq_ex`.
+#[inline(always)]
+pub unsafe fn ibv_create_cq_ex(
+    context: *mut ibv_context,
+    cq_attr: *mut ibv_cq_init_attr_ex,
+) -> *mut ibv_cq_ex {
+    unsafe {
+        match verbs_get_ctx_op!(context, create_cq_ex) {
+            Some(create_cq_ex) => create_cq_ex(context, cq_attr),
+            None => {
+                *libc::__errno_location() = libc::EOPNOTSUPP;
+                std::ptr::null_mut()
+            }
+        }
+    }
+}
+
+/// Queries extended device attributes
+///
+/// Falls back to `ibv_query_device` with the extended fields zeroed if the
+/// provider does not implement `query_device_ex`.
+#[inline(always)]
+pub unsafe fn ibv_query_device_ex(
+    context: *mut ibv_context,
+    input: *const ibv_query_device_ex_input,
+    attr: *mut ibv_device_attr_ex,
+) -> c_int {
+    unsafe {
+        if let Some(query_device_ex) = verbs_get_ctx_op!(context, query_device_ex) {
+            let ret = query_device_ex(context, input, attr, size_of::<ibv_device_attr_ex>());
+            if ret != libc::EOPNOTSUPP && ret != libc::ENOSYS {
+                return ret;
+            }
+        }
+        std::ptr::write_bytes(attr, 0, 1);
+        crate::ibv_query_device(context, &mut (*attr).orig_attr)
+    }
+}
+
+/// Converts an extended completion queue to a plain one
+#[inline(always)]
+pub fn ibv_cq_ex_to_cq(cq: *mut ibv_cq_ex) -> *mut ibv_cq {
+    cq.cast()
+}
+
+/// Starts polling an extended completion queue
+///
+/// Returns 0 with the first completion loaded, `ENOENT` if the queue is
+/// empty, or another errno on failure.
+#[inline(always)]
+pub unsafe fn ibv_start_poll(cq: *mut ibv_cq_ex, attr: *mut ibv_poll_cq_attr) -> c_int {
+    unsafe { (*cq).start_poll.unwrap_unchecked()(cq, attr) }
+}
+
+/// Advances to the next completion, `ENOENT` if there is none
+#[inline(always)]
+pub unsafe fn ibv_next_poll(cq: *mut ibv_cq_ex) -> c_int {
+    unsafe { (*cq).next_poll.unwrap_unchecked()(cq) }
+}
+
+/// Ends a polling session started with [`ibv_start_poll`]
+#[inline(always)]
+pub unsafe fn ibv_end_poll(cq: *mut ibv_cq_ex) {
+    unsafe { (*cq).end_poll.unwrap_unchecked()(cq) }
+}
+
+/// Reads the opcode of the current completion
+#[inline(always)]
+pub unsafe fn ibv_wc_read_opcode(cq: *mut ibv_cq_ex) -> ibv_wc_opcode {
+    unsafe { (*cq).read_opcode.unwrap_unchecked()(cq) }
+}
+
+/// Reads the byte length of the current completion
+#[inline(always)]
+pub unsafe fn ibv_wc_read_byte_len(cq: *mut ibv_cq_ex) -> u32 {
+    unsafe { (*cq).read_byte_len.unwrap_unchecked()(cq) }
+}
+
+/// Reads the hardware timestamp of the current completion
+///
+/// Only valid if the CQ was created with `IBV_WC_EX_WITH_COMPLETION_TIMESTAMP`.
+#[inline(always)]
+pub unsafe fn ibv_wc_read_completion_ts(cq: *mut ibv_cq_ex) -> u64 {
+    unsafe { (*cq).read_completion_ts.unwrap_unchecked()(cq) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::MaybeUninit;
+
+    #[test]
+    fn test_null_ops_fallback() {
+        // A legacy context with an empty ops table
+        let mut context = MaybeUninit::<ibv_context>::zeroed();
+        let context = context.as_mut_ptr();
+
+        let mut cq = MaybeUninit::<ibv_cq>::zeroed();
+        let cq = cq.as_mut_ptr();
+        // ibv_qp holds enums without a zero variant, so only set the context
+        let mut qp = MaybeUninit::<ibv_qp>::uninit();
+        let qp = qp.as_mut_ptr();
+        unsafe {
+            std::ptr::addr_of_mut!((*cq).context).write(context);
+            std::ptr::addr_of_mut!((*qp).context).write(context);
+
+            let ops = OpsAvailability::from_context(context);
+            assert_eq!(ops, OpsAvailability::default());
+            assert!(!ops.data_path());
+
+            let mut wc = ibv_wc::default();
+            assert_eq!(ibv_poll_cq(cq, 1, &mut wc), -libc::EOPNOTSUPP);
+            assert_eq!(ibv_req_notify_cq(cq, 0), libc::EOPNOTSUPP);
+
+            let mut send_wr = ibv_send_wr::default();
+            let mut bad_send_wr = std::ptr::null_mut();
+            assert_eq!(
+                ibv_post_send(qp, &mut send_wr, &mut bad_send_wr),
+                libc::EOPNOTSUPP
+            );
+            assert_eq!(bad_send_wr, &mut send_wr as *mut _);
+
+            let mut recv_wr = ibv_recv_wr::default();
+            let mut bad_recv_wr = std::ptr::null_mut();
+            assert_eq!(
+                ibv_post_recv(qp, &mut recv_wr, &mut bad_recv_wr),
+                libc::EOPNOTSUPP
+            );
+            assert_eq!(bad_recv_wr, &mut recv_wr as *mut _);
+
+            // A missing op is told apart from a rejected work request
+            assert_eq!(
+                post_send_error_kind(qp, libc::EOPNOTSUPP),
+                ErrorKind::Unsupported("post_send".to_string())
+            );
+            assert_eq!(
+                post_recv_error_kind(qp, libc::EOPNOTSUPP),
+                ErrorKind::Unsupported("post_recv".to_string())
+            );
+            assert_eq!(
+                post_send_error_kind(qp, libc::ENOMEM),
+                ErrorKind::InsufficientBuffer
+            );
+        }
+    }
 }
@@ -40,8 +40,15 @@
 //!
 //! ### Device Management
 //! - [`Devices`]: Collection of RDMA devices with filtering support
+//!   (`Devices::open_in_netns` enumerates another network namespace with the `netns` feature,
+//!   `Devices::watch` re-enumerates on device hotplug with the `watch` feature)
 //! - [`Device`]: Opened RDMA device with allocated protection domain
+//! - [`ProtectionDomainRef`]: Shared handle to a device's protection domain
 //! - [`DeviceInfo`]: Device metadata including name, GUID, ports, and capabilities
+//! - [`DeviceLimits`]: Resource limits (QPs, WRs, CQEs, MRs, PDs, SGEs) for sizing pools
+//! - [`DevicesReport`]: Timestamped device list emitted by the CLI
+//! - [`DeviceChange`]: Port or GID change between two snapshots, see [`DeviceInfo::diff`]
+//! - [`Endpoint`]: One usable (device, port, GID) combination, see [`Devices::endpoints`]
 //! - [`Port`]: Port information with GID list
 //! - [`Gid`]: Global Identifier entry with type (IB/RoCE)
 //!
@@ -53,14 +60,62 @@
 //! - [`Guid`]: 64-bit device identifier with colon-separated formatting
 //! - [`FwVer`]: Firmware version wrapper
 //! - [`LinkLayer`]: Link layer type (InfiniBand/Ethernet)
+//! - [`TransportType`]: Device transport type (InfiniBand/iWARP/usNIC)
 //! - [`WRID`]: Work completion ID with type encoding
 //! - [`WCType`]: Work completion operation type (Recv/SendData/SendImm)
+//! - [`imm_to_wire`], [`imm_from_wire`]: Immediate data byte-order conversion
+//!
+//! ### Memory Registration
+//! - [`RegisteredBuffer`]: Owned buffer registered as a memory region
+//! - [`MemoryRegion`]: Memory region with automatic deregistration, also for raw
+//!   pointers via [`Device::register_raw`]
+//! - [`MemoryRegistry`]: Per-device lookup of lkey/rkey by address
+//!
+//! ### Completion Queues
+//! - [`CompletionQueue`]: Completion queue polled into `ibv_wc` slices, or
+//!   waited on through a [`CompletionChannel`]
+//! - [`CompletionChannel`]: Completion event channel, pollable through its `AsRawFd` fd
+//!   (awaitable with the `tokio` feature)
+//! - [`CompletionQueueEx`]: Extended completion queue with hardware timestamps
+//!
+//! ### Queue Pair Setup
+//! - [`QueuePair`]: Queue pair with its granted inline data capacity and attribute queries
+//! - [`QpState`]: Named queue pair state, e.g. to assert a connection reached RTS
+//! - [`QpType`]: Queue pair transport type (RC/UC/UD/raw packet)
+//! - [`QpInitAttrBuilder`]: `ibv_qp_init_attr` builder validated against device limits
+//! - [`RtrParams`]: INIT→RTR parameters (path MTU, PSN, destination, SL or traffic class)
+//! - [`RtsParams`]: RTR→RTS parameters (timeouts, retries)
+//! - [`random_psn`]: Fresh 24-bit starting PSN for each connection
+//! - [`modify_to_init`], [`modify_to_rtr`], [`modify_to_rts`]: State transitions
+//!
+//! ### Connections
+//! - [`RcConnection`]: Message-oriented RC connection owning its QP, CQs and buffers
+//! - [`ConnectionInfo`]: Endpoint details exchanged out of band to connect
+//! - [`Event`]: Completion event returned by [`RcConnection::poll`]
+//!
+//! ### Connection Manager (`rdmacm` feature)
+//! - `CmEventChannel`: librdmacm event channel, with events acknowledged on drop
+//! - `CmId`: Address and route resolution, listen, connect and accept
+//!
+//! ### Work Requests
+//! - [`SendWr`]: Send work request builder with inline-data validation
+//! - [`post_send_typed`]: Post a send from a typed [`SendOpcode`]
+//!
+//! ### Debugging
+//! - [`set_cleanup_error_hook`]: Reports verbs destroy calls that fail during drop
+//! - `verify_abi`: Runtime check of binding struct layouts (requires the `debug` feature)
+//! - `selftest_loopback`: RDMA write between two local ports, verifying the whole
+//!   stack on real hardware (requires the `selftest` feature)
 //!
 //! ### FFI Wrapper Functions
 //! - [`ibv_poll_cq`]: Poll completion queue for work completions
 //! - [`ibv_post_send`]: Post send work request to a queue pair
 //! - [`ibv_post_recv`]: Post receive work request to a queue pair
 //! - [`ibv_req_notify_cq`]: Request completion queue event notifications
+//! - [`ibv_create_cq_ex`], [`ibv_query_device_ex`]: Extended verbs via `verbs_context`
+//! - [`OpsAvailability`]: Which provider ops are implemented, from [`Device::ops_available`]
+//! - [`ibv_start_poll`], [`ibv_next_poll`], [`ibv_end_poll`]: Extended CQ polling
+//! - [`ibv_wc_read_completion_ts`]: Read completion timestamps from an extended CQ
 //!
 //! ## Generated Bindings
 //!
@@ -87,14 +142,60 @@ include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 mod error;
 pub use error::{Error, ErrorKind, Result};
 
+mod cleanup;
+pub(crate) use cleanup::check_cleanup;
+pub use cleanup::{CleanupErrorHook, set_cleanup_error_hook};
+
 mod config;
-pub use config::{DeviceConfig, GidType};
+pub use config::{DEFAULT_CQ_SIZE, DeviceConfig, GidType};
 
 mod devices;
-pub use devices::{Device, DeviceInfo, Devices, Gid, Port};
+pub use devices::{
+    Device, DeviceChange, DeviceInfo, DeviceLimits, Devices, DevicesReport, Endpoint, Gid, Port,
+    ProtectionDomainRef,
+};
+
+mod memory;
+pub use memory::{MemoryRegion, MemoryRegistry, RegisteredBuffer};
+
+#[cfg(feature = "debug")]
+mod abi;
+#[cfg(feature = "debug")]
+pub use abi::verify_abi;
 
 mod ffi;
-pub use ffi::{ibv_poll_cq, ibv_post_recv, ibv_post_send, ibv_req_notify_cq};
+pub use ffi::{
+    OpsAvailability, ibv_cq_ex_to_cq, ibv_create_cq_ex, ibv_end_poll, ibv_next_poll, ibv_poll_cq,
+    ibv_post_recv, ibv_post_send, ibv_query_device_ex, ibv_req_notify_cq, ibv_start_poll,
+    ibv_wc_read_byte_len, ibv_wc_read_completion_ts, ibv_wc_read_opcode, verbs_get_ctx,
+};
+
+mod cq;
+pub use cq::{CompletionChannel, CompletionQueue, CompletionQueueEx, CqExPoll};
+
+#[cfg(feature = "rdmacm")]
+mod cm;
+#[cfg(feature = "rdmacm")]
+pub use cm::{CmEvent, CmEventChannel, CmId};
+
+mod connection;
+pub use connection::{ConnectionInfo, Event, RcConnection, RcConnectionConfig};
+
+mod qp;
+pub use qp::{
+    QpInitAttrBuilder, QpState, QpType, QueuePair, RtrParams, RtsParams, modify_to_init,
+    modify_to_rtr, modify_to_rts, random_psn,
+};
+
+#[cfg(feature = "selftest")]
+mod selftest;
+#[cfg(feature = "selftest")]
+pub use selftest::selftest_loopback;
 
 mod types;
-pub use types::{FwVer, Guid, LinkLayer, WCType, WRID};
+pub use types::{
+    FwVer, Guid, LinkLayer, Mtu, PortState, TransportType, WCType, WRID, imm_from_wire, imm_to_wire,
+};
+
+mod wr;
+pub use wr::{SendOpcode, SendWr, post_send_typed};
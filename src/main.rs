@@ -3,13 +3,13 @@
 //! Query and display RDMA devices on the system in JSON format.
 
 use clap::Parser;
-use ruapc_rdma_sys::{DeviceConfig, Devices, GidType};
+use ruapc_rdma_sys::{DeviceConfig, Devices, DevicesReport, GidType};
 
 #[derive(Parser, Debug)]
 #[command(name = "ruapc-rdma-sys")]
 #[command(about = "Query and display RDMA devices in JSON format", long_about = None)]
 struct Args {
-    /// Filter by device name(s)
+    /// Filter by device name(s), optionally qualified as name@guid
     #[arg(short = 'd', long, num_args = 0..)]
     devices: Vec<String>,
 
@@ -25,9 +25,25 @@ struct Args {
     #[arg(long)]
     skip_link_local: bool,
 
+    /// Only include ports carrying this partition key (e.g. 0xffff)
+    #[arg(short = 'p', long, value_parser = parse_pkey)]
+    pkey: Option<u16>,
+
+    /// Keep only the first GID of each address on a port
+    #[arg(long)]
+    dedup_gids: bool,
+
+    /// Include unused (null) GID table entries
+    #[arg(long)]
+    include_null_gids: bool,
+
     /// Compact JSON output (default is pretty)
     #[arg(short = 'c', long)]
     compact: bool,
+
+    /// Print the JSON schema of the report instead of querying devices
+    #[arg(long)]
+    schema: bool,
 }
 
 impl Args {
@@ -38,19 +54,30 @@ impl Args {
             gid_type_filter: self.gid_types.iter().cloned().collect(),
             skip_inactive_port: self.skip_inactive,
             roce_v2_skip_link_local_addr: self.skip_link_local,
+            pkey_filter: self.pkey,
+            dedup_gids: self.dedup_gids,
+            include_null_gids: self.include_null_gids,
+            ..Default::default()
         }
     }
 }
 
+/// Parses a partition key in decimal or `0x`-prefixed hexadecimal.
+fn parse_pkey(s: &str) -> Result<u16, std::num::ParseIntError> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+}
+
 fn main() -> Result<(), ruapc_rdma_sys::Error> {
     let args = Args::parse();
-    let config = args.to_config();
-    let devices = Devices::open(&config)?;
-
-    let json: Vec<serde_json::Value> = devices
-        .iter()
-        .map(|d| serde_json::to_value(d.info()).unwrap())
-        .collect();
+    let json = if args.schema {
+        serde_json::to_value(schemars::schema_for!(DevicesReport)).unwrap()
+    } else {
+        let devices = Devices::open(&args.to_config())?;
+        serde_json::to_value(devices.to_report()).unwrap()
+    };
 
     if args.compact {
         println!("{}", serde_json::to_string(&json).unwrap());
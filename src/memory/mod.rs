@@ -0,0 +1,33 @@
+//! # Memory registration
+//!
+//! This module provides RAII wrappers for RDMA memory regions and a per-device
+//! registry that tracks the keys of every live registration.
+//!
+//! ## Module Organization
+//!
+//! - [`region.rs`](region): Memory region and owned registered buffer
+//! - [`registry.rs`](registry): Address-range lookup table for lkey/rkey
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use ruapc_rdma_sys::{Devices, ibv_access_flags};
+//!
+//! let devices = Devices::available()?;
+//! let device = devices.first().unwrap();
+//!
+//! let buf = device.register_memory(
+//!     vec![0u8; 4096],
+//!     ibv_access_flags::IBV_ACCESS_LOCAL_WRITE | ibv_access_flags::IBV_ACCESS_REMOTE_WRITE,
+//! )?;
+//! let (lkey, rkey) = device.memory_registry().lookup(buf.as_ptr() as u64).unwrap();
+//! assert_eq!(lkey, buf.mr().lkey());
+//! assert_eq!(rkey, buf.mr().rkey());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+mod region;
+mod registry;
+
+pub use region::{MemoryRegion, RegisteredBuffer};
+pub use registry::MemoryRegistry;
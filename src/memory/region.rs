@@ -0,0 +1,425 @@
+//! # Memory regions
+//!
+//! [`MemoryRegion`] wraps an `ibv_mr` and deregisters it on drop.
+//! [`RegisteredBuffer`] additionally owns the registered memory, which makes
+//! it the safe entry point for registration.
+
+use std::{
+    ops::{Deref, DerefMut, Range},
+    os::raw::c_void,
+    sync::Arc,
+};
+
+use crate::{Device, Error, ErrorKind, Result, ibv_access_flags, ibv_sge};
+
+/// Registered memory region with automatic deregistration.
+///
+/// Holds a reference to its [`Device`] so the protection domain outlives
+/// the registration. The region does not own the registered memory.
+pub struct MemoryRegion {
+    mr: *mut crate::ibv_mr,
+    iova: u64,
+    access: ibv_access_flags,
+    device: Arc<Device>,
+}
+
+unsafe impl Send for MemoryRegion {}
+unsafe impl Sync for MemoryRegion {}
+
+impl MemoryRegion {
+    /// Registers `len` bytes at `addr` with the device's protection domain.
+    ///
+    /// # Safety
+    ///
+    /// The memory must stay valid until the returned region is dropped.
+    pub(crate) unsafe fn register(
+        device: &Arc<Device>,
+        addr: *mut c_void,
+        len: usize,
+        access: ibv_access_flags,
+    ) -> Result<Self> {
+        let mr = unsafe { crate::ibv_reg_mr(device.pd_ptr(), addr, len, access.0 as _) };
+        Self::from_raw(device, mr, addr as u64, len, access)
+    }
+
+    /// Registers `len` bytes at `addr`, addressed by peers and SGEs as `iova`.
+    ///
+    /// # Safety
+    ///
+    /// The memory must stay valid until the returned region is dropped.
+    pub(crate) unsafe fn register_iova(
+        device: &Arc<Device>,
+        addr: *mut c_void,
+        len: usize,
+        iova: u64,
+        access: ibv_access_flags,
+    ) -> Result<Self> {
+        if access.0 & ibv_access_flags::IBV_ACCESS_ON_DEMAND.0 != 0 && !device.supports_odp() {
+            return Err(Error::new(
+                ErrorKind::Unsupported("on-demand paging".to_string()),
+                format!("device {} does not support ODP", device.info().name),
+            ));
+        }
+        let mr =
+            unsafe { crate::ibv_reg_mr_iova2(device.pd_ptr(), addr, len, iova, access.0 as _) };
+        Self::from_raw(device, mr, iova, len, access)
+    }
+
+    /// Wraps a freshly registered `mr` and records it in the registry.
+    fn from_raw(
+        device: &Arc<Device>,
+        mr: *mut crate::ibv_mr,
+        iova: u64,
+        len: usize,
+        access: ibv_access_flags,
+    ) -> Result<Self> {
+        if mr.is_null() {
+            return Err(ErrorKind::IBRegMemoryRegionFail.with_errno());
+        }
+        let this = Self {
+            mr,
+            iova,
+            access,
+            device: device.clone(),
+        };
+        device
+            .memory_registry()
+            .insert(iova, len as u64, this.lkey(), this.rkey());
+        Ok(this)
+    }
+
+    /// Returns the start address of the region.
+    pub fn addr(&self) -> u64 {
+        unsafe { (*self.mr).addr as u64 }
+    }
+
+    /// Returns the I/O virtual address used in SGEs and by peers.
+    ///
+    /// Equal to [`addr`](Self::addr) unless the region was registered with
+    /// [`Device::register_memory_iova`].
+    pub fn iova(&self) -> u64 {
+        self.iova
+    }
+
+    /// Returns the length of the region in bytes.
+    pub fn len(&self) -> usize {
+        unsafe { (*self.mr).length }
+    }
+
+    /// Returns true if the region has zero length.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the local key used in scatter/gather entries.
+    pub fn lkey(&self) -> u32 {
+        unsafe { (*self.mr).lkey }
+    }
+
+    /// Returns the remote key advertised to peers for RDMA access.
+    pub fn rkey(&self) -> u32 {
+        unsafe { (*self.mr).rkey }
+    }
+
+    /// Returns the access flags the region was registered with.
+    pub fn access(&self) -> ibv_access_flags {
+        self.access
+    }
+
+    /// Returns true if the region was registered with all of `flags`.
+    pub fn has_access(&self, flags: ibv_access_flags) -> bool {
+        self.access & flags == flags
+    }
+
+    /// Returns true if the local device may write to the region.
+    ///
+    /// Required for receive buffers and the targets of RDMA reads.
+    pub fn allows_local_write(&self) -> bool {
+        self.has_access(ibv_access_flags::IBV_ACCESS_LOCAL_WRITE)
+    }
+
+    /// Returns true if peers may RDMA read from the region.
+    pub fn allows_remote_read(&self) -> bool {
+        self.has_access(ibv_access_flags::IBV_ACCESS_REMOTE_READ)
+    }
+
+    /// Returns true if peers may RDMA write to the region.
+    pub fn allows_remote_write(&self) -> bool {
+        self.has_access(ibv_access_flags::IBV_ACCESS_REMOTE_WRITE)
+    }
+
+    /// Returns true if peers may perform atomic operations on the region.
+    pub fn allows_remote_atomic(&self) -> bool {
+        self.has_access(ibv_access_flags::IBV_ACCESS_REMOTE_ATOMIC)
+    }
+
+    /// Returns true if any remote access was granted, so the rkey is usable.
+    pub fn allows_remote_access(&self) -> bool {
+        self.allows_remote_read() || self.allows_remote_write() || self.allows_remote_atomic()
+    }
+
+    /// Returns the device this region is registered with.
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    /// Returns the raw memory region pointer.
+    ///
+    /// # Safety
+    ///
+    /// The returned pointer is only valid as long as this `MemoryRegion` exists.
+    pub unsafe fn mr_ptr(&self) -> *mut crate::ibv_mr {
+        self.mr
+    }
+}
+
+impl Drop for MemoryRegion {
+    fn drop(&mut self) {
+        self.device.memory_registry().remove(self.iova, self.lkey());
+        crate::check_cleanup("ibv_dereg_mr", unsafe { crate::ibv_dereg_mr(self.mr) });
+    }
+}
+
+impl std::fmt::Debug for MemoryRegion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemoryRegion")
+            .field("addr", &format_args!("{:#x}", self.addr()))
+            .field("iova", &format_args!("{:#x}", self.iova))
+            .field("len", &self.len())
+            .field("lkey", &self.lkey())
+            .field("rkey", &self.rkey())
+            .field("access", &format_args!("{:#x}", self.access.0))
+            .finish()
+    }
+}
+
+/// Heap buffer registered as a memory region.
+///
+/// Owns both the memory and its registration. The region is deregistered
+/// before the memory is freed.
+pub struct RegisteredBuffer {
+    // Declared before `buf` so the registration is dropped first.
+    mr: MemoryRegion,
+    buf: Box<[u8]>,
+}
+
+impl RegisteredBuffer {
+    /// Registers an owned buffer with the device's protection domain.
+    pub(crate) fn new(
+        device: &Arc<Device>,
+        mut buf: Box<[u8]>,
+        access: ibv_access_flags,
+    ) -> Result<Self> {
+        let mr = unsafe {
+            MemoryRegion::register(device, buf.as_mut_ptr() as *mut c_void, buf.len(), access)?
+        };
+        Ok(Self { mr, buf })
+    }
+
+    /// Registers an owned buffer addressed as `iova`.
+    pub(crate) fn with_iova(
+        device: &Arc<Device>,
+        mut buf: Box<[u8]>,
+        iova: u64,
+        access: ibv_access_flags,
+    ) -> Result<Self> {
+        let mr = unsafe {
+            MemoryRegion::register_iova(
+                device,
+                buf.as_mut_ptr() as *mut c_void,
+                buf.len(),
+                iova,
+                access,
+            )?
+        };
+        Ok(Self { mr, buf })
+    }
+
+    /// Returns the memory region covering this buffer.
+    pub fn mr(&self) -> &MemoryRegion {
+        &self.mr
+    }
+
+    /// Returns a scatter/gather entry covering `range` of this buffer.
+    ///
+    /// The address is offset from the region's [`iova`](MemoryRegion::iova),
+    /// so the entry is also valid for buffers registered at an I/O virtual
+    /// address.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds, reversed, or longer than
+    /// `u32::MAX` bytes, like slice indexing.
+    pub fn sge(&self, range: Range<usize>) -> ibv_sge {
+        range_sge(self.mr.iova(), self.buf.len(), self.mr.lkey(), range)
+    }
+}
+
+/// Builds the SGE for `range` of a `len`-byte region at `iova`.
+fn range_sge(iova: u64, len: usize, lkey: u32, range: Range<usize>) -> ibv_sge {
+    assert!(
+        range.start <= range.end && range.end <= len,
+        "SGE range {range:?} out of bounds for buffer of {len} bytes"
+    );
+    let length = u32::try_from(range.len()).expect("SGE length exceeds u32::MAX");
+    ibv_sge {
+        addr: iova + range.start as u64,
+        length,
+        lkey,
+    }
+}
+
+impl Deref for RegisteredBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.buf
+    }
+}
+
+impl DerefMut for RegisteredBuffer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.buf
+    }
+}
+
+impl std::fmt::Debug for RegisteredBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&self.mr, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::range_sge;
+    use crate::{Devices, ibv_access_flags};
+
+    #[test]
+    fn test_range_sge() {
+        let sge = range_sge(0x1000, 4096, 0x42, 0..4096);
+        assert_eq!((sge.addr, sge.length, sge.lkey), (0x1000, 4096, 0x42));
+        let sge = range_sge(0x1000, 4096, 0x42, 100..164);
+        assert_eq!((sge.addr, sge.length), (0x1064, 64));
+        let sge = range_sge(0x1000, 4096, 0x42, 4096..4096);
+        assert_eq!((sge.addr, sge.length), (0x2000, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_range_sge_past_end() {
+        range_sge(0x1000, 4096, 0x42, 4000..4097);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn test_range_sge_reversed() {
+        range_sge(0x1000, 4096, 0x42, 200..100);
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds u32::MAX")]
+    fn test_range_sge_too_long() {
+        range_sge(0, usize::MAX, 0x42, 0..1 << 32);
+    }
+
+    #[test]
+    fn register_memory() {
+        let devices = Devices::available().unwrap();
+        let device = devices.first().unwrap();
+        let access = ibv_access_flags::IBV_ACCESS_LOCAL_WRITE;
+
+        let a = device.register_memory(vec![0u8; 4096], access).unwrap();
+        let b = device.register_memory(vec![0u8; 1024], access).unwrap();
+        assert_eq!(a.mr().len(), 4096);
+        assert_eq!(a.mr().addr(), a.as_ptr() as u64);
+
+        let registry = device.memory_registry();
+        let addr = a.as_ptr() as u64 + 100;
+        assert_eq!(registry.lookup(addr), Some((a.mr().lkey(), a.mr().rkey())));
+        let addr = b.as_ptr() as u64;
+        assert_eq!(registry.lookup(addr), Some((b.mr().lkey(), b.mr().rkey())));
+
+        let addr = b.as_ptr() as u64;
+        drop(b);
+        assert_eq!(registry.lookup(addr), None);
+        drop(a);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn register_memory_access() {
+        let devices = Devices::available().unwrap();
+        let device = devices.first().unwrap();
+
+        let local = ibv_access_flags::IBV_ACCESS_LOCAL_WRITE;
+        let buf = device.register_memory(vec![0u8; 4096], local).unwrap();
+        assert_eq!(buf.mr().access(), local);
+        assert!(buf.mr().allows_local_write());
+        assert!(!buf.mr().allows_remote_read());
+        assert!(!buf.mr().allows_remote_write());
+        assert!(!buf.mr().allows_remote_atomic());
+        assert!(!buf.mr().allows_remote_access());
+
+        let remote = local
+            | ibv_access_flags::IBV_ACCESS_REMOTE_READ
+            | ibv_access_flags::IBV_ACCESS_REMOTE_WRITE;
+        let buf = device.register_memory(vec![0u8; 4096], remote).unwrap();
+        assert!(buf.mr().allows_remote_read());
+        assert!(buf.mr().allows_remote_write());
+        assert!(!buf.mr().allows_remote_atomic());
+        assert!(buf.mr().allows_remote_access());
+        assert!(buf.mr().has_access(remote));
+        assert!(
+            !buf.mr()
+                .has_access(ibv_access_flags::IBV_ACCESS_REMOTE_ATOMIC | local)
+        );
+    }
+
+    #[test]
+    fn register_memory_raw() {
+        let devices = Devices::available().unwrap();
+        let device = devices.first().unwrap();
+        let access = ibv_access_flags::IBV_ACCESS_LOCAL_WRITE;
+
+        let buf: &mut [u8] = Box::leak(vec![0u8; 4096].into_boxed_slice());
+        let (addr, len) = (buf.as_mut_ptr(), buf.len());
+        let mr = unsafe { device.register_raw(addr.cast(), len, access).unwrap() };
+        assert_eq!(mr.addr(), addr as u64);
+        assert_eq!(mr.len(), len);
+        assert_eq!(
+            device.memory_registry().lookup(addr as u64),
+            Some((mr.lkey(), mr.rkey()))
+        );
+
+        // Dropping the region deregisters but leaves the memory to the caller
+        drop(mr);
+        assert_eq!(device.memory_registry().lookup(addr as u64), None);
+        drop(unsafe { Box::from_raw(std::ptr::slice_from_raw_parts_mut(addr, len)) });
+    }
+
+    #[test]
+    fn register_memory_iova() {
+        let devices = Devices::available().unwrap();
+        let device = devices.first().unwrap();
+        let access = ibv_access_flags::IBV_ACCESS_LOCAL_WRITE;
+
+        let iova = 0x1000_0000;
+        let buf = device
+            .register_memory_iova(vec![0u8; 4096], iova, access)
+            .unwrap();
+        assert_eq!(buf.mr().iova(), iova);
+        let registry = device.memory_registry();
+        assert_eq!(
+            registry.lookup(iova + 100),
+            Some((buf.mr().lkey(), buf.mr().rkey()))
+        );
+
+        let odp = access | ibv_access_flags::IBV_ACCESS_ON_DEMAND;
+        match device.register_memory_iova(vec![0u8; 4096], iova, odp) {
+            Ok(buf) => assert!(device.supports_odp() && buf.mr().iova() == iova),
+            Err(err) if !device.supports_odp() => assert!(err.kind.is_unsupported(), "{err}"),
+            Err(err) => assert_eq!(err.kind, crate::ErrorKind::IBRegMemoryRegionFail),
+        }
+    }
+}
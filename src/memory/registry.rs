@@ -0,0 +1,115 @@
+//! # Memory registry
+//!
+//! Tracks the address range and keys of every live [`MemoryRegion`] of a
+//! device so that the keys covering a buffer address can be looked up.
+//!
+//! [`MemoryRegion`]: super::MemoryRegion
+
+use std::{collections::BTreeMap, sync::Mutex};
+
+/// Registered range with its keys.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    len: u64,
+    lkey: u32,
+    rkey: u32,
+}
+
+/// Thread-safe table mapping registered address ranges to their keys.
+///
+/// Each [`Device`](crate::Device) owns one registry. Memory regions insert
+/// themselves on registration and remove themselves when dropped, so the
+/// registry always reflects the live registrations. Ranges are keyed by
+/// [`MemoryRegion::iova`](super::MemoryRegion::iova), which is the buffer
+/// address unless the region was registered with a custom IOVA.
+#[derive(Debug, Default)]
+pub struct MemoryRegistry {
+    /// Keyed by (start address, lkey) since ranges may overlap or share a start.
+    entries: Mutex<BTreeMap<(u64, u32), Entry>>,
+}
+
+impl MemoryRegistry {
+    /// Records a registered range.
+    pub(crate) fn insert(&self, addr: u64, len: u64, lkey: u32, rkey: u32) {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert((addr, lkey), Entry { len, lkey, rkey });
+    }
+
+    /// Removes a registered range.
+    pub(crate) fn remove(&self, addr: u64, lkey: u32) {
+        self.entries.lock().unwrap().remove(&(addr, lkey));
+    }
+
+    /// Returns the `(lkey, rkey)` of a registered region containing `addr`.
+    ///
+    /// When several registrations overlap `addr`, the one with the highest
+    /// start address is returned. Returns `None` if no registration covers
+    /// the address.
+    pub fn lookup(&self, addr: u64) -> Option<(u32, u32)> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .range(..=(addr, u32::MAX))
+            .rev()
+            .find(|((start, _), entry)| addr - start < entry.len)
+            .map(|(_, entry)| (entry.lkey, entry.rkey))
+    }
+
+    /// Returns the number of live registrations.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Returns true if there are no live registrations.
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().unwrap().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_disjoint() {
+        let registry = MemoryRegistry::default();
+        registry.insert(0x1000, 0x1000, 1, 101);
+        registry.insert(0x4000, 0x100, 2, 102);
+        assert_eq!(registry.len(), 2);
+
+        assert_eq!(registry.lookup(0x1000), Some((1, 101)));
+        assert_eq!(registry.lookup(0x1fff), Some((1, 101)));
+        assert_eq!(registry.lookup(0x2000), None);
+        assert_eq!(registry.lookup(0x0fff), None);
+        assert_eq!(registry.lookup(0x40ff), Some((2, 102)));
+        assert_eq!(registry.lookup(0x4100), None);
+    }
+
+    #[test]
+    fn test_registry_overlapping() {
+        let registry = MemoryRegistry::default();
+        registry.insert(0x1000, 0x4000, 1, 101);
+        registry.insert(0x2000, 0x1000, 2, 102);
+        registry.insert(0x1000, 0x100, 3, 103);
+
+        assert_eq!(registry.lookup(0x1000), Some((3, 103)));
+        assert_eq!(registry.lookup(0x1100), Some((1, 101)));
+        assert_eq!(registry.lookup(0x2800), Some((2, 102)));
+        assert_eq!(registry.lookup(0x3000), Some((1, 101)));
+        assert_eq!(registry.lookup(0x4fff), Some((1, 101)));
+        assert_eq!(registry.lookup(0x5000), None);
+    }
+
+    #[test]
+    fn test_registry_remove() {
+        let registry = MemoryRegistry::default();
+        registry.insert(0x1000, 0x1000, 1, 101);
+        registry.insert(0x1000, 0x1000, 2, 102);
+        registry.remove(0x1000, 2);
+        assert_eq!(registry.lookup(0x1800), Some((1, 101)));
+        registry.remove(0x1000, 1);
+        assert_eq!(registry.lookup(0x1800), None);
+        assert!(registry.is_empty());
+    }
+}
@@ -0,0 +1,256 @@
+//! # Queue pair creation attributes
+//!
+//! [`QpInitAttrBuilder`] fills an `ibv_qp_init_attr` with sane capacity
+//! defaults and validates it against the device limits.
+
+use crate::{
+    Error, ErrorKind, Result, ibv_cq, ibv_device_attr, ibv_qp_cap, ibv_qp_init_attr, ibv_qp_type,
+    ibv_srq,
+};
+
+/// Builder for [`ibv_qp_init_attr`].
+///
+/// Defaults to a reliable-connected QP with 128 send and receive work
+/// requests, one SGE per request and no inline data.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use ruapc_rdma_sys::{Devices, QpInitAttrBuilder};
+/// # fn create(cq: *mut ruapc_rdma_sys::ibv_cq) -> ruapc_rdma_sys::Result<()> {
+/// let devices = Devices::available()?;
+/// let init_attr = QpInitAttrBuilder::default()
+///     .cq(cq)
+///     .max_send_wr(256)
+///     .max_send_sge(4)
+///     .build(&devices[0].info().device_attr)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct QpInitAttrBuilder {
+    attr: ibv_qp_init_attr,
+}
+
+impl Default for QpInitAttrBuilder {
+    fn default() -> Self {
+        Self {
+            attr: ibv_qp_init_attr {
+                qp_context: std::ptr::null_mut(),
+                send_cq: std::ptr::null_mut(),
+                recv_cq: std::ptr::null_mut(),
+                srq: std::ptr::null_mut(),
+                cap: ibv_qp_cap {
+                    max_send_wr: 128,
+                    max_recv_wr: 128,
+                    max_send_sge: 1,
+                    max_recv_sge: 1,
+                    max_inline_data: 0,
+                },
+                qp_type: ibv_qp_type::IBV_QPT_RC,
+                sq_sig_all: 0,
+            },
+        }
+    }
+}
+
+impl QpInitAttrBuilder {
+    /// Sets the QP transport type, as a [`QpType`](crate::QpType) or a raw
+    /// `ibv_qp_type`.
+    pub fn qp_type(mut self, qp_type: impl Into<ibv_qp_type>) -> Self {
+        self.attr.qp_type = qp_type.into();
+        self
+    }
+
+    /// Uses `cq` for both send and receive completions.
+    pub fn cq(self, cq: *mut ibv_cq) -> Self {
+        self.send_cq(cq).recv_cq(cq)
+    }
+
+    /// Sets the send completion queue.
+    pub fn send_cq(mut self, cq: *mut ibv_cq) -> Self {
+        self.attr.send_cq = cq;
+        self
+    }
+
+    /// Sets the receive completion queue.
+    pub fn recv_cq(mut self, cq: *mut ibv_cq) -> Self {
+        self.attr.recv_cq = cq;
+        self
+    }
+
+    /// Sets the shared receive queue.
+    pub fn srq(mut self, srq: *mut ibv_srq) -> Self {
+        self.attr.srq = srq;
+        self
+    }
+
+    /// Sets the maximum number of outstanding send work requests.
+    pub fn max_send_wr(mut self, max_send_wr: u32) -> Self {
+        self.attr.cap.max_send_wr = max_send_wr;
+        self
+    }
+
+    /// Sets the maximum number of outstanding receive work requests.
+    pub fn max_recv_wr(mut self, max_recv_wr: u32) -> Self {
+        self.attr.cap.max_recv_wr = max_recv_wr;
+        self
+    }
+
+    /// Sets the maximum number of SGEs per send work request.
+    pub fn max_send_sge(mut self, max_send_sge: u32) -> Self {
+        self.attr.cap.max_send_sge = max_send_sge;
+        self
+    }
+
+    /// Sets the maximum number of SGEs per receive work request.
+    pub fn max_recv_sge(mut self, max_recv_sge: u32) -> Self {
+        self.attr.cap.max_recv_sge = max_recv_sge;
+        self
+    }
+
+    /// Sets the maximum inline payload in bytes.
+    pub fn max_inline_data(mut self, max_inline_data: u32) -> Self {
+        self.attr.cap.max_inline_data = max_inline_data;
+        self
+    }
+
+    /// Sets whether every send work request generates a completion.
+    pub fn sq_sig_all(mut self, sq_sig_all: bool) -> Self {
+        self.attr.sq_sig_all = sq_sig_all.into();
+        self
+    }
+
+    /// Validates the attributes against `device_attr` and returns them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::IBCreateQueuePairFail`] if a completion queue is
+    /// missing or a work request or SGE count exceeds the device limits, and
+    /// [`ErrorKind::Unsupported`] if a raw packet QP is requested without
+    /// `CAP_NET_RAW`.
+    pub fn build(self, device_attr: &ibv_device_attr) -> Result<ibv_qp_init_attr> {
+        let invalid = |msg: String| Err(Error::new(ErrorKind::IBCreateQueuePairFail, msg));
+        let cap = &self.attr.cap;
+
+        if self.attr.send_cq.is_null() || self.attr.recv_cq.is_null() {
+            return invalid("send_cq and recv_cq must be set".to_string());
+        }
+        if self.attr.qp_type == ibv_qp_type::IBV_QPT_RAW_PACKET && !has_effective_cap(CAP_NET_RAW) {
+            return Err(Error::new(
+                ErrorKind::Unsupported("CAP_NET_RAW".to_string()),
+                "RAW_PACKET QPs require CAP_NET_RAW".to_string(),
+            ));
+        }
+        let max_wr = device_attr.max_qp_wr.max(0) as u32;
+        for (name, value) in [
+            ("max_send_wr", cap.max_send_wr),
+            ("max_recv_wr", cap.max_recv_wr),
+        ] {
+            if value > max_wr {
+                return invalid(format!("{name} {value} exceeds device max_qp_wr {max_wr}"));
+            }
+        }
+        let max_sge = device_attr.max_sge.max(0) as u32;
+        for (name, value) in [
+            ("max_send_sge", cap.max_send_sge),
+            ("max_recv_sge", cap.max_recv_sge),
+        ] {
+            if value > max_sge {
+                return invalid(format!("{name} {value} exceeds device max_sge {max_sge}"));
+            }
+        }
+        Ok(self.attr)
+    }
+}
+
+/// Capability number of `CAP_NET_RAW`.
+const CAP_NET_RAW: u32 = 13;
+
+/// Returns true if the process holds capability `cap`.
+///
+/// Reads the effective set from `/proc/self/status`; if it cannot be read,
+/// assumes the capability is held and leaves the check to the provider.
+fn has_effective_cap(cap: u32) -> bool {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| effective_caps(&status))
+        .is_none_or(|caps| caps & (1 << cap) != 0)
+}
+
+/// Parses the `CapEff` bitmask from the contents of `/proc/<pid>/status`.
+fn effective_caps(status: &str) -> Option<u64> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|hex| u64::from_str_radix(hex.trim(), 16).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device_attr() -> ibv_device_attr {
+        ibv_device_attr {
+            max_qp_wr: 1024,
+            max_sge: 4,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_qp_init_attr_defaults() {
+        let cq = std::ptr::NonNull::<ibv_cq>::dangling().as_ptr();
+        let attr = QpInitAttrBuilder::default()
+            .cq(cq)
+            .build(&device_attr())
+            .unwrap();
+        assert_eq!(attr.qp_type, ibv_qp_type::IBV_QPT_RC);
+        assert_eq!(attr.send_cq, cq);
+        assert_eq!(attr.recv_cq, cq);
+        assert!(attr.srq.is_null());
+        assert_eq!(attr.cap.max_send_wr, 128);
+        assert_eq!(attr.cap.max_recv_wr, 128);
+        assert_eq!(attr.cap.max_send_sge, 1);
+        assert_eq!(attr.cap.max_recv_sge, 1);
+        assert_eq!(attr.sq_sig_all, 0);
+    }
+
+    #[test]
+    fn test_qp_init_attr_validation() {
+        let cq = std::ptr::NonNull::<ibv_cq>::dangling().as_ptr();
+        let err = QpInitAttrBuilder::default()
+            .build(&device_attr())
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::IBCreateQueuePairFail);
+
+        let builder = QpInitAttrBuilder::default().cq(cq);
+        assert!(builder.max_send_sge(4).build(&device_attr()).is_ok());
+        assert!(builder.max_send_sge(5).build(&device_attr()).is_err());
+        assert!(builder.max_recv_sge(5).build(&device_attr()).is_err());
+        assert!(builder.max_recv_wr(1024).build(&device_attr()).is_ok());
+        assert!(builder.max_send_wr(1025).build(&device_attr()).is_err());
+    }
+
+    #[test]
+    fn test_qp_init_attr_qp_type() {
+        let cq = std::ptr::NonNull::<ibv_cq>::dangling().as_ptr();
+        let builder = QpInitAttrBuilder::default().cq(cq);
+        let attr = builder.qp_type(crate::QpType::Ud).build(&device_attr());
+        assert_eq!(attr.unwrap().qp_type, ibv_qp_type::IBV_QPT_UD);
+        let attr = builder
+            .qp_type(ibv_qp_type::IBV_QPT_UC)
+            .build(&device_attr());
+        assert_eq!(attr.unwrap().qp_type, ibv_qp_type::IBV_QPT_UC);
+    }
+
+    #[test]
+    fn test_effective_caps() {
+        let status = "Name:\tserver\nCapInh:\t0000000000000000\nCapEff:\t0000000000002000\n";
+        let caps = effective_caps(status).unwrap();
+        assert_ne!(caps & (1 << CAP_NET_RAW), 0);
+        assert_eq!(caps & !(1 << CAP_NET_RAW), 0);
+        assert_eq!(effective_caps("CapEff:\t0000000000000000\n"), Some(0));
+        assert_eq!(effective_caps("Name:\tserver\n"), None);
+    }
+}
@@ -0,0 +1,59 @@
+//! # Queue pair setup
+//!
+//! This module provides the [`QueuePair`] wrapper together with the
+//! parameters and state transitions needed to bring a reliable-connected
+//! queue pair from RESET to RTS.
+//!
+//! ## Module Organization
+//!
+//! - [`init_attr.rs`](init_attr): `ibv_qp_init_attr` builder with capacity validation
+//! - [`params.rs`](params): RTR/RTS transition parameters with defaults, random PSNs
+//! - [`modify.rs`](modify): `ibv_modify_qp` state transitions
+//! - [`qp_type.rs`](qp_type): Named queue pair transport types
+//! - [`queue_pair.rs`](queue_pair): `ibv_qp` wrapper with RAII cleanup
+//! - [`state.rs`](state): Named queue pair states
+//!
+//! ## Example
+//!
+//! ```rust,no_run
+//! use ruapc_rdma_sys::{RtrParams, RtsParams, ibv_access_flags, ibv_mtu, random_psn};
+//!
+//! # fn exchange(local_psn: u32) -> (u32, u32) { (0, local_psn) }
+//! # fn connect(qp: *mut ruapc_rdma_sys::ibv_qp) -> ruapc_rdma_sys::Result<()> {
+//! // Send the local PSN to the peer out of band and receive its QPN and PSN
+//! let local_psn = random_psn();
+//! let (remote_qpn, remote_psn) = exchange(local_psn);
+//! let rtr = RtrParams {
+//!     dest_qp_num: remote_qpn,
+//!     rq_psn: remote_psn,
+//!     path_mtu: ibv_mtu::IBV_MTU_4096,
+//!     ..Default::default()
+//! };
+//! // Longer ack timeout for high-latency links (4.096us * 2^18 ~= 1s)
+//! let rts = RtsParams {
+//!     timeout: 18,
+//!     sq_psn: local_psn,
+//!     ..Default::default()
+//! };
+//! unsafe {
+//!     ruapc_rdma_sys::modify_to_init(qp, 1, ibv_access_flags::IBV_ACCESS_LOCAL_WRITE)?;
+//!     ruapc_rdma_sys::modify_to_rtr(qp, 1, &rtr)?;
+//!     ruapc_rdma_sys::modify_to_rts(qp, &rts)?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod init_attr;
+mod modify;
+mod params;
+mod qp_type;
+mod queue_pair;
+mod state;
+
+pub use init_attr::QpInitAttrBuilder;
+pub use modify::{modify_to_init, modify_to_rtr, modify_to_rts};
+pub use params::{RtrParams, RtsParams, random_psn};
+pub use qp_type::QpType;
+pub use queue_pair::QueuePair;
+pub use state::QpState;
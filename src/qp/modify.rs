@@ -0,0 +1,94 @@
+//! # Queue pair state transitions
+//!
+//! Thin wrappers over `ibv_modify_qp` for the RESET→INIT→RTR→RTS sequence
+//! of a reliable-connected queue pair.
+
+use super::{RtrParams, RtsParams};
+use crate::{
+    Error, ErrorKind, Result, ibv_access_flags, ibv_mtu, ibv_qp, ibv_qp_attr, ibv_qp_attr_mask,
+    ibv_qp_state,
+};
+
+/// Returns a zero-filled `ibv_qp_attr` with valid enum fields.
+///
+/// `ibv_mtu` has no zero variant, so `path_mtu` is written before the value
+/// is assumed initialized.
+pub(crate) fn zeroed_qp_attr() -> ibv_qp_attr {
+    let mut attr = std::mem::MaybeUninit::<ibv_qp_attr>::zeroed();
+    unsafe {
+        std::ptr::addr_of_mut!((*attr.as_mut_ptr()).path_mtu).write(ibv_mtu::IBV_MTU_1024);
+        attr.assume_init()
+    }
+}
+
+/// Applies `attr` to `qp` and classifies failures.
+///
+/// # Safety
+///
+/// `qp` must be a valid queue pair.
+pub(crate) unsafe fn modify_qp(
+    qp: *mut ibv_qp,
+    attr: &mut ibv_qp_attr,
+    mask: ibv_qp_attr_mask,
+) -> Result<()> {
+    let ret = unsafe { crate::ibv_modify_qp(qp, attr, mask.0 as _) };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(Error::from_errno(ErrorKind::from_modify_qp_errno(ret), ret))
+    }
+}
+
+/// Transitions a queue pair from RESET to INIT.
+///
+/// # Errors
+///
+/// Returns an error classified by [`ErrorKind::from_modify_qp_errno`].
+///
+/// # Safety
+///
+/// `qp` must be a valid queue pair.
+pub unsafe fn modify_to_init(
+    qp: *mut ibv_qp,
+    port_num: u8,
+    access: ibv_access_flags,
+) -> Result<()> {
+    let mut attr = zeroed_qp_attr();
+    attr.qp_state = ibv_qp_state::IBV_QPS_INIT;
+    attr.pkey_index = 0;
+    attr.port_num = port_num;
+    attr.qp_access_flags = access.0;
+    let mask = ibv_qp_attr_mask::IBV_QP_STATE
+        | ibv_qp_attr_mask::IBV_QP_PKEY_INDEX
+        | ibv_qp_attr_mask::IBV_QP_PORT
+        | ibv_qp_attr_mask::IBV_QP_ACCESS_FLAGS;
+    unsafe { modify_qp(qp, &mut attr, mask) }
+}
+
+/// Transitions a queue pair from INIT to RTR.
+///
+/// # Errors
+///
+/// Returns an error classified by [`ErrorKind::from_modify_qp_errno`].
+///
+/// # Safety
+///
+/// `qp` must be a valid queue pair.
+pub unsafe fn modify_to_rtr(qp: *mut ibv_qp, port_num: u8, params: &RtrParams) -> Result<()> {
+    let (mut attr, mask) = params.to_qp_attr(port_num);
+    unsafe { modify_qp(qp, &mut attr, mask) }
+}
+
+/// Transitions a queue pair from RTR to RTS.
+///
+/// # Errors
+///
+/// Returns an error classified by [`ErrorKind::from_modify_qp_errno`].
+///
+/// # Safety
+///
+/// `qp` must be a valid queue pair.
+pub unsafe fn modify_to_rts(qp: *mut ibv_qp, params: &RtsParams) -> Result<()> {
+    let (mut attr, mask) = params.to_qp_attr();
+    unsafe { modify_qp(qp, &mut attr, mask) }
+}
@@ -0,0 +1,285 @@
+//! # Queue pair transition parameters
+//!
+//! [`RtrParams`] and [`RtsParams`] carry the attributes applied by the
+//! RESET→INIT→RTR→RTS transitions, with defaults suitable for a LAN.
+//! [`random_psn`] picks the starting packet sequence number.
+
+use crate::{ibv_gid, ibv_mtu, ibv_qp_attr, ibv_qp_attr_mask};
+
+/// Hop limit used in the global route header.
+const GRH_HOP_LIMIT: u8 = 64;
+
+/// Mask of the 24-bit packet sequence number.
+const PSN_MASK: u32 = 0xff_ffff;
+
+/// Returns a random 24-bit packet sequence number from the OS RNG.
+///
+/// A fresh PSN for each connection keeps a re-established QP from
+/// accepting stale packets of its predecessor, as a fixed PSN of 0 would.
+/// Send it to the peer as part of the connection info; the peer's
+/// [`RtrParams::rq_psn`] must equal this side's [`RtsParams::sq_psn`].
+pub fn random_psn() -> u32 {
+    let mut buf = [0u8; 4];
+    let ret = unsafe { libc::getrandom(buf.as_mut_ptr().cast(), buf.len(), 0) };
+    let value = if ret == buf.len() as isize {
+        u32::from_ne_bytes(buf)
+    } else {
+        // getrandom(2) needs Linux 3.17; the std hasher keys are also
+        // seeded from the OS RNG
+        use std::hash::{BuildHasher, Hasher};
+        std::hash::RandomState::new().build_hasher().finish() as u32
+    };
+    value & PSN_MASK
+}
+
+/// Parameters for the INIT→RTR (ready to receive) transition.
+#[derive(Debug, Clone, Copy)]
+pub struct RtrParams {
+    /// Remote queue pair number.
+    pub dest_qp_num: u32,
+    /// Starting receive packet sequence number; must match the remote `sq_psn`.
+    pub rq_psn: u32,
+    /// Remote GID. A null GID sends without a global route header, which is
+    /// only valid on InfiniBand within a subnet.
+    pub dest_gid: ibv_gid,
+    /// Local GID index used as the source of the global route header.
+    pub sgid_index: u8,
+    /// Remote LID, required on InfiniBand.
+    pub dest_lid: u16,
+    /// Path MTU; must not exceed the active MTU of either port.
+    pub path_mtu: ibv_mtu,
+    /// Number of outstanding RDMA reads/atomics the remote side may issue.
+    pub max_dest_rd_atomic: u8,
+    /// Minimum RNR NAK timer, encoded per the IB specification (12 = 0.64ms).
+    pub min_rnr_timer: u8,
+    /// InfiniBand service level (0-15) of the path, which the subnet maps
+    /// to a virtual lane through its SL-to-VL table.
+    ///
+    /// Ignored on RoCE, where switches classify traffic by the traffic
+    /// class / DSCP of the global route header instead.
+    pub service_level: u8,
+    /// Traffic class of the global route header, the RoCEv2 counterpart of
+    /// [`service_level`](Self::service_level).
+    ///
+    /// Copied into the IPv4 TOS / IPv6 traffic class byte: the DSCP is the
+    /// upper 6 bits and the ECN field the lower 2, so DSCP 26 is
+    /// `26 << 2`. Leave ECN at 0; the NIC sets it for ECN/DCQCN congestion
+    /// control. Only applied when `dest_gid` is set.
+    pub traffic_class: u8,
+}
+
+impl Default for RtrParams {
+    fn default() -> Self {
+        Self {
+            dest_qp_num: 0,
+            rq_psn: 0,
+            dest_gid: ibv_gid::default(),
+            sgid_index: 0,
+            dest_lid: 0,
+            path_mtu: ibv_mtu::IBV_MTU_1024,
+            max_dest_rd_atomic: 1,
+            min_rnr_timer: 12,
+            service_level: 0,
+            traffic_class: 0,
+        }
+    }
+}
+
+impl RtrParams {
+    /// Builds the attribute and mask for `ibv_modify_qp`.
+    pub(crate) fn to_qp_attr(self, port_num: u8) -> (ibv_qp_attr, ibv_qp_attr_mask) {
+        let mut attr = super::modify::zeroed_qp_attr();
+        attr.qp_state = crate::ibv_qp_state::IBV_QPS_RTR;
+        attr.path_mtu = self.path_mtu;
+        attr.dest_qp_num = self.dest_qp_num;
+        attr.rq_psn = self.rq_psn;
+        attr.max_dest_rd_atomic = self.max_dest_rd_atomic;
+        attr.min_rnr_timer = self.min_rnr_timer;
+        attr.ah_attr.dlid = self.dest_lid;
+        attr.ah_attr.sl = self.service_level;
+        attr.ah_attr.port_num = port_num;
+        if !self.dest_gid.is_null() {
+            attr.ah_attr.is_global = 1;
+            attr.ah_attr.grh.dgid = self.dest_gid;
+            attr.ah_attr.grh.sgid_index = self.sgid_index;
+            attr.ah_attr.grh.hop_limit = GRH_HOP_LIMIT;
+            attr.ah_attr.grh.traffic_class = self.traffic_class;
+        }
+        let mask = ibv_qp_attr_mask::IBV_QP_STATE
+            | ibv_qp_attr_mask::IBV_QP_AV
+            | ibv_qp_attr_mask::IBV_QP_PATH_MTU
+            | ibv_qp_attr_mask::IBV_QP_DEST_QPN
+            | ibv_qp_attr_mask::IBV_QP_RQ_PSN
+            | ibv_qp_attr_mask::IBV_QP_MAX_DEST_RD_ATOMIC
+            | ibv_qp_attr_mask::IBV_QP_MIN_RNR_TIMER;
+        (attr, mask)
+    }
+}
+
+/// Parameters for the RTR→RTS (ready to send) transition.
+#[derive(Debug, Clone, Copy)]
+pub struct RtsParams {
+    /// Local ack timeout, 4.096us * 2^timeout (14 ~= 67ms). 0 waits forever.
+    pub timeout: u8,
+    /// Retries after a transport timeout (0-7).
+    pub retry_cnt: u8,
+    /// Retries after an RNR NAK (0-7, 7 retries forever).
+    pub rnr_retry: u8,
+    /// Starting send packet sequence number; must match the remote `rq_psn`.
+    ///
+    /// Defaults to a fresh [`random_psn`], so exchange it with the peer.
+    pub sq_psn: u32,
+    /// Number of outstanding RDMA reads/atomics this side may issue.
+    pub max_rd_atomic: u8,
+}
+
+impl Default for RtsParams {
+    fn default() -> Self {
+        Self {
+            timeout: 14,
+            retry_cnt: 7,
+            rnr_retry: 7,
+            sq_psn: random_psn(),
+            max_rd_atomic: 1,
+        }
+    }
+}
+
+impl RtsParams {
+    /// Builds the attribute and mask for `ibv_modify_qp`.
+    pub(crate) fn to_qp_attr(self) -> (ibv_qp_attr, ibv_qp_attr_mask) {
+        let mut attr = super::modify::zeroed_qp_attr();
+        attr.qp_state = crate::ibv_qp_state::IBV_QPS_RTS;
+        attr.timeout = self.timeout;
+        attr.retry_cnt = self.retry_cnt;
+        attr.rnr_retry = self.rnr_retry;
+        attr.sq_psn = self.sq_psn;
+        attr.max_rd_atomic = self.max_rd_atomic;
+        let mask = ibv_qp_attr_mask::IBV_QP_STATE
+            | ibv_qp_attr_mask::IBV_QP_TIMEOUT
+            | ibv_qp_attr_mask::IBV_QP_RETRY_CNT
+            | ibv_qp_attr_mask::IBV_QP_RNR_RETRY
+            | ibv_qp_attr_mask::IBV_QP_SQ_PSN
+            | ibv_qp_attr_mask::IBV_QP_MAX_QP_RD_ATOMIC;
+        (attr, mask)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ibv_qp_state;
+
+    #[test]
+    fn test_rtr_params_to_qp_attr() {
+        let params = RtrParams {
+            dest_qp_num: 0x1234,
+            rq_psn: 42,
+            dest_lid: 7,
+            path_mtu: ibv_mtu::IBV_MTU_4096,
+            min_rnr_timer: 20,
+            ..Default::default()
+        };
+        let (attr, mask) = params.to_qp_attr(1);
+        assert_eq!(attr.qp_state, ibv_qp_state::IBV_QPS_RTR);
+        assert_eq!(attr.path_mtu, ibv_mtu::IBV_MTU_4096);
+        assert_eq!(attr.dest_qp_num, 0x1234);
+        assert_eq!(attr.rq_psn, 42);
+        assert_eq!(attr.min_rnr_timer, 20);
+        assert_eq!(attr.ah_attr.dlid, 7);
+        assert_eq!(attr.ah_attr.port_num, 1);
+        assert_eq!(attr.ah_attr.sl, 0);
+        assert_eq!(attr.ah_attr.is_global, 0);
+        assert_eq!(
+            mask & ibv_qp_attr_mask::IBV_QP_PATH_MTU,
+            ibv_qp_attr_mask::IBV_QP_PATH_MTU
+        );
+        assert_eq!(mask & ibv_qp_attr_mask::IBV_QP_SQ_PSN, ibv_qp_attr_mask(0));
+    }
+
+    #[test]
+    fn test_rtr_params_global_route() {
+        let dest_gid: ibv_gid = serde_json::from_str("\"fe80::1\"").unwrap();
+        let params = RtrParams {
+            dest_gid,
+            sgid_index: 3,
+            ..Default::default()
+        };
+        let (attr, _) = params.to_qp_attr(1);
+        assert_eq!(attr.ah_attr.is_global, 1);
+        assert_eq!(attr.ah_attr.grh.sgid_index, 3);
+        assert_eq!(attr.ah_attr.grh.dgid.as_raw(), dest_gid.as_raw());
+        assert_eq!(attr.ah_attr.grh.traffic_class, 0);
+    }
+
+    #[test]
+    fn test_rtr_params_traffic_class() {
+        let dest_gid: ibv_gid = serde_json::from_str("\"::ffff:192.168.1.10\"").unwrap();
+        // DSCP 26 (AF31), ECN not-ECT
+        let params = RtrParams {
+            dest_gid,
+            traffic_class: 26 << 2,
+            ..Default::default()
+        };
+        let (attr, _) = params.to_qp_attr(1);
+        assert_eq!(attr.ah_attr.grh.traffic_class, 0x68);
+        assert_eq!(attr.ah_attr.grh.traffic_class >> 2, 26);
+
+        // Without a GRH there is no traffic class to carry it
+        let params = RtrParams {
+            traffic_class: 26 << 2,
+            ..Default::default()
+        };
+        let (attr, _) = params.to_qp_attr(1);
+        assert_eq!(attr.ah_attr.is_global, 0);
+        assert_eq!(attr.ah_attr.grh.traffic_class, 0);
+    }
+
+    #[test]
+    fn test_rtr_params_service_level() {
+        let params = RtrParams {
+            dest_lid: 7,
+            service_level: 3,
+            ..Default::default()
+        };
+        let (attr, mask) = params.to_qp_attr(1);
+        assert_eq!(attr.ah_attr.sl, 3);
+        assert_eq!(attr.ah_attr.dlid, 7);
+        assert_eq!(
+            mask & ibv_qp_attr_mask::IBV_QP_AV,
+            ibv_qp_attr_mask::IBV_QP_AV
+        );
+    }
+
+    #[test]
+    fn test_random_psn() {
+        let psns: std::collections::HashSet<_> = (0..64).map(|_| random_psn()).collect();
+        assert!(psns.iter().all(|&psn| psn <= PSN_MASK));
+        // 64 draws from 2^24 values collide with probability ~1e-4
+        assert!(psns.len() > 60);
+        assert!(RtsParams::default().sq_psn <= PSN_MASK);
+    }
+
+    #[test]
+    fn test_rts_params_to_qp_attr() {
+        let params = RtsParams {
+            timeout: 20,
+            sq_psn: 99,
+            ..Default::default()
+        };
+        let (attr, mask) = params.to_qp_attr();
+        assert_eq!(attr.qp_state, ibv_qp_state::IBV_QPS_RTS);
+        assert_eq!(attr.timeout, 20);
+        assert_eq!(attr.retry_cnt, 7);
+        assert_eq!(attr.rnr_retry, 7);
+        assert_eq!(attr.sq_psn, 99);
+        assert_eq!(
+            mask & ibv_qp_attr_mask::IBV_QP_TIMEOUT,
+            ibv_qp_attr_mask::IBV_QP_TIMEOUT
+        );
+        assert_eq!(
+            mask & ibv_qp_attr_mask::IBV_QP_DEST_QPN,
+            ibv_qp_attr_mask(0)
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! # Queue pair types
+//!
+//! [`QpType`] names the transports a [`QueuePair`](crate::QueuePair) can be
+//! created with and converts to `ibv_qp_type` for
+//! [`QpInitAttrBuilder::qp_type`](crate::QpInitAttrBuilder::qp_type).
+
+use crate::{Error, ErrorKind, Result, ibv_qp_type};
+
+/// Transport type of a queue pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QpType {
+    /// Reliable connected: acknowledged, in-order, supports RDMA read,
+    /// write and atomics.
+    Rc,
+    /// Unreliable connected: no acknowledgements, supports sends and RDMA
+    /// writes.
+    Uc,
+    /// Unreliable datagram: connectionless sends of up to one MTU, addressed
+    /// per work request.
+    Ud,
+    /// Raw Ethernet frames; needs an Ethernet port and `CAP_NET_RAW`.
+    RawPacket,
+}
+
+impl QpType {
+    /// Returns the short name of this type.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Rc => "RC",
+            Self::Uc => "UC",
+            Self::Ud => "UD",
+            Self::RawPacket => "RAW_PACKET",
+        }
+    }
+}
+
+impl From<QpType> for ibv_qp_type {
+    fn from(qp_type: QpType) -> Self {
+        match qp_type {
+            QpType::Rc => ibv_qp_type::IBV_QPT_RC,
+            QpType::Uc => ibv_qp_type::IBV_QPT_UC,
+            QpType::Ud => ibv_qp_type::IBV_QPT_UD,
+            QpType::RawPacket => ibv_qp_type::IBV_QPT_RAW_PACKET,
+        }
+    }
+}
+
+impl TryFrom<ibv_qp_type> for QpType {
+    type Error = Error;
+
+    /// Converts a verbs QP type; XRC and driver-specific types have no
+    /// [`QpType`] and fail with [`ErrorKind::IBCreateQueuePairFail`].
+    fn try_from(qp_type: ibv_qp_type) -> Result<Self> {
+        match qp_type {
+            ibv_qp_type::IBV_QPT_RC => Ok(Self::Rc),
+            ibv_qp_type::IBV_QPT_UC => Ok(Self::Uc),
+            ibv_qp_type::IBV_QPT_UD => Ok(Self::Ud),
+            ibv_qp_type::IBV_QPT_RAW_PACKET => Ok(Self::RawPacket),
+            other => Err(Error::new(
+                ErrorKind::IBCreateQueuePairFail,
+                format!("unsupported QP type {other:?}"),
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for QpType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qp_type_conversion() {
+        let cases = [
+            (QpType::Rc, ibv_qp_type::IBV_QPT_RC),
+            (QpType::Uc, ibv_qp_type::IBV_QPT_UC),
+            (QpType::Ud, ibv_qp_type::IBV_QPT_UD),
+            (QpType::RawPacket, ibv_qp_type::IBV_QPT_RAW_PACKET),
+        ];
+        for (qp_type, raw) in cases {
+            assert_eq!(ibv_qp_type::from(qp_type), raw);
+            assert_eq!(QpType::try_from(raw).unwrap(), qp_type);
+        }
+        let err = QpType::try_from(ibv_qp_type::IBV_QPT_XRC_SEND).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::IBCreateQueuePairFail);
+        assert_eq!(QpType::RawPacket.to_string(), "RAW_PACKET");
+    }
+}
@@ -0,0 +1,512 @@
+//! # Queue pairs
+//!
+//! [`QueuePair`] wraps an `ibv_qp` created on a device's protection domain
+//! and records the capacities the provider actually granted.
+
+use std::{ops::Range, sync::Arc};
+
+use super::{
+    QpState,
+    modify::{modify_qp, zeroed_qp_attr},
+};
+use crate::{
+    Device, Error, ErrorKind, ProtectionDomainRef, RegisteredBuffer, Result, WCType, WRID,
+    ibv_access_flags, ibv_qp, ibv_qp_attr, ibv_qp_attr_mask, ibv_qp_init_attr, ibv_qp_type,
+    ibv_recv_wr,
+};
+
+/// Queue pair with automatic cleanup.
+///
+/// Holds a reference to its [`Device`] so the protection domain outlives the
+/// queue pair. The completion queues and SRQ it was created with are not
+/// owned and must outlive it.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// # use ruapc_rdma_sys::{Devices, QpInitAttrBuilder};
+/// # fn create(cq: *mut ruapc_rdma_sys::ibv_cq) -> ruapc_rdma_sys::Result<()> {
+/// let devices = Devices::available()?;
+/// let device = &devices[0];
+/// let init_attr = QpInitAttrBuilder::default()
+///     .cq(cq)
+///     .max_inline_data(64)
+///     .build(&device.info().device_attr)?;
+/// let qp = unsafe { device.create_qp(&init_attr)? };
+/// println!("QPN {} inline {}", qp.qp_num(), qp.max_inline_data());
+/// # Ok(())
+/// # }
+/// ```
+pub struct QueuePair {
+    qp: *mut ibv_qp,
+    max_inline_data: u32,
+    device: Arc<Device>,
+}
+
+unsafe impl Send for QueuePair {}
+unsafe impl Sync for QueuePair {}
+
+impl QueuePair {
+    /// Creates a queue pair and queries the granted inline capacity.
+    ///
+    /// # Safety
+    ///
+    /// The CQs and SRQ in `init_attr` must be valid and outlive the QP.
+    pub(crate) unsafe fn new(device: &Arc<Device>, init_attr: &ibv_qp_init_attr) -> Result<Self> {
+        let mut init_attr = *init_attr;
+        let qp = unsafe { crate::ibv_create_qp(device.pd_ptr(), &mut init_attr) };
+        if qp.is_null() {
+            return Err(ErrorKind::IBCreateQueuePairFail.with_errno());
+        }
+        let mut this = Self {
+            qp,
+            max_inline_data: 0,
+            device: device.clone(),
+        };
+
+        // The provider may round capacities up; query for the exact value
+        let (attr, _) = this.query(ibv_qp_attr_mask::IBV_QP_CAP)?;
+        this.max_inline_data = attr.cap.max_inline_data;
+        Ok(this)
+    }
+
+    /// Queries the current attributes of the queue pair.
+    ///
+    /// Only the fields selected by `mask` are guaranteed to be filled in the
+    /// returned `ibv_qp_attr`; the `ibv_qp_init_attr` reflects the creation
+    /// attributes with the granted capacities.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::IBQueryQueuePairFail`] if `ibv_query_qp` fails.
+    pub fn query(&self, mask: ibv_qp_attr_mask) -> Result<(ibv_qp_attr, ibv_qp_init_attr)> {
+        let mut attr = zeroed_qp_attr();
+        let mut init_attr = std::mem::MaybeUninit::<ibv_qp_init_attr>::zeroed();
+        let mut init_attr = unsafe {
+            std::ptr::addr_of_mut!((*init_attr.as_mut_ptr()).qp_type)
+                .write(ibv_qp_type::IBV_QPT_RC);
+            init_attr.assume_init()
+        };
+        let ret = unsafe { crate::ibv_query_qp(self.qp, &mut attr, mask.0 as _, &mut init_attr) };
+        if ret != 0 {
+            return Err(Error::from_errno(ErrorKind::IBQueryQueuePairFail, ret));
+        }
+        Ok((attr, init_attr))
+    }
+
+    /// Returns the current state of the queue pair.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::IBQueryQueuePairFail`] if `ibv_query_qp` fails.
+    pub fn state(&self) -> Result<QpState> {
+        let (attr, _) = self.query(ibv_qp_attr_mask::IBV_QP_STATE)?;
+        Ok(attr.qp_state.into())
+    }
+
+    /// Returns the device this queue pair belongs to.
+    pub fn device(&self) -> &Arc<Device> {
+        &self.device
+    }
+
+    /// Returns the protection domain the queue pair was created in.
+    pub fn protection_domain(&self) -> ProtectionDomainRef {
+        self.device.protection_domain()
+    }
+
+    /// Returns the queue pair number.
+    pub fn qp_num(&self) -> u32 {
+        unsafe { (*self.qp).qp_num }
+    }
+
+    /// Returns the maximum inline data size granted by the provider.
+    ///
+    /// Sends with a total payload up to this size may set
+    /// `IBV_SEND_INLINE`. It can exceed the requested value.
+    pub fn max_inline_data(&self) -> u32 {
+        self.max_inline_data
+    }
+
+    /// Replaces the remote access flags of the queue pair in place.
+    ///
+    /// Enables or disables incoming RDMA reads, writes and atomics on an
+    /// already connected QP without another state transition. The flags
+    /// fully replace the current ones, so include every access that should
+    /// stay enabled.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::IBModifyQueuePairFail`] if the QP is not in INIT,
+    /// RTR or RTS, or if `ibv_modify_qp` rejects the change.
+    pub fn set_access_flags(&self, flags: ibv_access_flags) -> Result<()> {
+        check_access_flags_state(self.state()?)?;
+        let mut attr = zeroed_qp_attr();
+        attr.qp_access_flags = flags.0;
+        let mask = ibv_qp_attr_mask::IBV_QP_ACCESS_FLAGS;
+        let ret = unsafe { crate::ibv_modify_qp(self.qp, &mut attr, mask.0 as _) };
+        if ret != 0 {
+            let mut err = Error::from_errno(ErrorKind::IBModifyQueuePairFail, ret);
+            err.msg = format!("set access flags {:#x}: {}", flags.0, err.msg);
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Changes the InfiniBand service level of the primary path.
+    ///
+    /// The address vector can only be modified in the INIT→RTR transition
+    /// and in SQD, so pick the SL at connection time with
+    /// [`RtrParams::service_level`](crate::RtrParams::service_level) and use
+    /// this to move a drained QP to another traffic class. The rest of the
+    /// current address vector is kept. The SL is ignored on RoCE.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::IBModifyQueuePairFail`] if `sl` exceeds 15 or
+    /// the QP is not in SQD, or an error classified by
+    /// [`ErrorKind::from_modify_qp_errno`] if `ibv_modify_qp` fails.
+    pub fn set_service_level(&self, sl: u8) -> Result<()> {
+        check_service_level(sl, self.state()?)?;
+        let mask = ibv_qp_attr_mask::IBV_QP_AV;
+        let (mut attr, _) = self.query(mask)?;
+        attr.ah_attr.sl = sl;
+        unsafe { modify_qp(self.qp, &mut attr, mask) }
+    }
+
+    /// Returns the minimum RNR NAK timer, encoded per the IB specification.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::IBQueryQueuePairFail`] if `ibv_query_qp` fails.
+    pub fn min_rnr_timer(&self) -> Result<u8> {
+        let (attr, _) = self.query(ibv_qp_attr_mask::IBV_QP_MIN_RNR_TIMER)?;
+        Ok(attr.min_rnr_timer)
+    }
+
+    /// Changes the minimum RNR NAK timer of a connected queue pair.
+    ///
+    /// This is how long a sender is told to back off when a receive finds
+    /// no posted buffer; `timer` uses the IB encoding of
+    /// [`RtrParams::min_rnr_timer`](crate::RtrParams::min_rnr_timer), where
+    /// 0 means 655.36ms and 1 to 31 range from 0.01ms to 491.52ms.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::IBModifyQueuePairFail`] if `timer` exceeds 31 or
+    /// the QP is not in RTS or SQD, or an error classified by
+    /// [`ErrorKind::from_modify_qp_errno`] if `ibv_modify_qp` fails.
+    pub fn set_min_rnr_timer(&self, timer: u8) -> Result<()> {
+        check_min_rnr_timer(timer, self.state()?)?;
+        let mut attr = zeroed_qp_attr();
+        attr.min_rnr_timer = timer;
+        unsafe { modify_qp(self.qp, &mut attr, ibv_qp_attr_mask::IBV_QP_MIN_RNR_TIMER) }
+    }
+
+    /// Pre-posts `count` receives, each covering one `chunk_size` slice of `buf`.
+    ///
+    /// Receive `i` points at bytes `i * chunk_size..(i + 1) * chunk_size` and
+    /// carries [`WRID::recv(base_wrid + i)`](WRID::recv), so a completion's
+    /// id locates its chunk. All requests go out in one `ibv_post_recv` call.
+    ///
+    /// `buf` must stay alive until the receives complete; if it is dropped
+    /// first, its region is deregistered and they complete in error.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidArgument`] if `chunk_size * count`
+    /// exceeds `buf.len()` or a chunk exceeds `u32::MAX` bytes,
+    /// [`ErrorKind::WRIDOverflow`] if `base_wrid + count - 1` does not fit a
+    /// WRID, or the classified error of `ibv_post_recv`.
+    pub fn post_recv_ring(
+        &self,
+        buf: &RegisteredBuffer,
+        chunk_size: usize,
+        count: usize,
+        base_wrid: u64,
+    ) -> Result<()> {
+        let ranges = ring_chunks(buf.len(), chunk_size, count)?;
+        let mut sges: Vec<_> = ranges.map(|range| buf.sge(range)).collect();
+        let mut wrs = Vec::with_capacity(count);
+        for (i, sge) in sges.iter_mut().enumerate() {
+            wrs.push(ibv_recv_wr {
+                wr_id: WRID::try_new(WCType::Recv, base_wrid + i as u64)?,
+                sg_list: sge,
+                num_sge: 1,
+                ..Default::default()
+            });
+        }
+        // Chain only after the vector stops growing, so the pointers stay put
+        for i in 1..wrs.len() {
+            let next: *mut ibv_recv_wr = &mut wrs[i];
+            wrs[i - 1].next = next;
+        }
+        let Some(first) = wrs.first_mut() else {
+            return Ok(());
+        };
+
+        let mut bad_wr = std::ptr::null_mut();
+        let ret = unsafe { crate::ibv_post_recv(self.qp, first, &mut bad_wr) };
+        if ret != 0 {
+            return Err(Error::from_errno(
+                unsafe { crate::ffi::post_recv_error_kind(self.qp, ret) },
+                ret,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Limits the send rate of the queue pair to `kbps` kilobits per second.
+    ///
+    /// A rate of 0 removes the limit. Requires packet pacing support for the
+    /// QP's transport type, as reported by `packet_pacing_caps`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::Unsupported`] if the device lacks packet pacing
+    /// for this QP type, or [`ErrorKind::IBModifyQueuePairFail`] if `kbps` is
+    /// outside the supported range or `ibv_modify_qp_rate_limit` fails.
+    pub fn set_rate_limit(&self, kbps: u32) -> Result<()> {
+        let caps = self.device.query_device_ex()?.packet_pacing_caps;
+        let qp_type = unsafe { (*self.qp).qp_type };
+        if caps.qp_rate_limit_max == 0 || caps.supported_qpts & (1 << qp_type as u32) == 0 {
+            return Err(Error::new(
+                ErrorKind::Unsupported("packet pacing".to_string()),
+                format!("device does not support packet pacing for {qp_type:?}"),
+            ));
+        }
+        if kbps != 0 && !(caps.qp_rate_limit_min..=caps.qp_rate_limit_max).contains(&kbps) {
+            return Err(Error::new(
+                ErrorKind::IBModifyQueuePairFail,
+                format!(
+                    "rate limit {kbps} kbps outside supported range {}..={} kbps",
+                    caps.qp_rate_limit_min, caps.qp_rate_limit_max
+                ),
+            ));
+        }
+
+        let mut attr = crate::ibv_qp_rate_limit_attr {
+            rate_limit: kbps,
+            ..Default::default()
+        };
+        let ret = unsafe { crate::ibv_modify_qp_rate_limit(self.qp, &mut attr) };
+        if ret != 0 {
+            return Err(Error::from_errno(ErrorKind::IBModifyQueuePairFail, ret));
+        }
+        Ok(())
+    }
+
+    /// Returns the raw queue pair pointer.
+    ///
+    /// # Safety
+    ///
+    /// The returned pointer is only valid as long as this `QueuePair` exists.
+    pub unsafe fn qp_ptr(&self) -> *mut ibv_qp {
+        self.qp
+    }
+}
+
+/// Checks that the access flags of a QP in `state` can be modified.
+fn check_access_flags_state(state: QpState) -> Result<()> {
+    match state {
+        QpState::Init | QpState::Rtr | QpState::Rts => Ok(()),
+        state => Err(Error::new(
+            ErrorKind::IBModifyQueuePairFail,
+            format!("cannot modify access flags in state {state}, expected INIT, RTR or RTS"),
+        )),
+    }
+}
+
+/// Highest InfiniBand service level; the SL field is 4 bits wide.
+const MAX_SERVICE_LEVEL: u8 = 15;
+
+/// Checks that the service level of a QP in `state` can be set to `sl`.
+fn check_service_level(sl: u8, state: QpState) -> Result<()> {
+    if sl > MAX_SERVICE_LEVEL {
+        return Err(Error::new(
+            ErrorKind::IBModifyQueuePairFail,
+            format!("service level {sl} exceeds {MAX_SERVICE_LEVEL}"),
+        ));
+    }
+    match state {
+        QpState::Sqd => Ok(()),
+        state => Err(Error::new(
+            ErrorKind::IBModifyQueuePairFail,
+            format!("cannot modify the address vector in state {state}, expected SQD"),
+        )),
+    }
+}
+
+/// Highest encoded minimum RNR NAK timer; the field is 5 bits wide.
+const MAX_MIN_RNR_TIMER: u8 = 31;
+
+/// Checks that the minimum RNR NAK timer of a QP in `state` can be set to
+/// `timer`.
+fn check_min_rnr_timer(timer: u8, state: QpState) -> Result<()> {
+    if timer > MAX_MIN_RNR_TIMER {
+        return Err(Error::new(
+            ErrorKind::IBModifyQueuePairFail,
+            format!("min RNR timer {timer} exceeds {MAX_MIN_RNR_TIMER}"),
+        ));
+    }
+    match state {
+        QpState::Rts | QpState::Sqd => Ok(()),
+        state => Err(Error::new(
+            ErrorKind::IBModifyQueuePairFail,
+            format!("cannot modify the min RNR timer in state {state}, expected RTS or SQD"),
+        )),
+    }
+}
+
+/// Splits the first `chunk_size * count` bytes of a `len`-byte buffer into
+/// `count` consecutive chunks.
+fn ring_chunks(
+    len: usize,
+    chunk_size: usize,
+    count: usize,
+) -> Result<impl Iterator<Item = Range<usize>>> {
+    if chunk_size > u32::MAX as usize {
+        return Err(Error::new(
+            ErrorKind::InvalidArgument,
+            format!("chunk of {chunk_size} bytes exceeds the u32 SGE length"),
+        ));
+    }
+    match chunk_size.checked_mul(count) {
+        Some(total) if total <= len => {}
+        _ => {
+            return Err(Error::new(
+                ErrorKind::InvalidArgument,
+                format!("{count} chunks of {chunk_size} bytes exceed buffer of {len} bytes"),
+            ));
+        }
+    }
+    Ok((0..count).map(move |i| i * chunk_size..(i + 1) * chunk_size))
+}
+
+impl Drop for QueuePair {
+    fn drop(&mut self) {
+        crate::check_cleanup("ibv_destroy_qp", unsafe { crate::ibv_destroy_qp(self.qp) });
+    }
+}
+
+impl std::fmt::Debug for QueuePair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueuePair")
+            .field("qp_num", &self.qp_num())
+            .field("max_inline_data", &self.max_inline_data)
+            .field("device", &self.device.info().name)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_access_flags_state, check_min_rnr_timer, check_service_level, ring_chunks};
+    use crate::{Devices, ErrorKind, QpInitAttrBuilder, QpState, WRID, ibv_access_flags};
+
+    #[test]
+    fn test_ring_chunks() {
+        let chunks: Vec<_> = ring_chunks(100, 32, 3).unwrap().collect();
+        assert_eq!(chunks, [0..32, 32..64, 64..96]);
+        assert_eq!(ring_chunks(96, 32, 3).unwrap().count(), 3);
+        assert_eq!(ring_chunks(0, 32, 0).unwrap().count(), 0);
+
+        for (len, chunk_size, count) in [(95, 32, 3), (0, 1, 1), (usize::MAX, 1 << 32, 2)] {
+            let err = ring_chunks(len, chunk_size, count).err().unwrap();
+            assert_eq!(err.kind, ErrorKind::InvalidArgument);
+        }
+        let err = ring_chunks(usize::MAX, 1 << 20, usize::MAX).err().unwrap();
+        assert_eq!(err.kind, ErrorKind::InvalidArgument);
+    }
+
+    #[test]
+    fn test_check_access_flags_state() {
+        for state in [QpState::Init, QpState::Rtr, QpState::Rts] {
+            check_access_flags_state(state).unwrap();
+        }
+        for state in [QpState::Reset, QpState::Sqd, QpState::Err, QpState::Unknown] {
+            let err = check_access_flags_state(state).unwrap_err();
+            assert_eq!(err.kind, ErrorKind::IBModifyQueuePairFail);
+            assert!(err.msg.contains(state.as_str()), "{}", err.msg);
+        }
+    }
+
+    #[test]
+    fn test_check_service_level() {
+        check_service_level(0, QpState::Sqd).unwrap();
+        check_service_level(15, QpState::Sqd).unwrap();
+        let err = check_service_level(16, QpState::Sqd).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::IBModifyQueuePairFail);
+        assert!(err.msg.contains("16"), "{}", err.msg);
+        for state in [QpState::Reset, QpState::Init, QpState::Rtr, QpState::Rts] {
+            let err = check_service_level(1, state).unwrap_err();
+            assert!(err.msg.contains(state.as_str()), "{}", err.msg);
+        }
+    }
+
+    #[test]
+    fn test_check_min_rnr_timer() {
+        for state in [QpState::Rts, QpState::Sqd] {
+            check_min_rnr_timer(0, state).unwrap();
+            check_min_rnr_timer(31, state).unwrap();
+        }
+        let err = check_min_rnr_timer(32, QpState::Rts).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::IBModifyQueuePairFail);
+        assert!(err.msg.contains("32"), "{}", err.msg);
+        for state in [QpState::Reset, QpState::Init, QpState::Rtr, QpState::Err] {
+            let err = check_min_rnr_timer(12, state).unwrap_err();
+            assert!(err.msg.contains(state.as_str()), "{}", err.msg);
+        }
+    }
+
+    #[test]
+    fn create_qp_max_inline_data() {
+        let devices = Devices::available().unwrap();
+        let device = &devices[0];
+        let cq = unsafe {
+            crate::ibv_create_cq(
+                device.context_ptr(),
+                16,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        assert!(!cq.is_null());
+
+        let init_attr = QpInitAttrBuilder::default()
+            .cq(cq)
+            .max_inline_data(32)
+            .build(&device.info().device_attr)
+            .unwrap();
+        let qp = unsafe { device.create_qp(&init_attr) }.unwrap();
+        assert!(qp.max_inline_data() >= 32);
+        assert_eq!(qp.state().unwrap(), QpState::Reset);
+        let remote_write = ibv_access_flags::IBV_ACCESS_REMOTE_WRITE;
+        let err = qp.set_access_flags(remote_write).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::IBModifyQueuePairFail);
+        let port_num = device.info().ports[0].port_num;
+        unsafe { crate::modify_to_init(qp.qp_ptr(), port_num, remote_write) }.unwrap();
+        qp.set_access_flags(remote_write | ibv_access_flags::IBV_ACCESS_REMOTE_READ)
+            .unwrap();
+
+        let buf = device
+            .register_memory(vec![0u8; 64], ibv_access_flags::IBV_ACCESS_LOCAL_WRITE)
+            .unwrap();
+        let err = qp.post_recv_ring(&buf, 16, 5, 0).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidArgument);
+        let err = qp
+            .post_recv_ring(&buf, 16, 2, !WRID::TYPE_MASK)
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::WRIDOverflow);
+        qp.post_recv_ring(&buf, 16, 4, 100).unwrap();
+
+        let pacing = device.query_device_ex().unwrap().packet_pacing_caps;
+        if pacing.qp_rate_limit_max == 0 {
+            let err = qp.set_rate_limit(1_000_000).unwrap_err();
+            assert!(err.kind.is_unsupported(), "{err}");
+        }
+        drop(qp);
+
+        assert_eq!(unsafe { crate::ibv_destroy_cq(cq) }, 0);
+    }
+}
@@ -0,0 +1,81 @@
+//! # Queue pair states
+//!
+//! [`QpState`] names the `ibv_qp_state` values for diagnostics and
+//! assertions on connection progress.
+
+use crate::ibv_qp_state;
+
+/// State of a queue pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QpState {
+    /// Newly created or reset; no work requests are processed.
+    Reset,
+    /// Initialized; receive requests may be posted.
+    Init,
+    /// Ready to receive.
+    Rtr,
+    /// Ready to send; the connection is fully established.
+    Rts,
+    /// Send queue drained.
+    Sqd,
+    /// Send queue error (unreliable transports only).
+    Sqe,
+    /// Error; outstanding work requests complete with flush errors.
+    Err,
+    /// State reported by the provider is unknown.
+    Unknown,
+}
+
+impl QpState {
+    /// Returns the short name of this state.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Reset => "RESET",
+            Self::Init => "INIT",
+            Self::Rtr => "RTR",
+            Self::Rts => "RTS",
+            Self::Sqd => "SQD",
+            Self::Sqe => "SQE",
+            Self::Err => "ERR",
+            Self::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+impl From<ibv_qp_state> for QpState {
+    fn from(state: ibv_qp_state) -> Self {
+        match state {
+            ibv_qp_state::IBV_QPS_RESET => Self::Reset,
+            ibv_qp_state::IBV_QPS_INIT => Self::Init,
+            ibv_qp_state::IBV_QPS_RTR => Self::Rtr,
+            ibv_qp_state::IBV_QPS_RTS => Self::Rts,
+            ibv_qp_state::IBV_QPS_SQD => Self::Sqd,
+            ibv_qp_state::IBV_QPS_SQE => Self::Sqe,
+            ibv_qp_state::IBV_QPS_ERR => Self::Err,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+impl std::fmt::Display for QpState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qp_state_from() {
+        assert_eq!(QpState::from(ibv_qp_state::IBV_QPS_RESET), QpState::Reset);
+        assert_eq!(QpState::from(ibv_qp_state::IBV_QPS_RTS), QpState::Rts);
+        assert_eq!(QpState::from(ibv_qp_state::IBV_QPS_ERR), QpState::Err);
+        assert_eq!(
+            QpState::from(ibv_qp_state::IBV_QPS_UNKNOWN),
+            QpState::Unknown
+        );
+        assert_eq!(QpState::Rtr.to_string(), "RTR");
+    }
+}
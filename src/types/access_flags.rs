@@ -0,0 +1,53 @@
+//! Memory region access flag presets
+//!
+//! `ibv_reg_mr` rejects or silently under-grants some flag combinations, so
+//! the presets on `ibv_access_flags` encode the minimal mask for the common
+//! roles of a buffer. Combine them with `|` when a buffer plays several
+//! roles.
+
+pub use crate::ibv_access_flags;
+
+impl ibv_access_flags {
+    /// Access for a buffer posted as a receive.
+    ///
+    /// The local HCA writes incoming sends into receive buffers, which needs
+    /// `LOCAL_WRITE` even though no remote peer addresses the region. A
+    /// receive into a region without it completes with a local protection
+    /// error.
+    pub const fn for_recv() -> Self {
+        Self::IBV_ACCESS_LOCAL_WRITE
+    }
+
+    /// Access for a buffer that remote peers RDMA write into.
+    ///
+    /// Adds `REMOTE_WRITE`; the verbs spec requires `LOCAL_WRITE` alongside
+    /// any remote write or atomic access, and `ibv_reg_mr` fails with
+    /// `EINVAL` without it.
+    pub const fn for_rdma_write_target() -> Self {
+        Self(Self::IBV_ACCESS_LOCAL_WRITE.0 | Self::IBV_ACCESS_REMOTE_WRITE.0)
+    }
+
+    /// Access for a buffer that remote peers RDMA read from.
+    ///
+    /// Only `REMOTE_READ`: the local HCA reads the region to serve the
+    /// request and never writes it, so `LOCAL_WRITE` is not needed.
+    pub const fn for_rdma_read_source() -> Self {
+        Self::IBV_ACCESS_REMOTE_READ
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_access_flag_presets() {
+        assert_eq!(ibv_access_flags::for_recv().0, 0x1);
+        assert_eq!(ibv_access_flags::for_rdma_write_target().0, 0x3);
+        assert_eq!(ibv_access_flags::for_rdma_read_source().0, 0x4);
+        assert_eq!(
+            ibv_access_flags::for_rdma_write_target(),
+            ibv_access_flags::IBV_ACCESS_LOCAL_WRITE | ibv_access_flags::IBV_ACCESS_REMOTE_WRITE
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! Device capability flags with named serialization
+//!
+//! `ibv_device_cap_flags` is a bitfield; this module serializes it as the
+//! list of set flag names (e.g. `["RESIZE_MAX_WR", "BAD_PKEY_CNTR"]`) so
+//! device capabilities are readable in JSON output.
+
+use super::named_flags::named_flags;
+use crate::ibv_device_cap_flags;
+
+/// Flag names without the `IBV_DEVICE_` prefix, in bit order.
+const FLAG_NAMES: &[(&str, ibv_device_cap_flags)] = &[
+    (
+        "RESIZE_MAX_WR",
+        ibv_device_cap_flags::IBV_DEVICE_RESIZE_MAX_WR,
+    ),
+    (
+        "BAD_PKEY_CNTR",
+        ibv_device_cap_flags::IBV_DEVICE_BAD_PKEY_CNTR,
+    ),
+    (
+        "BAD_QKEY_CNTR",
+        ibv_device_cap_flags::IBV_DEVICE_BAD_QKEY_CNTR,
+    ),
+    ("RAW_MULTI", ibv_device_cap_flags::IBV_DEVICE_RAW_MULTI),
+    (
+        "AUTO_PATH_MIG",
+        ibv_device_cap_flags::IBV_DEVICE_AUTO_PATH_MIG,
+    ),
+    (
+        "CHANGE_PHY_PORT",
+        ibv_device_cap_flags::IBV_DEVICE_CHANGE_PHY_PORT,
+    ),
+    (
+        "UD_AV_PORT_ENFORCE",
+        ibv_device_cap_flags::IBV_DEVICE_UD_AV_PORT_ENFORCE,
+    ),
+    (
+        "CURR_QP_STATE_MOD",
+        ibv_device_cap_flags::IBV_DEVICE_CURR_QP_STATE_MOD,
+    ),
+    (
+        "SHUTDOWN_PORT",
+        ibv_device_cap_flags::IBV_DEVICE_SHUTDOWN_PORT,
+    ),
+    ("INIT_TYPE", ibv_device_cap_flags::IBV_DEVICE_INIT_TYPE),
+    (
+        "PORT_ACTIVE_EVENT",
+        ibv_device_cap_flags::IBV_DEVICE_PORT_ACTIVE_EVENT,
+    ),
+    (
+        "SYS_IMAGE_GUID",
+        ibv_device_cap_flags::IBV_DEVICE_SYS_IMAGE_GUID,
+    ),
+    (
+        "RC_RNR_NAK_GEN",
+        ibv_device_cap_flags::IBV_DEVICE_RC_RNR_NAK_GEN,
+    ),
+    ("SRQ_RESIZE", ibv_device_cap_flags::IBV_DEVICE_SRQ_RESIZE),
+    ("N_NOTIFY_CQ", ibv_device_cap_flags::IBV_DEVICE_N_NOTIFY_CQ),
+    ("MEM_WINDOW", ibv_device_cap_flags::IBV_DEVICE_MEM_WINDOW),
+    ("UD_IP_CSUM", ibv_device_cap_flags::IBV_DEVICE_UD_IP_CSUM),
+    ("XRC", ibv_device_cap_flags::IBV_DEVICE_XRC),
+    (
+        "MEM_MGT_EXTENSIONS",
+        ibv_device_cap_flags::IBV_DEVICE_MEM_MGT_EXTENSIONS,
+    ),
+    (
+        "MEM_WINDOW_TYPE_2A",
+        ibv_device_cap_flags::IBV_DEVICE_MEM_WINDOW_TYPE_2A,
+    ),
+    (
+        "MEM_WINDOW_TYPE_2B",
+        ibv_device_cap_flags::IBV_DEVICE_MEM_WINDOW_TYPE_2B,
+    ),
+    ("RC_IP_CSUM", ibv_device_cap_flags::IBV_DEVICE_RC_IP_CSUM),
+    ("RAW_IP_CSUM", ibv_device_cap_flags::IBV_DEVICE_RAW_IP_CSUM),
+    (
+        "MANAGED_FLOW_STEERING",
+        ibv_device_cap_flags::IBV_DEVICE_MANAGED_FLOW_STEERING,
+    ),
+];
+
+named_flags!(
+    ibv_device_cap_flags,
+    FLAG_NAMES,
+    "DeviceCapFlags",
+    "device capability"
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_cap_flags_names() {
+        let flags = ibv_device_cap_flags::IBV_DEVICE_RESIZE_MAX_WR
+            | ibv_device_cap_flags::IBV_DEVICE_BAD_PKEY_CNTR;
+        assert_eq!(flags.names(), vec!["RESIZE_MAX_WR", "BAD_PKEY_CNTR"]);
+        assert_eq!(flags.unknown_bits(), 0);
+    }
+
+    #[test]
+    fn test_device_cap_flags_serialize() {
+        let flags =
+            ibv_device_cap_flags::IBV_DEVICE_RESIZE_MAX_WR | ibv_device_cap_flags::IBV_DEVICE_XRC;
+        let json = serde_json::to_string(&flags).unwrap();
+        assert_eq!(json, r#"["RESIZE_MAX_WR","XRC"]"#);
+
+        let empty = serde_json::to_string(&ibv_device_cap_flags(0)).unwrap();
+        assert_eq!(empty, "[]");
+    }
+
+    #[test]
+    fn test_device_cap_flags_unknown_bits_roundtrip() {
+        let flags = ibv_device_cap_flags::IBV_DEVICE_MEM_WINDOW | ibv_device_cap_flags(1 << 31);
+        let json = serde_json::to_string(&flags).unwrap();
+        assert_eq!(json, r#"["MEM_WINDOW","0x80000000"]"#);
+        let der: ibv_device_cap_flags = serde_json::from_str(&json).unwrap();
+        assert_eq!(der, flags);
+    }
+
+    #[test]
+    fn test_device_cap_flags_deserialize() {
+        let flags: ibv_device_cap_flags =
+            serde_json::from_str(r#"["RC_RNR_NAK_GEN","MEM_WINDOW"]"#).unwrap();
+        assert_eq!(
+            flags,
+            ibv_device_cap_flags::IBV_DEVICE_RC_RNR_NAK_GEN
+                | ibv_device_cap_flags::IBV_DEVICE_MEM_WINDOW
+        );
+
+        let flags: ibv_device_cap_flags = serde_json::from_str("4097").unwrap();
+        assert_eq!(
+            flags,
+            ibv_device_cap_flags::IBV_DEVICE_RESIZE_MAX_WR
+                | ibv_device_cap_flags::IBV_DEVICE_RC_RNR_NAK_GEN
+        );
+
+        let result: Result<ibv_device_cap_flags, _> = serde_json::from_str(r#"["NOT_A_FLAG"]"#);
+        assert!(result.is_err());
+    }
+}
@@ -56,6 +56,7 @@ impl JsonSchema for FwVer {
     fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
         json_schema!({
             "type": "string",
+            "maxLength": 64,
         })
     }
 }
@@ -114,6 +115,20 @@ mod tests {
         assert_eq!(format!("{}", fw), original_str);
     }
 
+    #[test]
+    fn test_fw_ver_schema_length() {
+        let schema = serde_json::to_value(schemars::schema_for!(FwVer)).unwrap();
+        assert_eq!(schema["type"], "string");
+        let max_len = schema["maxLength"].as_u64().unwrap() as usize;
+        let long: FwVer = serde_json::from_value("9".repeat(100).into()).unwrap();
+        let short: FwVer = serde_json::from_value("20.28.1042".into()).unwrap();
+        // A buffer without a NUL terminator displays all 64 bytes
+        for fw in [FwVer([0; 64]), short, long, FwVer([b'9'; 64])] {
+            let json = serde_json::to_value(fw).unwrap();
+            assert!(json.as_str().unwrap().len() <= max_len, "{json}");
+        }
+    }
+
     #[test]
     fn test_fw_ver_deserialize_truncates() {
         let json = "\"abcdefghijklmnopqrstuvwxyz1234567890ABCDEFGHIJKLMNOPQRSTUVWXYZ12345678901234567890123\"";
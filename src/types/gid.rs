@@ -39,6 +39,66 @@ impl ibv_gid {
     pub fn is_null(&self) -> bool {
         self.interface_id() == 0
     }
+
+    /// Checks if the GID is a link-local unicast address (`fe80::/10`)
+    pub fn is_link_local(&self) -> bool {
+        self.as_ipv6().is_unicast_link_local()
+    }
+
+    /// Checks if the GID is a globally routable unicast address
+    ///
+    /// Excludes multicast, loopback, unspecified, link-local and unique local
+    /// (`fc00::/7`) addresses. IPv4-mapped GIDs count as global.
+    pub fn is_global_unicast(&self) -> bool {
+        let ip = self.as_ipv6();
+        !(ip.is_multicast()
+            || ip.is_loopback()
+            || ip.is_unspecified()
+            || ip.is_unicast_link_local()
+            || ip.is_unique_local())
+    }
+
+    /// Checks if the GID is an IPv4-mapped address (`::ffff:a.b.c.d`)
+    pub fn is_ipv4_mapped(&self) -> bool {
+        self.as_ipv6().to_ipv4_mapped().is_some()
+    }
+
+    /// Returns the GID as eight colon-separated groups of four hex digits
+    ///
+    /// Unlike the IPv6 display form, zeros are not compressed, e.g.
+    /// `fe80:0000:0000:0000:0000:0000:0000:0001`. Deserialization accepts
+    /// this form back.
+    pub fn to_full_string(&self) -> String {
+        self.as_ipv6()
+            .segments()
+            .iter()
+            .map(|segment| format!("{segment:04x}"))
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+
+    /// Parses the raw 16-byte form written as 32 hex digits
+    ///
+    /// Accepts either a contiguous string or eight colon-separated groups of
+    /// exactly four digits (e.g. `fe80:0000:0000:0000:0000:0000:0000:0001`).
+    fn parse_hex(s: &str) -> Option<Self> {
+        let digits: String = if s.len() == 39 {
+            let groups: Vec<_> = s.split(':').collect();
+            if groups.len() != 8 || groups.iter().any(|g| g.len() != 4) {
+                return None;
+            }
+            groups.concat()
+        } else {
+            s.to_string()
+        };
+        if digits.len() != 32 || !digits.bytes().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let bits = u128::from_str_radix(&digits, 16).ok()?;
+        Some(Self {
+            raw: bits.to_be_bytes(),
+        })
+    }
 }
 
 impl std::fmt::Debug for ibv_gid {
@@ -47,6 +107,20 @@ impl std::fmt::Debug for ibv_gid {
     }
 }
 
+impl PartialEq for ibv_gid {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_raw() == other.as_raw()
+    }
+}
+
+impl Eq for ibv_gid {}
+
+impl std::hash::Hash for ibv_gid {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.as_raw().hash(state);
+    }
+}
+
 impl Serialize for ibv_gid {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -63,6 +137,9 @@ impl<'de> Deserialize<'de> for ibv_gid {
     {
         use serde::de::Error;
         let s = String::deserialize(deserializer)?;
+        if let Some(gid) = ibv_gid::parse_hex(&s) {
+            return Ok(gid);
+        }
         let addr = s
             .parse::<Ipv6Addr>()
             .map_err(|_| D::Error::custom("invalid IPv6 address format"))?;
@@ -80,10 +157,141 @@ impl JsonSchema for ibv_gid {
     }
 
     fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        // GIDs serialize in compressed form (`fe80::1`, `::ffff:10.0.0.1`),
+        // which the standard format describes and a short pattern cannot
         json_schema!({
             "type": "string",
-            "pattern": "^(?:[0-9a-fA-F]{1,4}:){7}[0-9a-fA-F]{1,4}$",
+            "format": "ipv6",
             "description": "IPv6 address format GID"
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> ibv_gid {
+        serde_json::from_value(serde_json::Value::String(s.to_string())).unwrap()
+    }
+
+    #[test]
+    fn test_gid_schema_format() {
+        let schema = serde_json::to_value(schemars::schema_for!(ibv_gid)).unwrap();
+        assert_eq!(schema["format"], "ipv6");
+        assert!(schema.get("pattern").is_none(), "{schema}");
+        for s in [
+            "::",
+            "fe80::1",
+            "fe80::5054:ff:fe12:3456",
+            "::ffff:192.168.1.10",
+            "2001:db8:1:2:3:4:5:6",
+        ] {
+            let json = serde_json::to_value(parse(s)).unwrap();
+            let text = json.as_str().unwrap();
+            assert_eq!(text, s);
+            assert!(text.parse::<Ipv6Addr>().is_ok(), "{json}");
+        }
+    }
+
+    #[test]
+    fn test_gid_deserialize_ipv6() {
+        let gid = parse("fe80::1");
+        assert_eq!(gid.subnet_prefix(), 0xfe80_0000_0000_0000);
+        assert_eq!(gid.interface_id(), 1);
+    }
+
+    #[test]
+    fn test_gid_deserialize_hex() {
+        let expected = parse("fe80::5054:ff:fe12:3456");
+        let gid = parse("fe80000000000000505400fffe123456");
+        assert_eq!(gid.as_raw(), expected.as_raw());
+
+        let gid = parse("FE80000000000000505400FFFE123456");
+        assert_eq!(gid.as_raw(), expected.as_raw());
+
+        let gid = parse("fe80:0000:0000:0000:5054:00ff:fe12:3456");
+        assert_eq!(gid.as_raw(), expected.as_raw());
+    }
+
+    #[test]
+    fn test_gid_deserialize_ipv4_mapped() {
+        let expected = parse("::ffff:192.168.1.1");
+        let gid = parse("00000000000000000000ffffc0a80101");
+        assert_eq!(gid.as_raw(), expected.as_raw());
+    }
+
+    #[test]
+    fn test_gid_deserialize_invalid() {
+        for s in [
+            "fe80000000000000505400fffe12345",
+            "fe80000000000000505400fffe1234567",
+            "ge80000000000000505400fffe123456",
+            "not a gid",
+        ] {
+            let result: Result<ibv_gid, _> =
+                serde_json::from_value(serde_json::Value::String(s.to_string()));
+            assert!(result.is_err(), "{s} should be rejected");
+        }
+    }
+
+    #[test]
+    fn test_gid_to_full_string() {
+        let gid = parse("fe80::1");
+        assert_eq!(gid.as_ipv6().to_string(), "fe80::1");
+        assert_eq!(
+            gid.to_full_string(),
+            "fe80:0000:0000:0000:0000:0000:0000:0001"
+        );
+        assert_eq!(parse(&gid.to_full_string()).as_raw(), gid.as_raw());
+
+        let gid = parse("::ffff:192.168.1.1");
+        assert_eq!(
+            gid.to_full_string(),
+            "0000:0000:0000:0000:0000:ffff:c0a8:0101"
+        );
+    }
+
+    #[test]
+    fn test_gid_classification() {
+        let link_local = parse("fe80::5054:ff:fe12:3456");
+        assert!(link_local.is_link_local());
+        assert!(!link_local.is_global_unicast());
+        assert!(!link_local.is_ipv4_mapped());
+
+        let global = parse("2001:db8::1");
+        assert!(!global.is_link_local());
+        assert!(global.is_global_unicast());
+        assert!(!global.is_ipv4_mapped());
+
+        let mapped = parse("::ffff:192.168.1.1");
+        assert!(!mapped.is_link_local());
+        assert!(mapped.is_global_unicast());
+        assert!(mapped.is_ipv4_mapped());
+
+        for s in ["fd00::1", "ff02::1", "::1", "::"] {
+            let gid = parse(s);
+            assert!(!gid.is_link_local(), "{s}");
+            assert!(!gid.is_global_unicast(), "{s}");
+            assert!(!gid.is_ipv4_mapped(), "{s}");
+        }
+    }
+
+    #[test]
+    fn test_gid_eq() {
+        assert_eq!(parse("fe80::1"), parse("fe800000000000000000000000000001"));
+        assert_ne!(parse("fe80::1"), parse("fe80::2"));
+        let set: std::collections::HashSet<_> =
+            [parse("fe80::1"), parse("fe80::1"), parse("::1")].into();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn test_gid_serialize_roundtrip() {
+        let gid = parse("fe80000000000000505400fffe123456");
+        let json = serde_json::to_string(&gid).unwrap();
+        assert_eq!(json, "\"fe80::5054:ff:fe12:3456\"");
+        let der: ibv_gid = serde_json::from_str(&json).unwrap();
+        assert_eq!(der.as_raw(), gid.as_raw());
+    }
+}
@@ -1,6 +1,12 @@
 //! RDMA Globally Unique Identifier (GUID) type with serialization support
 //!
 //! The GUID is a 64-bit identifier that uniquely identifies an RDMA device.
+//!
+//! GUIDs are big-endian on the wire and in sysfs: the first byte is the most
+//! significant and is printed first. [`Guid::from_bytes`] and
+//! [`Guid::to_bytes`] use that order; [`Guid::as_host_u64`] gives the
+//! numeric value and [`Guid::as_network_u64`] the raw `__be64` as returned by
+//! `ibv_get_device_guid`.
 
 use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -8,26 +14,57 @@ use std::borrow::Cow;
 
 /// Globally Unique Identifier for RDMA devices
 #[repr(transparent)]
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub struct Guid(u64);
 
 impl Guid {
-    /// Creates a GUID from a u64 value in big-endian (network) byte order.
-    /// The provided value is assumed to already be in the internal representation
-    /// format and is stored as-is without additional byte-order conversion.
+    /// Creates a GUID from a raw `__be64`, as returned by `ibv_get_device_guid`.
+    ///
+    /// The value is stored as-is. To build a GUID from its numeric value,
+    /// pass `value.to_be()`, or use [`Guid::from_bytes`].
     pub fn from_be(guid: u64) -> Self {
         Self(guid)
     }
 
-    /// Converts GUID from network byte order to host byte order
-    fn as_u64(&self) -> u64 {
+    /// Creates a GUID from its bytes in big-endian (network) order.
+    ///
+    /// `bytes[0]` is the most significant byte, printed first.
+    pub fn from_bytes(bytes: [u8; 8]) -> Self {
+        Self(u64::from_ne_bytes(bytes))
+    }
+
+    /// Returns the GUID bytes in big-endian (network) order.
+    pub fn to_bytes(&self) -> [u8; 8] {
+        self.0.to_ne_bytes()
+    }
+
+    /// Returns the numeric value of the GUID in host byte order.
+    pub fn as_host_u64(&self) -> u64 {
         u64::from_be(self.0)
     }
+
+    /// Returns the GUID as a raw `__be64`, the inverse of [`Guid::from_be`].
+    pub fn as_network_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl PartialOrd for Guid {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Guid {
+    /// Orders GUIDs by their numeric value, matching the displayed order.
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_host_u64().cmp(&other.as_host_u64())
+    }
 }
 
 impl std::fmt::Display for Guid {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let guid = self.as_u64();
+        let guid = self.as_host_u64();
         write!(
             f,
             "{:04x}:{:04x}:{:04x}:{:04x}",
@@ -98,6 +135,21 @@ mod tests {
         assert_eq!(format!("{}", guid), "506b:0b03:0039:e8a4");
     }
 
+    #[test]
+    fn test_guid_byte_layout() {
+        let bytes = [0x50, 0x6b, 0x0b, 0x03, 0x00, 0x39, 0xe8, 0xa4];
+        let guid = Guid::from_bytes(bytes);
+        assert_eq!(guid.to_string(), "506b:0b03:0039:e8a4");
+        assert_eq!(guid.to_bytes(), bytes);
+        assert_eq!(guid.as_host_u64(), 0x506b0b03_0039e8a4);
+        assert_eq!(guid.as_network_u64().to_ne_bytes(), bytes);
+        assert_eq!(Guid::from_be(guid.as_network_u64()), guid);
+        assert_eq!(Guid::from_be(0x506b0b03_0039e8a4u64.to_be()), guid);
+
+        let parsed: Guid = serde_json::from_str("\"506b:0b03:0039:e8a4\"").unwrap();
+        assert_eq!(parsed.to_bytes(), bytes);
+    }
+
     #[test]
     fn test_guid_debug() {
         let guid = Guid(u64::to_be(0x12345678_9ABCDEF0));
@@ -105,6 +157,16 @@ mod tests {
         assert_eq!(debug_str, "1234:5678:9abc:def0");
     }
 
+    #[test]
+    fn test_guid_schema_pattern() {
+        let schema = serde_json::to_value(schemars::schema_for!(Guid)).unwrap();
+        let pattern = regex::Regex::new(schema["pattern"].as_str().unwrap()).unwrap();
+        for value in [0, 0x506b0b03_0039e8a4, 0x0000_00ff_0000_0001, u64::MAX] {
+            let json = serde_json::to_value(Guid::from_be(value.to_be())).unwrap();
+            assert!(pattern.is_match(json.as_str().unwrap()), "{json}");
+        }
+    }
+
     #[test]
     fn test_guid_serialize() {
         let guid = Guid(u64::to_be(0xAABBCCDD_EEFF1122));
@@ -149,6 +211,14 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_guid_ord() {
+        let low = Guid(u64::to_be(0x00000000_000000ff));
+        let high = Guid(u64::to_be(0x01000000_00000000));
+        assert!(low < high);
+        assert_eq!(low.cmp(&low), std::cmp::Ordering::Equal);
+    }
+
     #[test]
     fn test_guid_case_insensitive() {
         let json = "\"ABCD:EF01:2345:6789\"";
@@ -5,10 +5,17 @@
 //!
 //! ## Module Organization
 //!
+//! - [`access_flags`]: Memory region access flag presets per buffer role
+//! - [`device_cap_flags`]: Device capability flags serialized as names
 //! - [`fw_ver`]: Firmware version wrapper for null-terminated strings
 //! - [`gid`]: Global Identifier (GID) with IPv6 conversion
 //! - [`guid`]: Globally Unique Identifier with colon-separated formatting
 //! - [`link_layer`]: Link layer type (InfiniBand/Ethernet)
+//! - [`mtu`]: Path MTU serialized as a byte count
+//! - [`named_flags`]: Shared name-based serialization of the flag bitfields
+//! - [`port_cap_flags`]: Port capability flags serialized as names
+//! - [`port_state`]: Port state serialized as a short name
+//! - [`transport_type`]: Transport type (InfiniBand/iWARP/usNIC)
 //! - [`wrid`]: Work Request ID with type encoding
 //! - [`wc`]: Work completion helper methods
 //! - [`pthread`]: pthread wrapper types for RDMA bindings
@@ -20,11 +27,19 @@
 //! - JSON Schema generation via schemars
 //! - Custom display and debug formatting
 
+mod access_flags;
+
+mod named_flags;
+
+mod device_cap_flags;
+
 mod fw_ver;
 pub use fw_ver::FwVer;
 
 mod gid;
+
 mod wc;
+pub use wc::{imm_from_wire, imm_to_wire};
 
 pub mod guid;
 pub use guid::Guid;
@@ -32,6 +47,17 @@ pub use guid::Guid;
 mod link_layer;
 pub use link_layer::LinkLayer;
 
+mod mtu;
+pub use mtu::Mtu;
+
+mod port_cap_flags;
+
+mod port_state;
+pub use port_state::PortState;
+
+mod transport_type;
+pub use transport_type::TransportType;
+
 mod pthread;
 pub use pthread::{pthread_cond_t, pthread_mutex_t};
 
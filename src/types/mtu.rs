@@ -0,0 +1,169 @@
+//! RDMA path MTU type with byte-count serialization
+//!
+//! Wraps `ibv_mtu` so it serializes as the MTU in bytes (e.g. `4096`)
+//! instead of the raw enum variant.
+
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+
+use crate::ibv_mtu;
+
+/// Path MTU of a port
+///
+/// Serializes as the byte count. Deserialization also accepts the `ibv_mtu`
+/// variant names and numeric enum values (1 to 5).
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Mtu(pub ibv_mtu);
+
+impl Mtu {
+    /// Returns the MTU in bytes
+    pub fn bytes(&self) -> u32 {
+        match self.0 {
+            ibv_mtu::IBV_MTU_256 => 256,
+            ibv_mtu::IBV_MTU_512 => 512,
+            ibv_mtu::IBV_MTU_1024 => 1024,
+            ibv_mtu::IBV_MTU_2048 => 2048,
+            ibv_mtu::IBV_MTU_4096 => 4096,
+        }
+    }
+
+    /// Creates an MTU from a byte count, if it is a valid path MTU
+    pub fn from_bytes(bytes: u32) -> Option<Self> {
+        let mtu = match bytes {
+            256 => ibv_mtu::IBV_MTU_256,
+            512 => ibv_mtu::IBV_MTU_512,
+            1024 => ibv_mtu::IBV_MTU_1024,
+            2048 => ibv_mtu::IBV_MTU_2048,
+            4096 => ibv_mtu::IBV_MTU_4096,
+            _ => return None,
+        };
+        Some(Self(mtu))
+    }
+
+    /// Converts a byte count or a numeric `ibv_mtu` value
+    fn from_u64(value: u64) -> Option<Self> {
+        let mtu = match value {
+            1 => ibv_mtu::IBV_MTU_256,
+            2 => ibv_mtu::IBV_MTU_512,
+            3 => ibv_mtu::IBV_MTU_1024,
+            4 => ibv_mtu::IBV_MTU_2048,
+            5 => ibv_mtu::IBV_MTU_4096,
+            _ => return Self::from_bytes(u32::try_from(value).ok()?),
+        };
+        Some(Self(mtu))
+    }
+
+    /// Parses an `IBV_MTU_<bytes>` name or a decimal byte count
+    fn from_name(name: &str) -> Option<Self> {
+        let bytes = name.strip_prefix("IBV_MTU_").unwrap_or(name);
+        Self::from_bytes(bytes.parse().ok()?)
+    }
+}
+
+impl Default for Mtu {
+    fn default() -> Self {
+        Self(ibv_mtu::IBV_MTU_1024)
+    }
+}
+
+impl From<ibv_mtu> for Mtu {
+    fn from(mtu: ibv_mtu) -> Self {
+        Self(mtu)
+    }
+}
+
+impl From<Mtu> for ibv_mtu {
+    fn from(mtu: Mtu) -> Self {
+        mtu.0
+    }
+}
+
+impl PartialEq<ibv_mtu> for Mtu {
+    fn eq(&self, other: &ibv_mtu) -> bool {
+        self.0 == *other
+    }
+}
+
+impl std::fmt::Display for Mtu {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.bytes())
+    }
+}
+
+impl Serialize for Mtu {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u32(self.bytes())
+    }
+}
+
+impl<'de> Deserialize<'de> for Mtu {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Number(u64),
+            Name(String),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Number(value) => Self::from_u64(value)
+                .ok_or_else(|| D::Error::custom(format!("invalid MTU {value}"))),
+            Repr::Name(name) => Self::from_name(&name)
+                .ok_or_else(|| D::Error::custom(format!("invalid MTU {name:?}"))),
+        }
+    }
+}
+
+impl JsonSchema for Mtu {
+    fn schema_name() -> Cow<'static, str> {
+        "Mtu".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "integer",
+            "enum": [256, 512, 1024, 2048, 4096]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mtu_serialize() {
+        let mtu = Mtu(ibv_mtu::IBV_MTU_4096);
+        assert_eq!(serde_json::to_string(&mtu).unwrap(), "4096");
+        assert_eq!(mtu.to_string(), "4096");
+    }
+
+    #[test]
+    fn test_mtu_deserialize() {
+        for json in ["2048", "4", "\"IBV_MTU_2048\"", "\"2048\""] {
+            let mtu: Mtu = serde_json::from_str(json).unwrap();
+            assert_eq!(mtu, ibv_mtu::IBV_MTU_2048, "{json}");
+        }
+        for json in ["0", "1500", "\"IBV_MTU_9000\""] {
+            assert!(serde_json::from_str::<Mtu>(json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn test_mtu_bytes_roundtrip() {
+        for bytes in [256, 512, 1024, 2048, 4096] {
+            assert_eq!(Mtu::from_bytes(bytes).unwrap().bytes(), bytes);
+        }
+        assert!(Mtu::from_bytes(1500).is_none());
+    }
+}
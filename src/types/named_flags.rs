@@ -0,0 +1,125 @@
+//! Named serialization shared by the capability flag bitfields
+//!
+//! [`named_flags!`] implements `names`, `unknown_bits`, `from_name`,
+//! `Serialize`, `Deserialize` and `JsonSchema` for a bindgen bitfield from
+//! its table of flag names, so every flags type serializes the same way.
+
+/// Implements named serialization for a bitfield type.
+///
+/// `$names` is a `&[(&str, $ty)]` table in bit order, `$schema` the JSON
+/// Schema name and `$what` the noun used in messages, e.g.
+/// `"port capability"`. Flags serialize as the list of set names, with any
+/// unknown bits appended as one `0x`-prefixed hex string; a list of names
+/// or a plain integer deserializes.
+macro_rules! named_flags {
+    ($ty:ident, $names:ident, $schema:literal, $what:literal) => {
+        impl $ty {
+            /// Returns the names of all known flags that are set.
+            pub fn names(&self) -> Vec<&'static str> {
+                $names
+                    .iter()
+                    .filter(|(_, flag)| self.0 & flag.0 != 0)
+                    .map(|(name, _)| *name)
+                    .collect()
+            }
+
+            /// Returns the bits that don't correspond to any known flag name.
+            pub fn unknown_bits(&self) -> u32 {
+                $names
+                    .iter()
+                    .fold(self.0, |bits, (_, flag)| bits & !flag.0)
+            }
+
+            /// Parses a single flag name, or a `0x`-prefixed hex value for unknown bits.
+            pub fn from_name(name: &str) -> Option<Self> {
+                if let Some(hex) = name.strip_prefix("0x") {
+                    return u32::from_str_radix(hex, 16).ok().map(Self);
+                }
+                $names
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, flag)| *flag)
+            }
+        }
+
+        impl ::serde::Serialize for $ty {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: ::serde::Serializer,
+            {
+                use ::serde::ser::SerializeSeq;
+
+                let names = self.names();
+                let unknown = self.unknown_bits();
+                let len = names.len() + usize::from(unknown != 0);
+                let mut seq = serializer.serialize_seq(Some(len))?;
+                for name in names {
+                    seq.serialize_element(name)?;
+                }
+                if unknown != 0 {
+                    seq.serialize_element(&format!("{unknown:#x}"))?;
+                }
+                seq.end()
+            }
+        }
+
+        impl<'de> ::serde::Deserialize<'de> for $ty {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: ::serde::Deserializer<'de>,
+            {
+                use ::serde::de;
+
+                struct FlagsVisitor;
+
+                impl<'de> de::Visitor<'de> for FlagsVisitor {
+                    type Value = $ty;
+
+                    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        f.write_str(concat!("a list of ", $what, " flag names or an integer"))
+                    }
+
+                    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+                        u32::try_from(value)
+                            .map($ty)
+                            .map_err(|_| E::custom(concat!($what, " flags out of range")))
+                    }
+
+                    fn visit_seq<A: de::SeqAccess<'de>>(
+                        self,
+                        mut seq: A,
+                    ) -> Result<Self::Value, A::Error> {
+                        let mut flags = $ty(0);
+                        while let Some(name) = seq.next_element::<String>()? {
+                            flags |= $ty::from_name(&name).ok_or_else(|| {
+                                de::Error::custom(format!(
+                                    concat!("unknown ", $what, " flag: {}"),
+                                    name
+                                ))
+                            })?;
+                        }
+                        Ok(flags)
+                    }
+                }
+
+                deserializer.deserialize_any(FlagsVisitor)
+            }
+        }
+
+        impl ::schemars::JsonSchema for $ty {
+            fn schema_name() -> std::borrow::Cow<'static, str> {
+                $schema.into()
+            }
+
+            fn json_schema(_generator: &mut ::schemars::SchemaGenerator) -> ::schemars::Schema {
+                ::schemars::json_schema!({
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "description": concat!("Set ", $what, " flag names")
+                })
+            }
+        }
+    };
+}
+
+pub(crate) use named_flags;
@@ -0,0 +1,100 @@
+//! Port capability flags with named serialization
+//!
+//! `ibv_port_cap_flags` is a bitfield; this module serializes it as the
+//! list of set flag names (e.g. `["CM", "IP_BASED_GIDS"]`) so port
+//! capabilities are readable in JSON output. `IP_BASED_GIDS` marks a RoCE
+//! port whose GIDs are derived from its IP addresses.
+
+use super::named_flags::named_flags;
+use crate::ibv_port_cap_flags;
+
+/// Flag names without the `IBV_PORT_` prefix and `_SUP` suffix, in bit
+/// order.
+const FLAG_NAMES: &[(&str, ibv_port_cap_flags)] = &[
+    ("SM", ibv_port_cap_flags::IBV_PORT_SM),
+    ("NOTICE", ibv_port_cap_flags::IBV_PORT_NOTICE_SUP),
+    ("TRAP", ibv_port_cap_flags::IBV_PORT_TRAP_SUP),
+    ("OPT_IPD", ibv_port_cap_flags::IBV_PORT_OPT_IPD_SUP),
+    ("AUTO_MIGR", ibv_port_cap_flags::IBV_PORT_AUTO_MIGR_SUP),
+    ("SL_MAP", ibv_port_cap_flags::IBV_PORT_SL_MAP_SUP),
+    ("MKEY_NVRAM", ibv_port_cap_flags::IBV_PORT_MKEY_NVRAM),
+    ("PKEY_NVRAM", ibv_port_cap_flags::IBV_PORT_PKEY_NVRAM),
+    ("LED_INFO", ibv_port_cap_flags::IBV_PORT_LED_INFO_SUP),
+    (
+        "SYS_IMAGE_GUID",
+        ibv_port_cap_flags::IBV_PORT_SYS_IMAGE_GUID_SUP,
+    ),
+    (
+        "PKEY_SW_EXT_PORT_TRAP",
+        ibv_port_cap_flags::IBV_PORT_PKEY_SW_EXT_PORT_TRAP_SUP,
+    ),
+    (
+        "EXTENDED_SPEEDS",
+        ibv_port_cap_flags::IBV_PORT_EXTENDED_SPEEDS_SUP,
+    ),
+    ("CAP_MASK2", ibv_port_cap_flags::IBV_PORT_CAP_MASK2_SUP),
+    ("CM", ibv_port_cap_flags::IBV_PORT_CM_SUP),
+    ("SNMP_TUNNEL", ibv_port_cap_flags::IBV_PORT_SNMP_TUNNEL_SUP),
+    ("REINIT", ibv_port_cap_flags::IBV_PORT_REINIT_SUP),
+    ("DEVICE_MGMT", ibv_port_cap_flags::IBV_PORT_DEVICE_MGMT_SUP),
+    (
+        "VENDOR_CLASS",
+        ibv_port_cap_flags::IBV_PORT_VENDOR_CLASS_SUP,
+    ),
+    ("DR_NOTICE", ibv_port_cap_flags::IBV_PORT_DR_NOTICE_SUP),
+    (
+        "CAP_MASK_NOTICE",
+        ibv_port_cap_flags::IBV_PORT_CAP_MASK_NOTICE_SUP,
+    ),
+    ("BOOT_MGMT", ibv_port_cap_flags::IBV_PORT_BOOT_MGMT_SUP),
+    (
+        "LINK_LATENCY",
+        ibv_port_cap_flags::IBV_PORT_LINK_LATENCY_SUP,
+    ),
+    ("CLIENT_REG", ibv_port_cap_flags::IBV_PORT_CLIENT_REG_SUP),
+    ("IP_BASED_GIDS", ibv_port_cap_flags::IBV_PORT_IP_BASED_GIDS),
+];
+
+named_flags!(
+    ibv_port_cap_flags,
+    FLAG_NAMES,
+    "PortCapFlags",
+    "port capability"
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_port_cap_flags_names() {
+        let flags =
+            ibv_port_cap_flags::IBV_PORT_CM_SUP | ibv_port_cap_flags::IBV_PORT_IP_BASED_GIDS;
+        assert_eq!(flags.names(), vec!["CM", "IP_BASED_GIDS"]);
+        assert_eq!(flags.unknown_bits(), 0);
+        // Bit 0 is reserved
+        assert_eq!(ibv_port_cap_flags(1).unknown_bits(), 1);
+    }
+
+    #[test]
+    fn test_port_cap_flags_serde() {
+        let flags = ibv_port_cap_flags::IBV_PORT_IP_BASED_GIDS
+            | ibv_port_cap_flags::IBV_PORT_CM_SUP
+            | ibv_port_cap_flags(1 << 31);
+        let json = serde_json::to_string(&flags).unwrap();
+        assert_eq!(json, r#"["CM","IP_BASED_GIDS","0x80000000"]"#);
+        let der: ibv_port_cap_flags = serde_json::from_str(&json).unwrap();
+        assert_eq!(der, flags);
+
+        // Integers from older reports are still accepted
+        let der: ibv_port_cap_flags = serde_json::from_str("65538").unwrap();
+        assert_eq!(
+            der,
+            ibv_port_cap_flags::IBV_PORT_SM | ibv_port_cap_flags::IBV_PORT_CM_SUP
+        );
+        assert_eq!(serde_json::to_string(&ibv_port_cap_flags(0)).unwrap(), "[]");
+
+        let result: Result<ibv_port_cap_flags, _> = serde_json::from_str(r#"["NOT_A_FLAG"]"#);
+        assert!(result.is_err());
+    }
+}
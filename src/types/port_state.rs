@@ -0,0 +1,159 @@
+//! RDMA port state type with readable serialization
+//!
+//! Wraps `ibv_port_state` so it serializes as a short name such as `"ACTIVE"`
+//! instead of the raw enum variant.
+
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+
+use crate::ibv_port_state;
+
+/// Logical port state
+///
+/// Serializes as `"NOP"`, `"DOWN"`, `"INIT"`, `"ARMED"`, `"ACTIVE"` or
+/// `"ACTIVE_DEFER"`. Deserialization also accepts the `ibv_port_state`
+/// variant names and numeric values.
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct PortState(pub ibv_port_state);
+
+impl PortState {
+    /// Returns the short name of this state
+    pub fn as_str(&self) -> &'static str {
+        match self.0 {
+            ibv_port_state::IBV_PORT_NOP => "NOP",
+            ibv_port_state::IBV_PORT_DOWN => "DOWN",
+            ibv_port_state::IBV_PORT_INIT => "INIT",
+            ibv_port_state::IBV_PORT_ARMED => "ARMED",
+            ibv_port_state::IBV_PORT_ACTIVE => "ACTIVE",
+            ibv_port_state::IBV_PORT_ACTIVE_DEFER => "ACTIVE_DEFER",
+        }
+    }
+
+    /// Parses a short name, with or without the `IBV_PORT_` prefix
+    fn from_name(name: &str) -> Option<Self> {
+        let state = match name.strip_prefix("IBV_PORT_").unwrap_or(name) {
+            "NOP" => ibv_port_state::IBV_PORT_NOP,
+            "DOWN" => ibv_port_state::IBV_PORT_DOWN,
+            "INIT" => ibv_port_state::IBV_PORT_INIT,
+            "ARMED" => ibv_port_state::IBV_PORT_ARMED,
+            "ACTIVE" => ibv_port_state::IBV_PORT_ACTIVE,
+            "ACTIVE_DEFER" => ibv_port_state::IBV_PORT_ACTIVE_DEFER,
+            _ => return None,
+        };
+        Some(Self(state))
+    }
+
+    /// Converts the numeric `ibv_port_state` value
+    fn from_u64(value: u64) -> Option<Self> {
+        let state = match value {
+            0 => ibv_port_state::IBV_PORT_NOP,
+            1 => ibv_port_state::IBV_PORT_DOWN,
+            2 => ibv_port_state::IBV_PORT_INIT,
+            3 => ibv_port_state::IBV_PORT_ARMED,
+            4 => ibv_port_state::IBV_PORT_ACTIVE,
+            5 => ibv_port_state::IBV_PORT_ACTIVE_DEFER,
+            _ => return None,
+        };
+        Some(Self(state))
+    }
+}
+
+impl Default for PortState {
+    fn default() -> Self {
+        Self(ibv_port_state::IBV_PORT_NOP)
+    }
+}
+
+impl From<ibv_port_state> for PortState {
+    fn from(state: ibv_port_state) -> Self {
+        Self(state)
+    }
+}
+
+impl From<PortState> for ibv_port_state {
+    fn from(state: PortState) -> Self {
+        state.0
+    }
+}
+
+impl PartialEq<ibv_port_state> for PortState {
+    fn eq(&self, other: &ibv_port_state) -> bool {
+        self.0 == *other
+    }
+}
+
+impl std::fmt::Display for PortState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for PortState {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for PortState {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Number(u64),
+            Name(String),
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Number(value) => Self::from_u64(value)
+                .ok_or_else(|| D::Error::custom(format!("invalid port state {value}"))),
+            Repr::Name(name) => Self::from_name(&name)
+                .ok_or_else(|| D::Error::custom(format!("invalid port state {name:?}"))),
+        }
+    }
+}
+
+impl JsonSchema for PortState {
+    fn schema_name() -> Cow<'static, str> {
+        "PortState".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "enum": ["NOP", "DOWN", "INIT", "ARMED", "ACTIVE", "ACTIVE_DEFER"]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_port_state_serialize() {
+        let state = PortState(ibv_port_state::IBV_PORT_ACTIVE);
+        assert_eq!(serde_json::to_string(&state).unwrap(), "\"ACTIVE\"");
+        assert_eq!(state.to_string(), "ACTIVE");
+    }
+
+    #[test]
+    fn test_port_state_deserialize() {
+        for json in ["\"DOWN\"", "\"IBV_PORT_DOWN\"", "1"] {
+            let state: PortState = serde_json::from_str(json).unwrap();
+            assert_eq!(state, ibv_port_state::IBV_PORT_DOWN, "{json}");
+        }
+        for json in ["\"UP\"", "6"] {
+            assert!(serde_json::from_str::<PortState>(json).is_err(), "{json}");
+        }
+    }
+}
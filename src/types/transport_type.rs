@@ -0,0 +1,141 @@
+//! RDMA transport type with serialization support
+//!
+//! The transport type tells which verbs semantics a device implements:
+//! - InfiniBand: IB and RoCE devices, addressed by GIDs
+//! - iWARP: RDMA over TCP, addressed by IP through the connection manager
+//! - usNIC: Cisco user-space NIC, UD only
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Transport type of an RDMA device
+///
+/// Corresponds to the `ibv_transport_type` enum from libibverbs:
+/// - IBV_TRANSPORT_UNKNOWN = -1
+/// - IBV_TRANSPORT_IB = 0
+/// - IBV_TRANSPORT_IWARP = 1
+/// - IBV_TRANSPORT_USNIC = 2
+/// - IBV_TRANSPORT_USNIC_UDP = 3
+/// - IBV_TRANSPORT_UNSPECIFIED = 4
+///
+/// RoCE devices report `InfiniBand`; tell them apart by the port
+/// [`LinkLayer`](crate::LinkLayer).
+#[repr(i8)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize, JsonSchema)]
+pub enum TransportType {
+    /// Transport unknown to libibverbs
+    #[default]
+    Unknown = -1,
+    /// InfiniBand transport, also used by RoCE
+    InfiniBand = 0,
+    /// iWARP (RDMA over TCP)
+    Iwarp = 1,
+    /// Cisco usNIC over raw Ethernet
+    Usnic = 2,
+    /// Cisco usNIC over UDP
+    UsnicUdp = 3,
+    /// Transport not specified by the provider
+    Unspecified = 4,
+}
+
+impl TransportType {
+    /// Creates a TransportType from a raw `ibv_transport_type` value
+    ///
+    /// Returns `TransportType::Unknown` for unknown values
+    pub const fn from_raw(value: i32) -> Self {
+        match value {
+            0 => Self::InfiniBand,
+            1 => Self::Iwarp,
+            2 => Self::Usnic,
+            3 => Self::UsnicUdp,
+            4 => Self::Unspecified,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// Returns the string representation of this transport type
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Unknown => "Unknown",
+            Self::InfiniBand => "InfiniBand",
+            Self::Iwarp => "iWARP",
+            Self::Usnic => "usNIC",
+            Self::UsnicUdp => "usNIC/UDP",
+            Self::Unspecified => "Unspecified",
+        }
+    }
+
+    /// Returns true if this is the InfiniBand transport (IB or RoCE)
+    pub fn is_infiniband(&self) -> bool {
+        matches!(self, Self::InfiniBand)
+    }
+
+    /// Returns true if this is the iWARP transport
+    pub fn is_iwarp(&self) -> bool {
+        matches!(self, Self::Iwarp)
+    }
+}
+
+impl std::fmt::Display for TransportType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ibv_transport_type;
+
+    #[test]
+    fn test_transport_type_from_raw() {
+        let cases = [
+            (
+                ibv_transport_type::IBV_TRANSPORT_UNKNOWN,
+                TransportType::Unknown,
+            ),
+            (
+                ibv_transport_type::IBV_TRANSPORT_IB,
+                TransportType::InfiniBand,
+            ),
+            (
+                ibv_transport_type::IBV_TRANSPORT_IWARP,
+                TransportType::Iwarp,
+            ),
+            (
+                ibv_transport_type::IBV_TRANSPORT_USNIC,
+                TransportType::Usnic,
+            ),
+            (
+                ibv_transport_type::IBV_TRANSPORT_USNIC_UDP,
+                TransportType::UsnicUdp,
+            ),
+            (
+                ibv_transport_type::IBV_TRANSPORT_UNSPECIFIED,
+                TransportType::Unspecified,
+            ),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(TransportType::from_raw(raw as i32), expected);
+            assert_eq!(expected as i32, raw as i32);
+        }
+        assert_eq!(TransportType::from_raw(99), TransportType::Unknown);
+    }
+
+    #[test]
+    fn test_transport_type_display() {
+        assert_eq!(TransportType::InfiniBand.to_string(), "InfiniBand");
+        assert_eq!(TransportType::Iwarp.to_string(), "iWARP");
+        assert_eq!(TransportType::UsnicUdp.to_string(), "usNIC/UDP");
+        assert!(TransportType::Iwarp.is_iwarp());
+        assert!(!TransportType::Iwarp.is_infiniband());
+    }
+
+    #[test]
+    fn test_transport_type_serialize_deserialize() {
+        let json = serde_json::to_string(&TransportType::Iwarp).unwrap();
+        assert_eq!(json, "\"Iwarp\"");
+        let deserialized: TransportType = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, TransportType::Iwarp);
+    }
+}
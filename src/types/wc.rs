@@ -2,11 +2,34 @@
 //!
 //! Provides type-safe helper methods for checking work completion status
 //! and extracting completion data.
+//!
+//! ## Immediate data byte order
+//!
+//! Immediate data travels in network (big-endian) byte order: verbs store
+//! `imm_data` in work requests and completions exactly as it appears on the
+//! wire. Senders convert with [`imm_to_wire`] and receivers with
+//! [`imm_from_wire`], which [`ibv_wc::imm`] and `SendWr::imm_data` already
+//! do, so both peers see the same value regardless of host endianness. The
+//! `wr_id` is local to the posting side and is never byte-swapped.
+//!
+//! Peers following another convention, e.g. senders writing host-order
+//! values straight into `imm_data`, can read the stored value unconverted
+//! with [`ibv_wc::imm_raw`].
 
-use crate::WCType;
+use crate::{Error, ErrorKind, WCType};
 
 pub use crate::{ibv_wc, ibv_wc_flags, ibv_wc_status};
 
+/// Converts immediate data from host to wire (big-endian) byte order.
+pub fn imm_to_wire(value: u32) -> u32 {
+    value.to_be()
+}
+
+/// Converts immediate data from wire (big-endian) to host byte order.
+pub fn imm_from_wire(wire: u32) -> u32 {
+    u32::from_be(wire)
+}
+
 impl ibv_wc {
     /// Checks if this work completion is for a receive operation
     pub fn is_recv(&self) -> bool {
@@ -30,13 +53,39 @@ impl ibv_wc {
         self.status == ibv_wc_status::IBV_WC_SUCCESS
     }
 
+    /// Converts a failed completion into an [`Error`]
+    ///
+    /// Returns None on success, otherwise an error of kind
+    /// [`ErrorKind::WorkCompletionFailed`] whose message names the status
+    /// and the vendor error, so a failed completion can be propagated with
+    /// `?` via `wc.to_error().map_or(Ok(()), Err)?`
+    pub fn to_error(&self) -> Option<Error> {
+        if self.succ() {
+            return None;
+        }
+        Some(Error::new(
+            ErrorKind::WorkCompletionFailed,
+            format!("{:?} (vendor_err {:#x})", self.status, self.vendor_err),
+        ))
+    }
+
     /// Extracts immediate data from this work completion
     ///
-    /// Returns Some with the immediate data value if the IBV_WC_WITH_IMM
-    /// flag is set, otherwise returns None
+    /// Returns Some with the immediate data value in host byte order if the
+    /// IBV_WC_WITH_IMM flag is set, otherwise returns None. The value is
+    /// assumed to be sent big-endian, see [`imm_to_wire`]
     pub fn imm(&self) -> Option<u32> {
+        self.imm_raw().map(imm_from_wire)
+    }
+
+    /// Extracts immediate data exactly as stored in the completion
+    ///
+    /// Unlike [`imm`](Self::imm), no byte-order conversion is applied, so
+    /// this is the wire value as the provider reported it. Use it when the
+    /// sender does not follow the big-endian convention
+    pub fn imm_raw(&self) -> Option<u32> {
         if ibv_wc_flags(self.wc_flags) & ibv_wc_flags::IBV_WC_WITH_IMM != ibv_wc_flags(0) {
-            Some(u32::from_be(unsafe { self.__bindgen_anon_1.imm_data }))
+            Some(unsafe { self.__bindgen_anon_1.imm_data })
         } else {
             None
         }
@@ -55,3 +104,56 @@ impl std::fmt::Debug for ibv_wc {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_imm_wire_roundtrip() {
+        for value in [0, 1, 0x0102_0304, u32::MAX] {
+            assert_eq!(imm_from_wire(imm_to_wire(value)), value);
+            assert_eq!(imm_to_wire(value).to_ne_bytes(), value.to_be_bytes());
+
+            let mut wc = ibv_wc {
+                wc_flags: ibv_wc_flags::IBV_WC_WITH_IMM.0,
+                ..Default::default()
+            };
+            wc.__bindgen_anon_1.imm_data = imm_to_wire(value);
+            assert_eq!(wc.imm(), Some(value));
+        }
+        assert_eq!(ibv_wc::default().imm(), None);
+    }
+
+    #[test]
+    fn test_imm_raw() {
+        let mut wc = ibv_wc {
+            wc_flags: ibv_wc_flags::IBV_WC_WITH_IMM.0,
+            ..Default::default()
+        };
+        wc.__bindgen_anon_1.imm_data = u32::from_ne_bytes([0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(wc.imm(), Some(0x0102_0304));
+        assert_eq!(
+            wc.imm_raw(),
+            Some(u32::from_ne_bytes([0x01, 0x02, 0x03, 0x04]))
+        );
+        assert_eq!(wc.imm_raw().map(u32::from_be), wc.imm());
+        assert_eq!(ibv_wc::default().imm_raw(), None);
+    }
+
+    #[test]
+    fn test_wc_to_error() {
+        let wc = ibv_wc::default();
+        assert!(wc.succ());
+        assert!(wc.to_error().is_none());
+
+        let wc = ibv_wc {
+            status: ibv_wc_status::IBV_WC_REM_ACCESS_ERR,
+            vendor_err: 0x88,
+            ..Default::default()
+        };
+        let err = wc.to_error().unwrap();
+        assert_eq!(err.kind, ErrorKind::WorkCompletionFailed);
+        assert_eq!(err.msg, "IBV_WC_REM_ACCESS_ERR (vendor_err 0x88)");
+    }
+}
@@ -3,6 +3,8 @@
 //! The WRID (Work Request ID) encodes both a type and an ID in a single
 //! 64-bit value for efficient work completion matching.
 
+use crate::{Error, ErrorKind, Result};
+
 /// Work completion ID with type information
 #[repr(transparent)]
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -26,12 +28,37 @@ impl WRID {
     /// Mask to extract type bits from WRID
     pub const TYPE_MASK: u64 = ((1 << (u64::BITS - Self::TYPE_BITS)) - 1) << Self::TYPE_BITS;
 
+    /// Number of ID bits holding the sequence number in stream packing
+    pub const SEQ_BITS: u32 = 32;
+    /// Largest stream ID that fits beside the sequence number and type bits
+    pub const MAX_STREAM_ID: u32 = (1 << (Self::TYPE_BITS - Self::SEQ_BITS)) - 1;
+
     /// Creates a new WRID with the specified type and ID
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` overlaps the type bits. Use [`WRID::try_new`] for
+    /// user-supplied IDs.
     pub fn new(wc_type: WCType, id: u64) -> Self {
         assert!(id & Self::TYPE_MASK == 0, "ID too large");
         Self(((wc_type as u64) << Self::TYPE_BITS) | id)
     }
 
+    /// Creates a new WRID, returning an error instead of panicking
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::WRIDOverflow`] if `id` overlaps the type bits.
+    pub fn try_new(wc_type: WCType, id: u64) -> Result<Self> {
+        if id & Self::TYPE_MASK != 0 {
+            return Err(Error::new(
+                ErrorKind::WRIDOverflow,
+                format!("id {id:#x} exceeds {} bits", Self::TYPE_BITS),
+            ));
+        }
+        Ok(Self(((wc_type as u64) << Self::TYPE_BITS) | id))
+    }
+
     /// Creates a WRID for a receive operation
     pub fn recv(id: u64) -> Self {
         Self::new(WCType::Recv, id)
@@ -61,6 +88,54 @@ impl WRID {
     pub fn get_id(&self) -> u64 {
         self.0 & !Self::TYPE_MASK
     }
+
+    /// Creates a WRID whose ID packs a stream ID and a sequence number
+    ///
+    /// The ID portion is `stream_id << 32 | seq`: the sequence number takes
+    /// the low 32 bits and the stream ID the remaining 30 bits below the type
+    /// bits. Read them back with [`WRID::get_stream_id`] and [`WRID::get_seq`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `stream_id` exceeds [`WRID::MAX_STREAM_ID`]. Use
+    /// [`WRID::try_new_stream`] for user-supplied IDs.
+    pub fn new_stream(wc_type: WCType, stream_id: u32, seq: u32) -> Self {
+        assert!(stream_id <= Self::MAX_STREAM_ID, "stream ID too large");
+        Self::new(wc_type, Self::pack_stream(stream_id, seq))
+    }
+
+    /// Creates a stream-packed WRID, returning an error instead of panicking
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::WRIDOverflow`] if `stream_id` exceeds
+    /// [`WRID::MAX_STREAM_ID`].
+    pub fn try_new_stream(wc_type: WCType, stream_id: u32, seq: u32) -> Result<Self> {
+        if stream_id > Self::MAX_STREAM_ID {
+            return Err(Error::new(
+                ErrorKind::WRIDOverflow,
+                format!(
+                    "stream id {stream_id:#x} exceeds {} bits",
+                    Self::TYPE_BITS - Self::SEQ_BITS
+                ),
+            ));
+        }
+        Self::try_new(wc_type, Self::pack_stream(stream_id, seq))
+    }
+
+    /// Returns the stream ID of a WRID created with [`WRID::new_stream`]
+    pub fn get_stream_id(&self) -> u32 {
+        (self.get_id() >> Self::SEQ_BITS) as u32
+    }
+
+    /// Returns the sequence number of a WRID created with [`WRID::new_stream`]
+    pub fn get_seq(&self) -> u32 {
+        self.get_id() as u32
+    }
+
+    fn pack_stream(stream_id: u32, seq: u32) -> u64 {
+        ((stream_id as u64) << Self::SEQ_BITS) | seq as u64
+    }
 }
 
 impl std::fmt::Debug for WRID {
@@ -73,6 +148,12 @@ impl std::fmt::Debug for WRID {
     }
 }
 
+impl std::fmt::Display for WRID {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -125,6 +206,19 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_wrid_try_new() {
+        let wrid = WRID::try_new(WCType::SendImm, 42).unwrap();
+        assert_eq!(wrid, WRID::send_imm(42));
+
+        let max_id = !WRID::TYPE_MASK;
+        let wrid = WRID::try_new(WCType::Recv, max_id).unwrap();
+        assert_eq!(wrid.get_id(), max_id);
+
+        let err = WRID::try_new(WCType::Recv, 1u64 << 62).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::WRIDOverflow);
+    }
+
     #[test]
     fn test_wrid_debug() {
         let wrid = WRID::recv(123);
@@ -140,6 +234,14 @@ mod tests {
         assert_eq!(debug_str, "SendImm(789)");
     }
 
+    #[test]
+    fn test_wrid_display() {
+        assert_eq!(format!("{}", WRID::recv(5)), "Recv(5)");
+        for wrid in [WRID::recv(123), WRID::send_data(456), WRID::send_imm(789)] {
+            assert_eq!(wrid.to_string(), format!("{wrid:?}"));
+        }
+    }
+
     #[test]
     fn test_wrid_type_mask() {
         let mask = WRID::TYPE_MASK;
@@ -164,4 +266,38 @@ mod tests {
         assert_eq!((value & WRID::TYPE_MASK) >> WRID::TYPE_BITS, 2);
         assert_eq!(value & !WRID::TYPE_MASK, 0x9ABC);
     }
+
+    #[test]
+    fn test_wrid_stream_packing() {
+        let wrid = WRID::new_stream(WCType::SendData, 7, 42);
+        assert_eq!(wrid.get_type(), WCType::SendData);
+        assert_eq!(wrid.get_stream_id(), 7);
+        assert_eq!(wrid.get_seq(), 42);
+        assert_eq!(wrid.get_id(), (7 << 32) | 42);
+    }
+
+    #[test]
+    fn test_wrid_stream_boundaries() {
+        for wc_type in [WCType::Recv, WCType::SendData, WCType::SendImm] {
+            let wrid = WRID::new_stream(wc_type, WRID::MAX_STREAM_ID, u32::MAX);
+            assert_eq!(wrid.get_type(), wc_type);
+            assert_eq!(wrid.get_stream_id(), WRID::MAX_STREAM_ID);
+            assert_eq!(wrid.get_seq(), u32::MAX);
+
+            let wrid = WRID::new_stream(wc_type, 0, 0);
+            assert_eq!(wrid.get_stream_id(), 0);
+            assert_eq!(wrid.get_seq(), 0);
+        }
+        assert_eq!(WRID::MAX_STREAM_ID, (1 << 30) - 1);
+
+        let err = WRID::try_new_stream(WCType::Recv, WRID::MAX_STREAM_ID + 1, 0).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::WRIDOverflow);
+        assert!(WRID::try_new_stream(WCType::Recv, WRID::MAX_STREAM_ID, 0).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "stream ID too large")]
+    fn test_wrid_stream_overflow_panics() {
+        WRID::new_stream(WCType::Recv, u32::MAX, 0);
+    }
 }
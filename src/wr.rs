@@ -0,0 +1,542 @@
+//! Work request builders
+//!
+//! [`SendWr`] owns an `ibv_send_wr` together with its scatter/gather list so
+//! the pointers handed to `ibv_post_send` stay valid for the duration of the
+//! post call. [`post_send_typed`] builds and posts a request from a
+//! [`SendOpcode`], filling the opcode-specific union fields.
+
+use crate::{
+    Error, ErrorKind, Result, WRID, ibv_atomic_cap, ibv_device_attr, ibv_qp, ibv_send_flags,
+    ibv_send_wr, ibv_sge, ibv_wr_opcode,
+};
+
+/// Send opcode with its opcode-specific payload.
+///
+/// Each variant maps to an `ibv_wr_opcode`; immediate data is carried by the
+/// variant so it can't be forgotten.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendOpcode {
+    /// Two-sided send (`IBV_WR_SEND`).
+    Send,
+    /// Send with 32-bit immediate data (`IBV_WR_SEND_WITH_IMM`).
+    SendWithImm(u32),
+    /// One-sided write (`IBV_WR_RDMA_WRITE`).
+    RdmaWrite,
+    /// One-sided write with immediate data (`IBV_WR_RDMA_WRITE_WITH_IMM`).
+    RdmaWriteWithImm(u32),
+    /// One-sided read (`IBV_WR_RDMA_READ`).
+    RdmaRead,
+    /// 64-bit remote fetch-and-add (`IBV_WR_ATOMIC_FETCH_AND_ADD`).
+    ///
+    /// The original remote value is written to the single 8-byte SGE.
+    FetchAdd {
+        /// Value added to the remote word.
+        add: u64,
+    },
+    /// 64-bit remote compare-and-swap (`IBV_WR_ATOMIC_CMP_AND_SWP`).
+    ///
+    /// The original remote value is written to the single 8-byte SGE.
+    CompareSwap {
+        /// Value the remote word is compared with.
+        compare: u64,
+        /// Value stored if the comparison succeeds.
+        swap: u64,
+    },
+}
+
+impl SendOpcode {
+    /// Returns the verbs opcode.
+    pub fn wr_opcode(&self) -> ibv_wr_opcode {
+        match self {
+            Self::Send => ibv_wr_opcode::IBV_WR_SEND,
+            Self::SendWithImm(_) => ibv_wr_opcode::IBV_WR_SEND_WITH_IMM,
+            Self::RdmaWrite => ibv_wr_opcode::IBV_WR_RDMA_WRITE,
+            Self::RdmaWriteWithImm(_) => ibv_wr_opcode::IBV_WR_RDMA_WRITE_WITH_IMM,
+            Self::RdmaRead => ibv_wr_opcode::IBV_WR_RDMA_READ,
+            Self::FetchAdd { .. } => ibv_wr_opcode::IBV_WR_ATOMIC_FETCH_AND_ADD,
+            Self::CompareSwap { .. } => ibv_wr_opcode::IBV_WR_ATOMIC_CMP_AND_SWP,
+        }
+    }
+
+    /// Returns true if the opcode targets remote memory and needs an rkey.
+    pub fn is_rdma(&self) -> bool {
+        matches!(
+            self,
+            Self::RdmaWrite | Self::RdmaWriteWithImm(_) | Self::RdmaRead
+        ) || self.is_atomic()
+    }
+
+    /// Returns true for the atomic opcodes.
+    pub fn is_atomic(&self) -> bool {
+        matches!(self, Self::FetchAdd { .. } | Self::CompareSwap { .. })
+    }
+
+    /// Checks that the device can execute this opcode.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidArgument`] for an atomic opcode if the
+    /// device reports `IBV_ATOMIC_NONE`.
+    pub fn check_device(&self, device_attr: &ibv_device_attr) -> Result<()> {
+        if self.is_atomic() && device_attr.atomic_cap == ibv_atomic_cap::IBV_ATOMIC_NONE {
+            return Err(Error::new(
+                ErrorKind::InvalidArgument,
+                format!("{self:?}: device does not support atomic operations (IBV_ATOMIC_NONE)"),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the immediate data, if the opcode carries any.
+    pub fn imm_data(&self) -> Option<u32> {
+        match *self {
+            Self::SendWithImm(imm) | Self::RdmaWriteWithImm(imm) => Some(imm),
+            _ => None,
+        }
+    }
+}
+
+/// Builder for a single send work request.
+///
+/// # Examples
+///
+/// ```rust
+/// use ruapc_rdma_sys::{SendWr, WRID, ibv_wr_opcode};
+///
+/// let wr = SendWr::new(ibv_wr_opcode::IBV_WR_SEND, WRID::send_data(1))
+///     .sge(0x1000, 64, 0x1234)
+///     .signaled(true)
+///     .inline(true);
+/// assert!(wr.validate(128).is_ok());
+/// assert!(wr.validate(32).is_err());
+/// ```
+pub struct SendWr {
+    wr: ibv_send_wr,
+    sges: Vec<ibv_sge>,
+}
+
+impl SendWr {
+    /// Creates a send work request with the given opcode and WRID.
+    pub fn new(opcode: ibv_wr_opcode, wr_id: WRID) -> Self {
+        let wr = ibv_send_wr {
+            wr_id,
+            opcode,
+            ..Default::default()
+        };
+        Self {
+            wr,
+            sges: Vec::new(),
+        }
+    }
+
+    /// Appends a scatter/gather entry.
+    pub fn sge(self, addr: u64, length: u32, lkey: u32) -> Self {
+        self.push_sge(ibv_sge { addr, length, lkey })
+    }
+
+    /// Appends a prebuilt scatter/gather entry, e.g. from
+    /// [`RegisteredBuffer::sge`](crate::RegisteredBuffer::sge).
+    pub fn push_sge(mut self, sge: ibv_sge) -> Self {
+        self.sges.push(sge);
+        self
+    }
+
+    /// Sets whether the request generates a completion (`IBV_SEND_SIGNALED`).
+    pub fn signaled(self, signaled: bool) -> Self {
+        self.flag(ibv_send_flags::IBV_SEND_SIGNALED, signaled)
+    }
+
+    /// Sets whether the payload is copied inline into the WQE (`IBV_SEND_INLINE`).
+    ///
+    /// Inline sends skip the DMA read of the SGEs, which lowers latency for
+    /// small messages. The payload must fit in the QP's `max_inline_data`,
+    /// which is checked by [`SendWr::validate`].
+    pub fn inline(self, inline: bool) -> Self {
+        self.flag(ibv_send_flags::IBV_SEND_INLINE, inline)
+    }
+
+    /// Replaces all send flags.
+    pub fn flags(mut self, flags: ibv_send_flags) -> Self {
+        self.wr.send_flags = flags.0;
+        self
+    }
+
+    /// Sets the immediate data, in host byte order.
+    ///
+    /// The value is stored with [`imm_to_wire`](crate::imm_to_wire); the
+    /// receiver reads it back with [`ibv_wc::imm`](crate::ibv_wc::imm).
+    pub fn imm_data(mut self, imm: u32) -> Self {
+        self.wr.__bindgen_anon_1.imm_data = crate::imm_to_wire(imm);
+        self
+    }
+
+    /// Sets the remote address and key of an RDMA read or write.
+    pub fn rdma(mut self, remote_addr: u64, rkey: u32) -> Self {
+        self.wr.wr.rdma.remote_addr = remote_addr;
+        self.wr.wr.rdma.rkey = rkey;
+        self
+    }
+
+    /// Sets the remote word and operands of an atomic operation.
+    pub fn atomic(mut self, remote_addr: u64, rkey: u32, compare_add: u64, swap: u64) -> Self {
+        self.wr.wr.atomic.remote_addr = remote_addr;
+        self.wr.wr.atomic.rkey = rkey;
+        self.wr.wr.atomic.compare_add = compare_add;
+        self.wr.wr.atomic.swap = swap;
+        self
+    }
+
+    fn flag(mut self, flag: ibv_send_flags, enable: bool) -> Self {
+        if enable {
+            self.wr.send_flags |= flag.0;
+        } else {
+            self.wr.send_flags &= !flag.0;
+        }
+        self
+    }
+
+    /// Returns true if `IBV_SEND_INLINE` is set.
+    pub fn is_inline(&self) -> bool {
+        self.wr.send_flags & ibv_send_flags::IBV_SEND_INLINE.0 != 0
+    }
+
+    /// Returns the total payload length across all SGEs.
+    pub fn payload_len(&self) -> usize {
+        self.sges.iter().map(|sge| sge.length as usize).sum()
+    }
+
+    /// Validates the request against the QP's `max_inline_data`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ErrorKind::InvalidArgument`] if the request is inline and
+    /// its payload exceeds `max_inline_data`.
+    pub fn validate(&self, max_inline_data: u32) -> Result<()> {
+        let len = self.payload_len();
+        if self.is_inline() && len > max_inline_data as usize {
+            return Err(Error::new(
+                ErrorKind::InvalidArgument,
+                format!("inline payload of {len} bytes exceeds max_inline_data {max_inline_data}"),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns a pointer to the underlying `ibv_send_wr` with its SGE list attached.
+    ///
+    /// The pointer is only valid while `self` is neither moved nor modified.
+    pub fn as_mut_ptr(&mut self) -> *mut ibv_send_wr {
+        self.wr.sg_list = self.sges.as_mut_ptr();
+        self.wr.num_sge = self.sges.len() as _;
+        &mut self.wr
+    }
+
+    /// Validates and posts this request to a queue pair.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if validation fails, or an error classified by
+    /// [`ErrorKind::from_post_errno`] if `ibv_post_send` fails.
+    ///
+    /// # Safety
+    ///
+    /// `qp` must be a valid queue pair, and every SGE must reference memory
+    /// registered with the QP's protection domain that stays valid until the
+    /// request completes.
+    pub unsafe fn post(&mut self, qp: *mut ibv_qp, max_inline_data: u32) -> Result<()> {
+        self.validate(max_inline_data)?;
+        unsafe { self.post_unchecked(qp) }
+    }
+
+    /// Posts this request without validation.
+    ///
+    /// On failure the error message names the WRID of the rejected request.
+    unsafe fn post_unchecked(&mut self, qp: *mut ibv_qp) -> Result<()> {
+        let mut bad_wr: *mut ibv_send_wr = std::ptr::null_mut();
+        let ret = unsafe { crate::ibv_post_send(qp, self.as_mut_ptr(), &mut bad_wr) };
+        if ret == 0 {
+            return Ok(());
+        }
+        let mut err = Error::from_errno(unsafe { crate::ffi::post_send_error_kind(qp, ret) }, ret);
+        if !bad_wr.is_null() {
+            err.msg = format!("{} (bad_wr: {:?})", err.msg, unsafe { (*bad_wr).wr_id });
+        }
+        Err(err)
+    }
+}
+
+/// Builds and posts a single send work request.
+///
+/// `sges` are `(addr, length, lkey)` triples. `remote` is the
+/// `(remote_addr, rkey)` target, required for RDMA and atomic opcodes and
+/// rejected for sends, so the `wr.rdma`/`wr.atomic` union is always filled
+/// when it is read. Atomics must target an 8-byte aligned remote word and
+/// take a single 8-byte SGE, and are checked against `device_attr` with
+/// [`SendOpcode::check_device`] before anything is posted.
+///
+/// # Errors
+///
+/// Returns [`ErrorKind::InvalidArgument`] if the device can't execute the
+/// opcode or `remote` or `sges` don't match it, or an error classified by
+/// [`ErrorKind::from_post_errno`] naming the rejected WRID if
+/// `ibv_post_send` fails.
+///
+/// # Safety
+///
+/// `qp` must be a valid queue pair, and every SGE and the remote range must
+/// reference registered memory that stays valid until the request completes.
+pub unsafe fn post_send_typed(
+    qp: *mut ibv_qp,
+    device_attr: &ibv_device_attr,
+    opcode: SendOpcode,
+    wr_id: WRID,
+    sges: &[(u64, u32, u32)],
+    flags: ibv_send_flags,
+    remote: Option<(u64, u32)>,
+) -> Result<()> {
+    opcode.check_device(device_attr)?;
+    let mut wr = SendWr::new(opcode.wr_opcode(), wr_id).flags(flags);
+    for &(addr, length, lkey) in sges {
+        wr = wr.sge(addr, length, lkey);
+    }
+    if let Some(imm) = opcode.imm_data() {
+        wr = wr.imm_data(imm);
+    }
+    let invalid = |msg: String| Err(Error::new(ErrorKind::InvalidArgument, msg));
+    match (opcode.is_rdma(), remote) {
+        (true, Some((remote_addr, rkey))) => match opcode {
+            SendOpcode::FetchAdd { add } => {
+                wr = wr.atomic(remote_addr, rkey, add, 0);
+            }
+            SendOpcode::CompareSwap { compare, swap } => {
+                wr = wr.atomic(remote_addr, rkey, compare, swap);
+            }
+            _ => wr = wr.rdma(remote_addr, rkey),
+        },
+        (false, None) => {}
+        (true, None) => return invalid(format!("{opcode:?} requires a remote address and rkey")),
+        (false, Some(_)) => return invalid(format!("{opcode:?} does not take a remote address")),
+    }
+    if opcode.is_atomic() {
+        let (remote_addr, _) = remote.unwrap_or_default();
+        if remote_addr % 8 != 0 {
+            return invalid(format!(
+                "{opcode:?}: remote address {remote_addr:#x} is not 8-byte aligned"
+            ));
+        }
+        if !matches!(sges, [(_, 8, _)]) {
+            return invalid(format!("{opcode:?} requires a single 8-byte SGE"));
+        }
+    }
+    unsafe { wr.post_unchecked(qp) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_wr_inline_flag() {
+        let wr = SendWr::new(ibv_wr_opcode::IBV_WR_SEND, WRID::send_data(1));
+        assert!(!wr.is_inline());
+        let wr = wr.inline(true);
+        assert!(wr.is_inline());
+        let wr = wr.inline(false);
+        assert!(!wr.is_inline());
+    }
+
+    #[test]
+    fn test_send_wr_validate_inline() {
+        let wr = SendWr::new(ibv_wr_opcode::IBV_WR_SEND, WRID::send_data(1))
+            .sge(0x1000, 40, 1)
+            .sge(0x2000, 24, 1)
+            .inline(true);
+        assert_eq!(wr.payload_len(), 64);
+        assert!(wr.validate(64).is_ok());
+
+        let err = wr.validate(63).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidArgument);
+    }
+
+    #[test]
+    fn test_send_wr_validate_not_inline() {
+        let wr = SendWr::new(ibv_wr_opcode::IBV_WR_SEND, WRID::send_data(1)).sge(0x1000, 4096, 1);
+        assert!(wr.validate(0).is_ok());
+    }
+
+    #[test]
+    fn test_send_opcode() {
+        assert_eq!(SendOpcode::Send.wr_opcode(), ibv_wr_opcode::IBV_WR_SEND);
+        assert_eq!(
+            SendOpcode::RdmaWriteWithImm(1).wr_opcode(),
+            ibv_wr_opcode::IBV_WR_RDMA_WRITE_WITH_IMM
+        );
+        assert!(SendOpcode::RdmaRead.is_rdma());
+        assert!(!SendOpcode::SendWithImm(1).is_rdma());
+        assert_eq!(SendOpcode::SendWithImm(7).imm_data(), Some(7));
+        assert_eq!(SendOpcode::RdmaWrite.imm_data(), None);
+    }
+
+    #[test]
+    fn test_send_wr_rdma_and_imm() {
+        let mut wr = SendWr::new(ibv_wr_opcode::IBV_WR_RDMA_WRITE_WITH_IMM, WRID::send_imm(1))
+            .rdma(0xdead_0000, 0x55)
+            .imm_data(0x0102_0304);
+        let raw = unsafe { &*wr.as_mut_ptr() };
+        unsafe {
+            assert_eq!(raw.wr.rdma.remote_addr, 0xdead_0000);
+            assert_eq!(raw.wr.rdma.rkey, 0x55);
+            assert_eq!(
+                crate::imm_from_wire(raw.__bindgen_anon_1.imm_data),
+                0x0102_0304
+            );
+        }
+    }
+
+    #[test]
+    fn test_post_send_typed_remote_mismatch() {
+        let qp = std::ptr::null_mut();
+        let device_attr = ibv_device_attr::default();
+        let flags = ibv_send_flags::IBV_SEND_SIGNALED;
+        let err = unsafe {
+            post_send_typed(
+                qp,
+                &device_attr,
+                SendOpcode::RdmaWrite,
+                WRID::send_data(1),
+                &[],
+                flags,
+                None,
+            )
+        }
+        .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidArgument);
+
+        let err = unsafe {
+            post_send_typed(
+                qp,
+                &device_attr,
+                SendOpcode::Send,
+                WRID::send_data(1),
+                &[],
+                flags,
+                Some((0x1000, 1)),
+            )
+        }
+        .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidArgument);
+    }
+
+    #[test]
+    fn test_atomic_opcodes() {
+        let fetch_add = SendOpcode::FetchAdd { add: 1 };
+        let cmp_swap = SendOpcode::CompareSwap {
+            compare: 0,
+            swap: 1,
+        };
+        assert_eq!(
+            fetch_add.wr_opcode(),
+            ibv_wr_opcode::IBV_WR_ATOMIC_FETCH_AND_ADD
+        );
+        assert_eq!(
+            cmp_swap.wr_opcode(),
+            ibv_wr_opcode::IBV_WR_ATOMIC_CMP_AND_SWP
+        );
+        assert!(fetch_add.is_atomic() && fetch_add.is_rdma());
+        assert!(!SendOpcode::RdmaRead.is_atomic());
+
+        let mut device_attr = ibv_device_attr {
+            atomic_cap: ibv_atomic_cap::IBV_ATOMIC_NONE,
+            ..Default::default()
+        };
+        let err = cmp_swap.check_device(&device_attr).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidArgument);
+        assert!(SendOpcode::RdmaWrite.check_device(&device_attr).is_ok());
+        device_attr.atomic_cap = ibv_atomic_cap::IBV_ATOMIC_HCA;
+        assert!(cmp_swap.check_device(&device_attr).is_ok());
+    }
+
+    #[test]
+    fn test_send_wr_atomic() {
+        let mut wr = SendWr::new(ibv_wr_opcode::IBV_WR_ATOMIC_CMP_AND_SWP, WRID::send_data(1))
+            .atomic(0x1000, 0x55, 3, 4);
+        let raw = unsafe { &*wr.as_mut_ptr() };
+        unsafe {
+            assert_eq!(raw.wr.atomic.remote_addr, 0x1000);
+            assert_eq!(raw.wr.atomic.rkey, 0x55);
+            assert_eq!(raw.wr.atomic.compare_add, 3);
+            assert_eq!(raw.wr.atomic.swap, 4);
+        }
+    }
+
+    #[test]
+    fn test_post_send_typed_atomic_validation() {
+        let qp = std::ptr::null_mut();
+        let mut device_attr = ibv_device_attr {
+            atomic_cap: ibv_atomic_cap::IBV_ATOMIC_HCA,
+            ..Default::default()
+        };
+        let flags = ibv_send_flags::IBV_SEND_SIGNALED;
+        let opcode = SendOpcode::FetchAdd { add: 1 };
+        let wr_id = WRID::send_data(1);
+
+        let err = unsafe {
+            post_send_typed(
+                qp,
+                &device_attr,
+                opcode,
+                wr_id,
+                &[(0x2000, 8, 1)],
+                flags,
+                Some((0x1004, 2)),
+            )
+        }
+        .unwrap_err();
+        assert!(err.msg.contains("aligned"));
+
+        let err = unsafe {
+            post_send_typed(
+                qp,
+                &device_attr,
+                opcode,
+                wr_id,
+                &[(0x2000, 16, 1)],
+                flags,
+                Some((0x1000, 2)),
+            )
+        }
+        .unwrap_err();
+        assert!(err.msg.contains("8-byte SGE"));
+
+        // Rejected before the QP is touched
+        device_attr.atomic_cap = ibv_atomic_cap::IBV_ATOMIC_NONE;
+        let err = unsafe {
+            post_send_typed(
+                qp,
+                &device_attr,
+                opcode,
+                wr_id,
+                &[(0x2000, 8, 1)],
+                flags,
+                Some((0x1000, 2)),
+            )
+        }
+        .unwrap_err();
+        assert!(err.msg.contains("IBV_ATOMIC_NONE"));
+    }
+
+    #[test]
+    fn test_send_wr_as_mut_ptr() {
+        let mut wr = SendWr::new(ibv_wr_opcode::IBV_WR_SEND, WRID::send_data(7))
+            .sge(0x1000, 16, 2)
+            .signaled(true);
+        let ptr = wr.as_mut_ptr();
+        let raw = unsafe { &*ptr };
+        assert_eq!(raw.num_sge, 1);
+        assert_eq!(raw.wr_id, WRID::send_data(7));
+        assert_eq!(
+            raw.send_flags & ibv_send_flags::IBV_SEND_SIGNALED.0,
+            ibv_send_flags::IBV_SEND_SIGNALED.0
+        );
+    }
+}